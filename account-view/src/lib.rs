@@ -58,9 +58,9 @@ pub struct RuntimeAccount {
 
     /// Padding for alignment.
     ///
-    /// The value of this field is not directly used and always set to `0`.
-    /// Entrypoint implementations may use this space for their own purposes,
-    /// e.g., to track account resizing.
+    /// The runtime always sets this field to `0`. [`AccountView::resize`]
+    /// uses it to track the change in data length during the instruction,
+    /// stored as a little-endian `i32`.
     pub padding: [u8; 4],
 
     /// Address of the account.
@@ -378,6 +378,66 @@ impl AccountView {
         write_bytes(self.data_mut_ptr().sub(48), 0, 48);
     }
 
+    /// Return the change in data length since the start of the instruction.
+    #[inline(always)]
+    pub fn resize_delta(&self) -> i32 {
+        // SAFETY: The `raw` pointer is guaranteed to be valid.
+        i32::from_le_bytes(unsafe { (*self.raw).padding })
+    }
+
+    /// Resize the account's data: Either truncating or zero extending.
+    ///
+    /// Account data can be increased by up to [`MAX_PERMITTED_DATA_INCREASE`]
+    /// bytes in total over its length at the start of the instruction.
+    ///
+    /// The account data must not be borrowed, since resizing invalidates
+    /// existing references to it.
+    #[inline]
+    pub fn resize(&mut self, new_len: usize) -> ProgramResult {
+        self.check_borrow_mut()?;
+
+        // SAFETY: There are no active borrows on the account data.
+        unsafe { self.resize_unchecked(new_len) }
+    }
+
+    /// Resize the account's data: Either truncating or zero extending.
+    ///
+    /// # Safety
+    ///
+    /// This method is unsafe because it does not check if the account data is
+    /// already borrowed. It should only be called when there are no active
+    /// references to the account data.
+    ///
+    /// It also assumes that the account data is followed by the
+    /// [`MAX_PERMITTED_DATA_INCREASE`] bytes reserved by the runtime, so it
+    /// should only be called for instances of `AccountView` that were
+    /// created by the runtime and received in the `process_instruction`
+    /// entrypoint of a program.
+    pub unsafe fn resize_unchecked(&mut self, new_len: usize) -> ProgramResult {
+        let old_len = self.data_len();
+
+        // Return early if length hasn't changed
+        if new_len == old_len {
+            return Ok(());
+        }
+
+        // The delta is relative to the length at the start of the instruction,
+        // so an account shrunk earlier can grow back by more than the limit.
+        let delta = i64::from(self.resize_delta())
+            .checked_add(new_len as i64 - old_len as i64)
+            .filter(|delta| *delta <= MAX_PERMITTED_DATA_INCREASE as i64)
+            .ok_or(ProgramError::InvalidRealloc)?;
+
+        (*self.raw).data_len = new_len as u64;
+        (*self.raw).padding = (delta as i32).to_le_bytes();
+
+        if new_len > old_len {
+            write_bytes(self.data_mut_ptr().add(old_len), 0, new_len - old_len);
+        }
+
+        Ok(())
+    }
+
     /// Returns a raw pointer to the `RuntimeAccount` struct.
     pub fn account_ptr(&self) -> *const RuntimeAccount {
         self.raw as *const _
@@ -746,4 +806,51 @@ mod tests {
         let borrow_state = unsafe { (*account_view.raw).borrow_state };
         assert!(borrow_state == NOT_BORROWED);
     }
+
+    #[test]
+    fn test_resize() {
+        // 8-bytes aligned account data + 8 bytes of data followed by the
+        // space reserved for resizing.
+        let mut data = [0u64;
+            size_of::<RuntimeAccount>() / size_of::<u64>()
+                + 1
+                + MAX_PERMITTED_DATA_INCREASE / size_of::<u64>()];
+        data[0] = NOT_BORROWED as u64;
+
+        let account = data.as_mut_ptr() as *mut RuntimeAccount;
+        unsafe { (*account).data_len = 8 };
+        let mut account_view = AccountView { raw: account };
+        unsafe { account_view.borrow_unchecked_mut() }.fill(1);
+
+        // Shrinking and growing back zero extends the data.
+        account_view.resize(4).unwrap();
+        assert_eq!(account_view.resize_delta(), -4);
+        account_view.resize(8).unwrap();
+        assert_eq!(
+            unsafe { account_view.borrow_unchecked() },
+            [1, 1, 1, 1, 0, 0, 0, 0]
+        );
+
+        // Growth is limited relative to the original length.
+        assert_eq!(
+            account_view.resize(8 + MAX_PERMITTED_DATA_INCREASE + 1),
+            Err(ProgramError::InvalidRealloc)
+        );
+        account_view
+            .resize(8 + MAX_PERMITTED_DATA_INCREASE)
+            .unwrap();
+        assert_eq!(
+            account_view.resize_delta(),
+            MAX_PERMITTED_DATA_INCREASE as i32
+        );
+        account_view.resize(0).unwrap();
+        assert_eq!(account_view.resize_delta(), -8);
+
+        // The data cannot be resized while borrowed.
+        unsafe { (*account).borrow_state = NOT_BORROWED - 1 };
+        assert_eq!(
+            account_view.resize(8),
+            Err(ProgramError::AccountBorrowFailed)
+        );
+    }
 }
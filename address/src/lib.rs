@@ -16,6 +16,11 @@ pub mod error;
 mod hasher;
 #[cfg(any(feature = "curve25519", feature = "syscalls"))]
 pub mod syscalls;
+#[cfg(all(
+    feature = "vanity",
+    not(any(target_os = "solana", target_arch = "bpf"))
+))]
+pub mod vanity;
 
 #[cfg(feature = "sha2")]
 use crate::error::AddressError;
@@ -0,0 +1,556 @@
+//! Multi-threaded search for addresses matching a base58 pattern.
+//!
+//! A [`VanityPattern`] describes a base58 prefix and/or suffix that an
+//! address should have. The search functions in this module derive candidate
+//! addresses from a sequence of 32-byte values and test them against the
+//! pattern on several threads until one matches, the search is cancelled or
+//! the attempt budget is exhausted.
+//!
+//! Candidate `i` is derived from `sha256(seed || i)`, where `seed` is either
+//! random or supplied through [`SearchOptions::deterministic`]. In the
+//! deterministic mode the search always returns the match with the lowest
+//! index, regardless of the number of threads, which makes results
+//! reproducible in tests.
+//!
+//! Searching for keypairs lives in the `solana-keypair` crate, which builds on
+//! the generic [`search`] function.
+
+use {
+    crate::{error::AddressError, Address, MAX_SEEDS, MAX_SEED_LEN},
+    core::{fmt, num::NonZeroUsize},
+    std::{
+        string::String,
+        sync::{
+            atomic::{AtomicBool, AtomicU64, Ordering},
+            Mutex,
+        },
+        thread,
+        time::{Duration, Instant},
+    },
+};
+
+/// The base58 alphabet used to encode addresses.
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Maximum string length of a base58 encoded address.
+const MAX_BASE58_LEN: usize = 44;
+
+/// Number of candidates a thread claims at a time.
+const BATCH_SIZE: u64 = 256;
+
+/// Length of the extra seed appended to the seeds of a program address search.
+pub const PROGRAM_ADDRESS_SEARCH_SEED_LEN: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VanityError {
+    /// The pattern has neither a prefix nor a suffix.
+    EmptyPattern,
+    /// The pattern is longer than a base58 encoded address.
+    PatternTooLong,
+    /// The pattern contains a character that can never appear in an address.
+    InvalidCharacter(char),
+    /// The requested seed length is zero or exceeds [`MAX_SEED_LEN`].
+    InvalidSeedLength(usize),
+    /// The base, owner or seeds cannot produce an address.
+    Address(AddressError),
+    /// The search was cancelled before a match was found.
+    Cancelled,
+    /// The maximum number of attempts was reached without a match.
+    Exhausted(u64),
+}
+
+impl core::error::Error for VanityError {}
+
+impl fmt::Display for VanityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VanityError::EmptyPattern => f.write_str("pattern has no prefix or suffix"),
+            VanityError::PatternTooLong => {
+                f.write_str("pattern is longer than a base58 encoded address")
+            }
+            VanityError::InvalidCharacter(c) => {
+                write!(f, "pattern contains non-base58 character {c:?}")
+            }
+            VanityError::InvalidSeedLength(len) => write!(f, "invalid seed length {len}"),
+            VanityError::Address(error) => write!(f, "{error}"),
+            VanityError::Cancelled => f.write_str("search was cancelled"),
+            VanityError::Exhausted(attempts) => {
+                write!(f, "no match found after {attempts} attempts")
+            }
+        }
+    }
+}
+
+impl From<AddressError> for VanityError {
+    fn from(error: AddressError) -> Self {
+        VanityError::Address(error)
+    }
+}
+
+/// A base58 prefix and/or suffix that a matching address must have.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VanityPattern {
+    prefix: String,
+    suffix: String,
+    ignore_case: bool,
+}
+
+impl VanityPattern {
+    /// Creates a pattern from a prefix and a suffix, either of which may be
+    /// empty.
+    ///
+    /// When `ignore_case` is set, letters match regardless of their case, so
+    /// characters such as `l` that are not part of the base58 alphabet are
+    /// accepted as long as their other case is.
+    pub fn new(prefix: &str, suffix: &str, ignore_case: bool) -> Result<Self, VanityError> {
+        if prefix.is_empty() && suffix.is_empty() {
+            return Err(VanityError::EmptyPattern);
+        }
+        if prefix.len().saturating_add(suffix.len()) > MAX_BASE58_LEN {
+            return Err(VanityError::PatternTooLong);
+        }
+        if let Some(c) = prefix
+            .chars()
+            .chain(suffix.chars())
+            .find(|c| !is_valid_char(*c, ignore_case))
+        {
+            return Err(VanityError::InvalidCharacter(c));
+        }
+
+        let normalize = |s: &str| {
+            if ignore_case {
+                s.to_ascii_lowercase()
+            } else {
+                String::from(s)
+            }
+        };
+        Ok(Self {
+            prefix: normalize(prefix),
+            suffix: normalize(suffix),
+            ignore_case,
+        })
+    }
+
+    /// Creates a case-sensitive pattern matching addresses that start with
+    /// `prefix`.
+    pub fn starts_with(prefix: &str) -> Result<Self, VanityError> {
+        Self::new(prefix, "", false)
+    }
+
+    /// Creates a case-sensitive pattern matching addresses that end with
+    /// `suffix`.
+    pub fn ends_with(suffix: &str) -> Result<Self, VanityError> {
+        Self::new("", suffix, false)
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    pub fn suffix(&self) -> &str {
+        &self.suffix
+    }
+
+    pub fn ignore_case(&self) -> bool {
+        self.ignore_case
+    }
+
+    /// Returns whether the base58 encoding of `address` matches the pattern.
+    pub fn matches(&self, address: &Address) -> bool {
+        let mut buffer = [0u8; MAX_BASE58_LEN];
+        let len = five8::encode_32(address.as_array(), &mut buffer) as usize;
+        let encoded = &buffer[..len];
+
+        let eq = |a: &[u8], b: &[u8]| {
+            if self.ignore_case {
+                a.eq_ignore_ascii_case(b)
+            } else {
+                a == b
+            }
+        };
+        let prefix = self.prefix.as_bytes();
+        let suffix = self.suffix.as_bytes();
+        encoded.len() >= prefix.len()
+            && encoded.len() >= suffix.len()
+            && eq(&encoded[..prefix.len()], prefix)
+            && eq(&encoded[encoded.len() - suffix.len()..], suffix)
+    }
+}
+
+fn is_valid_char(c: char, ignore_case: bool) -> bool {
+    let in_alphabet = |c: char| c.is_ascii() && BASE58_ALPHABET.contains(&(c as u8));
+    if ignore_case {
+        in_alphabet(c.to_ascii_lowercase()) || in_alphabet(c.to_ascii_uppercase())
+    } else {
+        in_alphabet(c)
+    }
+}
+
+/// Progress of a running search, reported to [`SearchOptions::on_progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchProgress {
+    /// Number of candidates tested so far across all threads.
+    pub attempts: u64,
+    /// Time elapsed since the search started.
+    pub elapsed: Duration,
+}
+
+/// Options controlling how a search is run.
+#[derive(Clone, Copy)]
+pub struct SearchOptions<'a> {
+    threads: NonZeroUsize,
+    seed: Option<[u8; 32]>,
+    max_attempts: Option<u64>,
+    cancel: Option<&'a AtomicBool>,
+    progress: Option<&'a (dyn Fn(SearchProgress) + Sync)>,
+}
+
+impl Default for SearchOptions<'_> {
+    fn default() -> Self {
+        Self {
+            threads: thread::available_parallelism().unwrap_or(NonZeroUsize::MIN),
+            seed: None,
+            max_attempts: None,
+            cancel: None,
+            progress: None,
+        }
+    }
+}
+
+impl fmt::Debug for SearchOptions<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SearchOptions")
+            .field("threads", &self.threads)
+            .field("deterministic", &self.seed.is_some())
+            .field("max_attempts", &self.max_attempts)
+            .field("cancel", &self.cancel)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
+
+impl<'a> SearchOptions<'a> {
+    /// Sets the number of worker threads, which defaults to the available
+    /// parallelism.
+    pub fn threads(mut self, threads: NonZeroUsize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Derives candidates from `seed` instead of a random seed.
+    ///
+    /// The search then returns the same result for the same inputs on every
+    /// run. Anyone who knows the seed can recompute the candidates, so keypair
+    /// searches should only use this mode in tests.
+    pub fn deterministic(mut self, seed: [u8; 32]) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Stops the search with [`VanityError::Exhausted`] after `max_attempts`
+    /// candidates.
+    pub fn max_attempts(mut self, max_attempts: u64) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    /// Stops the search with [`VanityError::Cancelled`] once `cancel` is set.
+    pub fn cancel_flag(mut self, cancel: &'a AtomicBool) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Calls `progress` periodically from the worker threads.
+    pub fn on_progress(mut self, progress: &'a (dyn Fn(SearchProgress) + Sync)) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel
+            .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+    }
+}
+
+/// Searches for a value whose address matches `pattern`.
+///
+/// `candidate` maps the 32 bytes derived for each attempt to an address and
+/// the value returned on a match, or `None` if the bytes do not produce a
+/// usable candidate.
+pub fn search<T, F>(
+    pattern: &VanityPattern,
+    options: &SearchOptions,
+    candidate: F,
+) -> Result<T, VanityError>
+where
+    T: Send,
+    F: Fn(&[u8; 32]) -> Option<(Address, T)> + Sync,
+{
+    let seed = options.seed.unwrap_or_else(rand::random);
+    let limit = options.max_attempts.unwrap_or(u64::MAX);
+    let start = Instant::now();
+
+    let next = AtomicU64::new(0);
+    let attempts = AtomicU64::new(0);
+    // Lowest matching index so far; workers stop claiming candidates past it.
+    let found = AtomicU64::new(u64::MAX);
+    let best = Mutex::new(None::<(u64, T)>);
+
+    let worker = || loop {
+        if options.is_cancelled() {
+            return;
+        }
+        let first = next.fetch_add(BATCH_SIZE, Ordering::Relaxed);
+        if first >= limit || first >= found.load(Ordering::Acquire) {
+            return;
+        }
+        let last = first.saturating_add(BATCH_SIZE).min(limit);
+
+        let mut tested = 0;
+        for index in first..last {
+            if index >= found.load(Ordering::Relaxed) {
+                break;
+            }
+            tested += 1;
+            let bytes = solana_sha256_hasher::hashv(&[&seed, &index.to_le_bytes()]).to_bytes();
+            let Some((address, value)) = candidate(&bytes) else {
+                continue;
+            };
+            if pattern.matches(&address) {
+                let mut best = best.lock().unwrap();
+                if best
+                    .as_ref()
+                    .is_none_or(|(best_index, _)| index < *best_index)
+                {
+                    *best = Some((index, value));
+                    found.fetch_min(index, Ordering::Release);
+                }
+                break;
+            }
+        }
+
+        let total = attempts.fetch_add(tested, Ordering::Relaxed) + tested;
+        if let Some(progress) = options.progress {
+            progress(SearchProgress {
+                attempts: total,
+                elapsed: start.elapsed(),
+            });
+        }
+    };
+
+    thread::scope(|scope| {
+        for _ in 0..options.threads.get() {
+            scope.spawn(worker);
+        }
+    });
+
+    match best.into_inner().unwrap() {
+        Some((_, value)) => Ok(value),
+        None if options.is_cancelled() => Err(VanityError::Cancelled),
+        None => Err(VanityError::Exhausted(attempts.into_inner())),
+    }
+}
+
+/// The result of [`search_create_with_seed`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeedMatch {
+    /// Seed to pass to [`Address::create_with_seed`].
+    pub seed: String,
+    pub address: Address,
+}
+
+/// Searches for a `seed` of `seed_len` base58 characters such that
+/// [`Address::create_with_seed`] with `base` and `owner` matches `pattern`.
+pub fn search_create_with_seed(
+    base: &Address,
+    owner: &Address,
+    seed_len: usize,
+    pattern: &VanityPattern,
+    options: &SearchOptions,
+) -> Result<SeedMatch, VanityError> {
+    if seed_len == 0 || seed_len > MAX_SEED_LEN {
+        return Err(VanityError::InvalidSeedLength(seed_len));
+    }
+    // Surface an illegal owner up front rather than testing every candidate.
+    Address::create_with_seed(base, "", owner)?;
+
+    search(pattern, options, |bytes| {
+        let seed = bytes[..seed_len]
+            .iter()
+            .map(|b| BASE58_ALPHABET[usize::from(*b) % BASE58_ALPHABET.len()] as char)
+            .collect::<String>();
+        let address = Address::create_with_seed(base, &seed, owner).ok()?;
+        Some((address, SeedMatch { seed, address }))
+    })
+}
+
+/// The result of [`search_program_address`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgramAddressMatch {
+    /// Extra seed appended after the caller's seeds.
+    pub seed: [u8; PROGRAM_ADDRESS_SEARCH_SEED_LEN],
+    /// Bump seed returned by [`Address::find_program_address`].
+    pub bump: u8,
+    pub address: Address,
+}
+
+/// Searches for an extra seed such that [`Address::find_program_address`]
+/// with `seeds`, the extra seed and `program_id` matches `pattern`.
+///
+/// The extra seed is appended after `seeds`, so at most `MAX_SEEDS - 2`
+/// seeds may be given to leave room for it and the bump seed.
+pub fn search_program_address(
+    seeds: &[&[u8]],
+    program_id: &Address,
+    pattern: &VanityPattern,
+    options: &SearchOptions,
+) -> Result<ProgramAddressMatch, VanityError> {
+    if seeds.len() > MAX_SEEDS - 2 {
+        return Err(VanityError::Address(AddressError::InvalidSeeds));
+    }
+    if seeds.iter().any(|seed| seed.len() > MAX_SEED_LEN) {
+        return Err(VanityError::Address(AddressError::MaxSeedLengthExceeded));
+    }
+
+    search(pattern, options, |bytes| {
+        let mut seed = [0u8; PROGRAM_ADDRESS_SEARCH_SEED_LEN];
+        seed.copy_from_slice(&bytes[..PROGRAM_ADDRESS_SEARCH_SEED_LEN]);
+
+        let mut all_seeds = [&[][..]; MAX_SEEDS];
+        all_seeds[..seeds.len()].copy_from_slice(seeds);
+        all_seeds[seeds.len()] = &seed;
+        let (address, bump) =
+            Address::try_find_program_address(&all_seeds[..=seeds.len()], program_id)?;
+        Some((
+            address,
+            ProgramAddressMatch {
+                seed,
+                bump,
+                address,
+            },
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, core::sync::atomic::AtomicUsize};
+
+    const SEED: [u8; 32] = [7; 32];
+
+    fn options<'a>(threads: usize) -> SearchOptions<'a> {
+        SearchOptions::default()
+            .threads(NonZeroUsize::new(threads).unwrap())
+            .deterministic(SEED)
+    }
+
+    #[test]
+    fn test_pattern_validation() {
+        assert_eq!(
+            VanityPattern::new("", "", false),
+            Err(VanityError::EmptyPattern)
+        );
+        assert_eq!(
+            VanityPattern::starts_with("0"),
+            Err(VanityError::InvalidCharacter('0'))
+        );
+        assert_eq!(
+            VanityPattern::ends_with("l"),
+            Err(VanityError::InvalidCharacter('l'))
+        );
+        assert_eq!(
+            VanityPattern::starts_with(&"1".repeat(45)),
+            Err(VanityError::PatternTooLong)
+        );
+        // `l` is not base58 but `L` is.
+        assert!(VanityPattern::new("l", "", true).is_ok());
+        assert_eq!(
+            VanityPattern::new("0", "", true),
+            Err(VanityError::InvalidCharacter('0'))
+        );
+    }
+
+    #[test]
+    fn test_pattern_matches() {
+        let address = Address::from_str_const("14grJpemFaf88c8tiVb77W7TYg2W3ir6pfkKz3YjhhZ5");
+        assert!(VanityPattern::starts_with("14gr")
+            .unwrap()
+            .matches(&address));
+        assert!(!VanityPattern::starts_with("14GR")
+            .unwrap()
+            .matches(&address));
+        assert!(VanityPattern::new("14GR", "", true)
+            .unwrap()
+            .matches(&address));
+        assert!(VanityPattern::ends_with("hhZ5").unwrap().matches(&address));
+        assert!(VanityPattern::new("14g", "Z5", false)
+            .unwrap()
+            .matches(&address));
+        assert!(!VanityPattern::new("14g", "z5", false)
+            .unwrap()
+            .matches(&address));
+    }
+
+    #[test]
+    fn test_search_deterministic() {
+        let base = Address::new_from_array([1; 32]);
+        let owner = Address::new_from_array([2; 32]);
+        let pattern = VanityPattern::new("a", "", true).unwrap();
+
+        let single = search_create_with_seed(&base, &owner, 16, &pattern, &options(1)).unwrap();
+        assert!(pattern.matches(&single.address));
+        assert_eq!(
+            Address::create_with_seed(&base, &single.seed, &owner).unwrap(),
+            single.address
+        );
+        for threads in [2, 4, 8] {
+            let multi =
+                search_create_with_seed(&base, &owner, 16, &pattern, &options(threads)).unwrap();
+            assert_eq!(multi, single);
+        }
+
+        let program_id = Address::new_from_array([3; 32]);
+        let pattern = VanityPattern::ends_with("1").unwrap();
+        let single =
+            search_program_address(&[b"vault"], &program_id, &pattern, &options(1)).unwrap();
+        let (address, bump) = Address::find_program_address(&[b"vault", &single.seed], &program_id);
+        assert_eq!(address, single.address);
+        assert_eq!(bump, single.bump);
+        let multi =
+            search_program_address(&[b"vault"], &program_id, &pattern, &options(4)).unwrap();
+        assert_eq!(multi, single);
+    }
+
+    #[test]
+    fn test_search_stops() {
+        let pattern = VanityPattern::starts_with("1111111111").unwrap();
+        let calls = AtomicUsize::new(0);
+        let progress = |progress: SearchProgress| {
+            calls.fetch_add(1, Ordering::Relaxed);
+            assert!(progress.attempts <= 1000);
+        };
+        let result = search(
+            &pattern,
+            &options(2).max_attempts(1000).on_progress(&progress),
+            |bytes| Some((Address::new_from_array(*bytes), ())),
+        );
+        assert_eq!(result, Err(VanityError::Exhausted(1000)));
+        assert!(calls.load(Ordering::Relaxed) > 0);
+
+        let cancel = AtomicBool::new(true);
+        let result = search(&pattern, &options(2).cancel_flag(&cancel), |bytes| {
+            Some((Address::new_from_array(*bytes), ()))
+        });
+        assert_eq!(result, Err(VanityError::Cancelled));
+
+        let owner = Address::new_from_array(*b"0123456789AProgramDerivedAddress");
+        assert_eq!(
+            search_create_with_seed(&owner, &owner, 8, &pattern, &options(1)),
+            Err(VanityError::Address(AddressError::IllegalOwner))
+        );
+        assert_eq!(
+            search_create_with_seed(&owner, &owner, 33, &pattern, &options(1)),
+            Err(VanityError::InvalidSeedLength(33))
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! Aggregate signature certificates.
+//!
+//! A [`Certificate`] pairs an aggregate [`Signature`] with the set of signers
+//! that produced it. Signers are identified by their position in an ordered
+//! validator list and encoded as a Base2 bitmap with `solana-signer-store`,
+//! so a certificate over thousands of validators stays compact.
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wincode")]
+use wincode::{SchemaRead, SchemaWrite};
+use {
+    crate::{
+        error::BlsError,
+        pubkey::{AddToPubkeyProjective, PopVerified, PubkeyProjective, VerifySignature},
+        signature::{
+            AddToSignatureProjective, AsSignatureProjective, Signature, SignatureProjective,
+        },
+    },
+    alloc::vec::Vec,
+    bitvec::prelude::*,
+    solana_signer_store::{
+        decode, encode_base2, DecodeError, Decoded, EncodeError, EncodedSigners, Vector, Version,
+    },
+    thiserror::Error,
+};
+
+#[derive(Error, Debug, Eq, PartialEq)]
+pub enum CertificateError {
+    #[error("Certificate has no signers")]
+    NoSigners,
+    #[error("Signer index {0} is out of range")]
+    SignerOutOfRange(usize),
+    #[error("Duplicate signer index {0}")]
+    DuplicateSigner(usize),
+    #[error("Certificates have overlapping signers")]
+    OverlappingSigners,
+    #[error("Bitmap covers {actual} validators, expected {expected}")]
+    ValidatorCountMismatch { expected: usize, actual: usize },
+    #[error("Signer bitmap is not Base2 encoded")]
+    UnsupportedBitmap,
+    #[error(transparent)]
+    Encode(#[from] EncodeError),
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+    #[error(transparent)]
+    Bls(#[from] BlsError),
+}
+
+/// An aggregate signature together with the bitmap of its signers
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "wincode", derive(SchemaRead, SchemaWrite))]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Certificate {
+    signature: Signature,
+    bitmap: Vec<u8>,
+}
+
+impl Certificate {
+    /// Creates a certificate from an aggregate signature and its signers
+    pub fn new(signature: Signature, signers: &BitVec<u8, Lsb0>) -> Result<Self, CertificateError> {
+        if signers.not_any() {
+            return Err(CertificateError::NoSigners);
+        }
+        Ok(Self {
+            signature,
+            bitmap: encode_base2(signers)?,
+        })
+    }
+
+    /// Builds a certificate from individual votes on the same message
+    ///
+    /// Each vote is the index of the signer in the validator list and its
+    /// signature.
+    pub fn from_votes<'a, S: AddToSignatureProjective + ?Sized + 'a>(
+        validator_count: usize,
+        votes: impl IntoIterator<Item = (usize, &'a S)>,
+    ) -> Result<Self, CertificateError> {
+        let mut signers = BitVec::<u8, Lsb0>::repeat(false, validator_count);
+        let mut aggregate = SignatureProjective::identity();
+        for (index, signature) in votes {
+            let mut signer = signers
+                .get_mut(index)
+                .ok_or(CertificateError::SignerOutOfRange(index))?;
+            if signer.replace(true) {
+                return Err(CertificateError::DuplicateSigner(index));
+            }
+            signature.add_to_accumulator(&mut aggregate)?;
+        }
+        Self::new(aggregate.into(), &signers)
+    }
+
+    /// Returns the aggregate signature
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    /// Returns the encoded signer bitmap
+    pub fn bitmap(&self) -> &[u8] {
+        &self.bitmap
+    }
+
+    /// Decodes the signer bitmap
+    pub fn signers(&self) -> Result<BitVec<u8, Lsb0>, CertificateError> {
+        Self::decode_bitmap(&self.bitmap, usize::from(u16::MAX))
+    }
+
+    fn decode_bitmap(bitmap: &[u8], max_len: usize) -> Result<BitVec<u8, Lsb0>, CertificateError> {
+        match decode(bitmap, max_len)? {
+            Decoded::Base2(signers) => Ok(signers),
+            Decoded::Base3(..) => Err(CertificateError::UnsupportedBitmap),
+        }
+    }
+
+    /// Merges two certificates for the same message with disjoint signers
+    pub fn merge(&self, other: &Certificate) -> Result<Self, CertificateError> {
+        let mut signers = self.signers()?;
+        let other_signers = other.signers()?;
+        if signers.len() != other_signers.len() {
+            return Err(CertificateError::ValidatorCountMismatch {
+                expected: signers.len(),
+                actual: other_signers.len(),
+            });
+        }
+        if signers.iter_ones().any(|index| other_signers[index]) {
+            return Err(CertificateError::OverlappingSigners);
+        }
+        signers |= other_signers;
+
+        let signature = self.signature.try_as_projective()?;
+        let other_signature = other.signature.try_as_projective()?;
+        let aggregate = SignatureProjective::aggregate([signature, other_signature].iter())?;
+        Self::new(aggregate.into(), &signers)
+    }
+
+    /// Verifies the certificate for `message` against the ordered list of
+    /// validator public keys that the bitmap indexes into
+    pub fn verify<P: AddToPubkeyProjective>(
+        &self,
+        validators: &[PopVerified<P>],
+        message: &[u8],
+    ) -> Result<(), CertificateError> {
+        // Walk the bitmap in place rather than decoding it into a `BitVec`.
+        let signers = EncodedSigners::new(&self.bitmap, validators.len())?;
+        if signers.version() != Version::Base2 {
+            return Err(CertificateError::UnsupportedBitmap);
+        }
+        if signers.len() != validators.len() {
+            return Err(CertificateError::ValidatorCountMismatch {
+                expected: validators.len(),
+                actual: signers.len(),
+            });
+        }
+        let pubkey = PubkeyProjective::aggregate(
+            signers
+                .indices(Vector::Base)
+                .map(|index| &validators[index]),
+        )?;
+        pubkey.verify_signature(&self.signature, message)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{keypair::Keypair, pubkey::PubkeyAffine, signature::SignatureCompressed},
+    };
+
+    const MESSAGE: &[u8] = b"vote for slot 42";
+
+    fn validators(count: usize) -> (Vec<Keypair>, Vec<PopVerified<PubkeyAffine>>) {
+        let keypairs: Vec<Keypair> = (0..count).map(|_| Keypair::new()).collect();
+        let pubkeys = keypairs.iter().map(|keypair| keypair.public).collect();
+        (keypairs, pubkeys)
+    }
+
+    #[test]
+    fn test_from_votes_and_verify() {
+        let (keypairs, pubkeys) = validators(10);
+        let votes: Vec<(usize, SignatureCompressed)> = [1, 4, 7, 9]
+            .into_iter()
+            .map(|index| (index, keypairs[index].sign(MESSAGE).into()))
+            .collect();
+        let certificate = Certificate::from_votes(
+            pubkeys.len(),
+            votes.iter().map(|(index, signature)| (*index, signature)),
+        )
+        .unwrap();
+        assert_eq!(
+            certificate
+                .signers()
+                .unwrap()
+                .iter_ones()
+                .collect::<Vec<_>>(),
+            [1, 4, 7, 9]
+        );
+        assert_eq!(certificate.bitmap(), [0, 10, 0, 0b1001_0010, 0b10]);
+        certificate.verify(&pubkeys, MESSAGE).unwrap();
+        assert_eq!(
+            certificate.verify(&pubkeys, b"other message"),
+            Err(CertificateError::Bls(BlsError::VerificationFailed))
+        );
+        assert!(certificate.verify(&pubkeys[..9], MESSAGE).is_err());
+
+        let signature = keypairs[0].sign(MESSAGE);
+        assert_eq!(
+            Certificate::from_votes(10, [(10, &signature)]),
+            Err(CertificateError::SignerOutOfRange(10))
+        );
+        assert_eq!(
+            Certificate::from_votes(10, [(3, &signature), (3, &signature)]),
+            Err(CertificateError::DuplicateSigner(3))
+        );
+        assert_eq!(
+            Certificate::from_votes::<SignatureProjective>(10, []),
+            Err(CertificateError::NoSigners)
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let (keypairs, pubkeys) = validators(6);
+        let signatures: Vec<SignatureProjective> = keypairs
+            .iter()
+            .map(|keypair| keypair.sign(MESSAGE))
+            .collect();
+        let vote = |index: usize| (index, &signatures[index]);
+
+        let first = Certificate::from_votes(6, [vote(0), vote(2)]).unwrap();
+        let second = Certificate::from_votes(6, [vote(3), vote(5)]).unwrap();
+        let merged = first.merge(&second).unwrap();
+        merged.verify(&pubkeys, MESSAGE).unwrap();
+        assert_eq!(
+            merged,
+            Certificate::from_votes(6, [vote(0), vote(2), vote(3), vote(5)]).unwrap()
+        );
+
+        let overlapping = Certificate::from_votes(6, [vote(2), vote(4)]).unwrap();
+        assert_eq!(
+            first.merge(&overlapping),
+            Err(CertificateError::OverlappingSigners)
+        );
+        let shorter = Certificate::from_votes(5, [vote(4)]).unwrap();
+        assert_eq!(
+            first.merge(&shorter),
+            Err(CertificateError::ValidatorCountMismatch {
+                expected: 6,
+                actual: 5
+            })
+        );
+    }
+
+    #[cfg(any(feature = "serde", feature = "wincode"))]
+    fn certificate() -> Certificate {
+        let keypair = Keypair::new();
+        Certificate::from_votes(3, [(1, &keypair.sign(MESSAGE))]).unwrap()
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize_and_deserialize_certificate() {
+        let original = certificate();
+        let serialized = bincode::serialize(&original).unwrap();
+        let deserialized: Certificate = bincode::deserialize(&serialized).unwrap();
+        assert_eq!(original, deserialized);
+    }
+
+    #[cfg(feature = "wincode")]
+    #[test]
+    fn wincode_serialize_and_deserialize_certificate() {
+        let original = certificate();
+        let serialized = wincode::serialize(&original).unwrap();
+        let deserialized: Certificate = wincode::deserialize(&serialized).unwrap();
+        assert_eq!(original, deserialized);
+    }
+}
@@ -35,6 +35,8 @@ pub use crate::{
     },
 };
 
+#[cfg(all(feature = "certificate", not(target_os = "solana")))]
+pub mod certificate;
 pub mod error;
 #[cfg(not(target_os = "solana"))]
 pub mod keypair;
@@ -46,4 +48,8 @@ pub mod proof_of_possession;
 pub mod pubkey;
 #[cfg(not(target_os = "solana"))]
 pub mod secret_key;
+#[cfg(all(feature = "shamir", not(target_os = "solana")))]
+pub mod shamir;
 pub mod signature;
+#[cfg(all(feature = "threshold", not(target_os = "solana")))]
+pub mod threshold;
@@ -0,0 +1,392 @@
+//! Shamir secret sharing of BLS secret keys.
+//!
+//! [`split_secret_key`] splits a [`SecretKey`] into shares, any `threshold` of
+//! which recover it with [`recover_secret_key`]. The secret key is the
+//! constant term of a random polynomial over the scalar field, and each share
+//! is the evaluation of that polynomial at the share index.
+//!
+//! A share is encoded as:
+//!
+//! | Field     | Size | Notes                                           |
+//! |-----------|------|-------------------------------------------------|
+//! | threshold | 1    | number of shares needed to recover the secret   |
+//! | index     | 1    | x coordinate of the share, from 1 to 255        |
+//! | value     | 32   | little-endian scalar                            |
+//! | checksum  | 4    | first bytes of the SHA-256 of the fields above  |
+use {
+    crate::{
+        error::BlsError,
+        pubkey::{AsPubkeyProjective, PubkeyProjective},
+        secret_key::{SecretKey, BLS_SECRET_KEY_SIZE},
+    },
+    alloc::vec::Vec,
+    base64::{prelude::BASE64_STANDARD, Engine},
+    blstrs::Scalar,
+    core::{fmt, str::FromStr},
+    ff::Field,
+    rand::rngs::OsRng,
+    sha2::{Digest, Sha256},
+    thiserror::Error,
+    zeroize::Zeroizing,
+};
+
+const CHECKSUM_LEN: usize = 4;
+
+/// Size of an encoded secret key share in bytes
+pub const BLS_SECRET_KEY_SHARE_SIZE: usize = 2 + BLS_SECRET_KEY_SIZE + CHECKSUM_LEN;
+
+/// Domain separator of the share checksum, so that shares of other kinds of
+/// secrets are rejected.
+const CHECKSUM_DOMAIN: &[u8] = b"solana-bls-secret-key-share";
+
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum ShareError {
+    #[error("Threshold is zero or larger than the number of shares")]
+    InvalidThreshold,
+    #[error("Invalid share length")]
+    InvalidLength,
+    #[error("Invalid share encoding")]
+    InvalidEncoding,
+    #[error("Invalid share checksum")]
+    InvalidChecksum,
+    #[error("Invalid share index")]
+    InvalidIndex,
+    #[error("Share value is not a canonical scalar")]
+    InvalidValue,
+    #[error("Not enough shares to recover the secret key")]
+    NotEnoughShares,
+    #[error("Duplicate share index {0}")]
+    DuplicateIndex(u8),
+    #[error("Shares have different thresholds")]
+    ThresholdMismatch,
+    #[error("Recovered secret key does not match the public key")]
+    PubkeyMismatch,
+    #[error(transparent)]
+    Bls(#[from] BlsError),
+}
+
+/// One share of a BLS secret key
+///
+/// The share value is itself a secret key, whose signatures can be combined
+/// by Lagrange interpolation.
+#[derive(Clone, Eq, PartialEq)]
+pub struct SecretKeyShare {
+    pub(crate) threshold: u8,
+    pub(crate) index: u8,
+    pub(crate) value: SecretKey,
+}
+
+impl fmt::Debug for SecretKeyShare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecretKeyShare")
+            .field("threshold", &self.threshold)
+            .field("index", &self.index)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SecretKeyShare {
+    /// Returns the number of shares needed to recover the secret key
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// Returns the index of the share, from 1 to 255
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    fn checksum(bytes: &[u8]) -> [u8; CHECKSUM_LEN] {
+        let hash = Sha256::new()
+            .chain_update(CHECKSUM_DOMAIN)
+            .chain_update(bytes)
+            .finalize();
+        let mut checksum = [0; CHECKSUM_LEN];
+        checksum.copy_from_slice(&hash[..CHECKSUM_LEN]);
+        checksum
+    }
+
+    /// Encodes the share with its checksum
+    pub fn to_bytes(&self) -> Zeroizing<[u8; BLS_SECRET_KEY_SHARE_SIZE]> {
+        let mut bytes = Zeroizing::new([0; BLS_SECRET_KEY_SHARE_SIZE]);
+        let (body, checksum) = bytes.split_at_mut(BLS_SECRET_KEY_SHARE_SIZE - CHECKSUM_LEN);
+        body[0] = self.threshold;
+        body[1] = self.index;
+        body[2..].copy_from_slice(&self.value.0.to_bytes_le());
+        checksum.copy_from_slice(&Self::checksum(body));
+        bytes
+    }
+
+    /// Decodes a share, verifying its checksum
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ShareError> {
+        let bytes: &[u8; BLS_SECRET_KEY_SHARE_SIZE] =
+            bytes.try_into().map_err(|_| ShareError::InvalidLength)?;
+        let (body, checksum) = bytes.split_at(BLS_SECRET_KEY_SHARE_SIZE - CHECKSUM_LEN);
+        if Self::checksum(body) != checksum {
+            return Err(ShareError::InvalidChecksum);
+        }
+        let (threshold, index) = (body[0], body[1]);
+        if threshold == 0 {
+            return Err(ShareError::InvalidThreshold);
+        }
+        if index == 0 {
+            return Err(ShareError::InvalidIndex);
+        }
+        let value = Zeroizing::new(
+            <[u8; BLS_SECRET_KEY_SIZE]>::try_from(&body[2..])
+                .map_err(|_| ShareError::InvalidLength)?,
+        );
+        let value: Option<Scalar> = Scalar::from_bytes_le(&value).into();
+        Ok(Self {
+            threshold,
+            index,
+            value: SecretKey(value.ok_or(ShareError::InvalidValue)?),
+        })
+    }
+}
+
+/// Displays the share as base64
+impl fmt::Display for SecretKeyShare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&Zeroizing::new(BASE64_STANDARD.encode(*self.to_bytes())))
+    }
+}
+
+impl FromStr for SecretKeyShare {
+    type Err = ShareError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = Zeroizing::new(
+            BASE64_STANDARD
+                .decode(s.trim())
+                .map_err(|_| ShareError::InvalidEncoding)?,
+        );
+        Self::from_bytes(&bytes)
+    }
+}
+
+/// Splits `secret_key` into `shares` shares, any `threshold` of which recover
+/// it
+pub fn split_secret_key(
+    secret_key: &SecretKey,
+    threshold: u8,
+    shares: u8,
+) -> Result<Vec<SecretKeyShare>, ShareError> {
+    if threshold == 0 || threshold > shares {
+        return Err(ShareError::InvalidThreshold);
+    }
+    let coefficients = random_polynomial(secret_key.clone(), threshold);
+    Ok((1..=shares)
+        .map(|index| SecretKeyShare {
+            threshold,
+            index,
+            value: evaluate_polynomial(&coefficients, index),
+        })
+        .collect())
+}
+
+/// Returns a random polynomial of degree `threshold - 1` with `constant` as
+/// its constant term, from the lowest degree coefficient
+pub(crate) fn random_polynomial(constant: SecretKey, threshold: u8) -> Vec<SecretKey> {
+    let mut rng = OsRng;
+    core::iter::once(constant)
+        .chain((1..threshold).map(|_| SecretKey(Scalar::random(&mut rng))))
+        .collect()
+}
+
+/// Evaluates the polynomial with `coefficients`, from the lowest degree
+/// coefficient, at `index`
+#[allow(clippy::arithmetic_side_effects)]
+pub(crate) fn evaluate_polynomial(coefficients: &[SecretKey], index: u8) -> SecretKey {
+    let x = Scalar::from(u64::from(index));
+    // Horner's method, from the highest degree coefficient.
+    SecretKey(
+        coefficients
+            .iter()
+            .rev()
+            .fold(Scalar::ZERO, |acc, coefficient| acc * x + coefficient.0),
+    )
+}
+
+/// Checks that `shares` can be combined and returns the first `threshold` of
+/// them
+pub(crate) fn select_shares<T>(
+    shares: &[T],
+    threshold: impl Fn(&T) -> u8,
+    index: impl Fn(&T) -> u8,
+) -> Result<&[T], ShareError> {
+    let expected = threshold(shares.first().ok_or(ShareError::NotEnoughShares)?);
+    if shares.iter().any(|share| threshold(share) != expected) {
+        return Err(ShareError::ThresholdMismatch);
+    }
+    for (position, share) in shares.iter().enumerate() {
+        if shares[..position]
+            .iter()
+            .any(|other| index(other) == index(share))
+        {
+            return Err(ShareError::DuplicateIndex(index(share)));
+        }
+    }
+    shares
+        .get(..usize::from(expected))
+        .ok_or(ShareError::NotEnoughShares)
+}
+
+/// Returns the Lagrange coefficients that interpolate the polynomial at zero
+/// from its values at the distinct, non-zero `indexes`
+#[allow(clippy::arithmetic_side_effects)]
+pub(crate) fn lagrange_coefficients(indexes: &[u8]) -> Vec<Scalar> {
+    indexes
+        .iter()
+        .map(|&index| {
+            let x = Scalar::from(u64::from(index));
+            let (numerator, denominator) = indexes
+                .iter()
+                .filter(|&&other| other != index)
+                .map(|&other| Scalar::from(u64::from(other)))
+                .fold(
+                    (Scalar::ONE, Scalar::ONE),
+                    |(numerator, denominator), other| {
+                        (numerator * other, denominator * (other - x))
+                    },
+                );
+            // The indexes are distinct, so the denominator is not zero.
+            numerator * denominator.invert().unwrap()
+        })
+        .collect()
+}
+
+/// Recovers a secret key from at least `threshold` of its shares and checks
+/// that it matches `expected_pubkey`
+#[allow(clippy::arithmetic_side_effects)]
+pub fn recover_secret_key<P: AsPubkeyProjective + ?Sized>(
+    shares: &[SecretKeyShare],
+    expected_pubkey: &P,
+) -> Result<SecretKey, ShareError> {
+    let shares = select_shares(shares, SecretKeyShare::threshold, SecretKeyShare::index)?;
+    let indexes: Vec<u8> = shares.iter().map(SecretKeyShare::index).collect();
+    let secret_key = SecretKey(
+        lagrange_coefficients(&indexes)
+            .into_iter()
+            .zip(shares)
+            .fold(Scalar::ZERO, |acc, (coefficient, share)| {
+                acc + coefficient * share.value.0
+            }),
+    );
+
+    let expected_pubkey = expected_pubkey
+        .try_as_projective()
+        .map_err(|_| ShareError::PubkeyMismatch)?;
+    if PubkeyProjective::from_secret(&secret_key) != expected_pubkey {
+        return Err(ShareError::PubkeyMismatch);
+    }
+    Ok(secret_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::pubkey::{Pubkey, PubkeyCompressed},
+        alloc::{string::ToString, vec},
+    };
+
+    #[test]
+    fn test_split_and_recover() {
+        let secret_key = SecretKey::new();
+        let pubkey: Pubkey = PubkeyProjective::from_secret(&secret_key).into();
+        let shares = split_secret_key(&secret_key, 3, 5).unwrap();
+        assert_eq!(shares.len(), 5);
+        assert!(shares.iter().all(|share| share.threshold() == 3));
+        assert_eq!(
+            shares.iter().map(SecretKeyShare::index).collect::<Vec<_>>(),
+            vec![1, 2, 3, 4, 5]
+        );
+
+        for subset in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+            let subset: Vec<_> = subset.iter().map(|i| shares[*i].clone()).collect();
+            assert_eq!(recover_secret_key(&subset, &pubkey).unwrap(), secret_key);
+        }
+        let compressed: PubkeyCompressed = PubkeyProjective::from_secret(&secret_key).into();
+        assert_eq!(
+            recover_secret_key(&shares, &compressed).unwrap(),
+            secret_key
+        );
+        assert_eq!(
+            recover_secret_key(&shares[..2], &pubkey).unwrap_err(),
+            ShareError::NotEnoughShares
+        );
+        let other: Pubkey = PubkeyProjective::from_secret(&SecretKey::new()).into();
+        assert_eq!(
+            recover_secret_key(&shares, &other).unwrap_err(),
+            ShareError::PubkeyMismatch
+        );
+    }
+
+    #[test]
+    fn test_invalid_split_and_recover() {
+        let secret_key = SecretKey::new();
+        let pubkey: Pubkey = PubkeyProjective::from_secret(&secret_key).into();
+        assert_eq!(
+            split_secret_key(&secret_key, 0, 3).unwrap_err(),
+            ShareError::InvalidThreshold
+        );
+        assert_eq!(
+            split_secret_key(&secret_key, 4, 3).unwrap_err(),
+            ShareError::InvalidThreshold
+        );
+
+        let shares = split_secret_key(&secret_key, 2, 3).unwrap();
+        assert_eq!(
+            recover_secret_key(&[shares[1].clone(), shares[1].clone()], &pubkey).unwrap_err(),
+            ShareError::DuplicateIndex(2)
+        );
+        let other = split_secret_key(&secret_key, 3, 3).unwrap();
+        assert_eq!(
+            recover_secret_key(&[shares[0].clone(), other[1].clone()], &pubkey).unwrap_err(),
+            ShareError::ThresholdMismatch
+        );
+    }
+
+    #[test]
+    fn test_encoding() {
+        let secret_key = SecretKey::new();
+        let pubkey: Pubkey = PubkeyProjective::from_secret(&secret_key).into();
+        let shares = split_secret_key(&secret_key, 2, 3).unwrap();
+        let decoded: Vec<SecretKeyShare> = shares
+            .iter()
+            .map(|share| share.to_string().parse().unwrap())
+            .collect();
+        assert_eq!(decoded, shares);
+        assert_eq!(
+            recover_secret_key(&decoded[1..], &pubkey).unwrap(),
+            secret_key
+        );
+
+        let bytes = shares[0].to_bytes();
+        let mut corrupted = *bytes;
+        corrupted[5] ^= 1;
+        assert_eq!(
+            SecretKeyShare::from_bytes(&corrupted).unwrap_err(),
+            ShareError::InvalidChecksum
+        );
+        assert_eq!(
+            SecretKeyShare::from_bytes(&bytes[1..]).unwrap_err(),
+            ShareError::InvalidLength
+        );
+        assert_eq!(
+            "not base64!".parse::<SecretKeyShare>().unwrap_err(),
+            ShareError::InvalidEncoding
+        );
+
+        // A non-canonical scalar with a valid checksum.
+        let mut non_canonical = [0xff; BLS_SECRET_KEY_SHARE_SIZE];
+        non_canonical[..2].copy_from_slice(&[2, 1]);
+        let checksum = SecretKeyShare::checksum(&non_canonical[..BLS_SECRET_KEY_SHARE_SIZE - 4]);
+        non_canonical[BLS_SECRET_KEY_SHARE_SIZE - 4..].copy_from_slice(&checksum);
+        assert_eq!(
+            SecretKeyShare::from_bytes(&non_canonical).unwrap_err(),
+            ShareError::InvalidValue
+        );
+    }
+}
@@ -0,0 +1,373 @@
+//! Threshold BLS signatures.
+//!
+//! Holders of [`SecretKeyShare`]s each sign a message with
+//! [`SecretKeyShare::sign`], and any `threshold` of the resulting
+//! [`PartialSignature`]s are combined by Lagrange interpolation with
+//! [`combine_partial_signatures`]. The combined signature is an ordinary BLS
+//! signature of the group secret key, so it verifies under the group public
+//! key with [`VerifiableSignature::verify`](crate::VerifiableSignature::verify).
+//!
+//! Shares come either from splitting an existing key with
+//! [`split_secret_key`](crate::shamir::split_secret_key) or from
+//! [`simulate_dkg`], which runs a dealerless key generation in process for
+//! testing.
+use {
+    crate::{
+        error::BlsError,
+        hash::{hash_bound_pop_to_projective, HashedMessage},
+        proof_of_possession::ProofOfPossessionProjective,
+        pubkey::{AsPubkeyAffine, PopVerified, PubkeyProjective, VerifyPop},
+        secret_key::SecretKey,
+        shamir::{
+            evaluate_polynomial, lagrange_coefficients, random_polynomial, select_shares,
+            SecretKeyShare, ShareError,
+        },
+        signature::{AsSignatureAffine, SignatureProjective},
+    },
+    alloc::vec::Vec,
+    blstrs::{G1Projective, G2Projective, Scalar},
+    ff::Field,
+    group::Group,
+};
+
+/// A signature by one share of a secret key
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PartialSignature {
+    threshold: u8,
+    index: u8,
+    signature: SignatureProjective,
+}
+
+impl PartialSignature {
+    /// Creates a partial signature from the share's threshold and index
+    pub fn new(
+        threshold: u8,
+        index: u8,
+        signature: SignatureProjective,
+    ) -> Result<Self, ShareError> {
+        if threshold == 0 {
+            return Err(ShareError::InvalidThreshold);
+        }
+        if index == 0 {
+            return Err(ShareError::InvalidIndex);
+        }
+        Ok(Self {
+            threshold,
+            index,
+            signature,
+        })
+    }
+
+    /// Returns the number of partial signatures needed to combine a signature
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// Returns the index of the share that produced the signature
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    /// Returns the signature of the share's secret key
+    pub fn signature(&self) -> &SignatureProjective {
+        &self.signature
+    }
+}
+
+/// The public key of one share of a secret key
+///
+/// Share public keys are derived from the key generation transcript rather
+/// than registered by their holders, so they can verify partial signatures
+/// without a proof of possession.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PubkeyShare {
+    threshold: u8,
+    index: u8,
+    pubkey: PubkeyProjective,
+}
+
+impl PubkeyShare {
+    /// Creates a share public key from the share's threshold and index
+    pub fn new(threshold: u8, index: u8, pubkey: PubkeyProjective) -> Result<Self, ShareError> {
+        if threshold == 0 {
+            return Err(ShareError::InvalidThreshold);
+        }
+        if index == 0 {
+            return Err(ShareError::InvalidIndex);
+        }
+        Ok(Self {
+            threshold,
+            index,
+            pubkey,
+        })
+    }
+
+    /// Returns the number of shares needed to combine a signature
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// Returns the index of the share
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    /// Returns the public key of the share's secret key
+    pub fn pubkey(&self) -> &PubkeyProjective {
+        &self.pubkey
+    }
+
+    /// Verifies a partial signature of `message` by this share
+    pub fn verify(&self, partial: &PartialSignature, message: &[u8]) -> Result<(), BlsError> {
+        if partial.threshold != self.threshold || partial.index != self.index {
+            return Err(BlsError::VerificationFailed);
+        }
+        self.pubkey
+            .try_as_affine()?
+            ._verify_signature(
+                &partial.signature.try_as_affine()?,
+                &HashedMessage::new(message),
+            )
+            .then_some(())
+            .ok_or(BlsError::VerificationFailed)
+    }
+}
+
+impl SecretKeyShare {
+    /// Signs a message with the share
+    pub fn sign(&self, message: &[u8]) -> PartialSignature {
+        PartialSignature {
+            threshold: self.threshold(),
+            index: self.index(),
+            signature: self.value.sign(message),
+        }
+    }
+
+    /// Returns the public key of the share
+    pub fn pubkey_share(&self) -> PubkeyShare {
+        PubkeyShare {
+            threshold: self.threshold(),
+            index: self.index(),
+            pubkey: PubkeyProjective::from_secret(&self.value),
+        }
+    }
+}
+
+/// Combines at least `threshold` partial signatures of the same message into a
+/// signature of the group secret key
+pub fn combine_partial_signatures(
+    partials: &[PartialSignature],
+) -> Result<SignatureProjective, ShareError> {
+    let partials = select_shares(
+        partials,
+        PartialSignature::threshold,
+        PartialSignature::index,
+    )?;
+    let indexes: Vec<u8> = partials.iter().map(PartialSignature::index).collect();
+    let coefficients = lagrange_coefficients(&indexes);
+    Ok(SignatureProjective::aggregate_with_scalars(
+        partials.iter().map(PartialSignature::signature),
+        coefficients.iter(),
+    )?)
+}
+
+/// The result of [`simulate_dkg`]
+#[derive(Debug)]
+pub struct DkgOutput {
+    /// The group public key, whose proof of possession has been verified
+    pub group_pubkey: PopVerified<PubkeyProjective>,
+    /// A proof of possession of the group public key, combined from the shares
+    pub proof_of_possession: ProofOfPossessionProjective,
+    /// The secret key share of each participant, in index order
+    pub shares: Vec<SecretKeyShare>,
+    /// The public key share of each participant, in index order
+    pub pubkey_shares: Vec<PubkeyShare>,
+}
+
+/// Runs a dealerless key generation among `participants` in process
+///
+/// Every participant deals shares of a random secret with a Feldman
+/// commitment to its polynomial, and the group secret key is the sum of the
+/// dealt secrets. The group secret key is never assembled, but every secret
+/// share is returned together, so this is only suitable for testing.
+#[allow(clippy::arithmetic_side_effects)]
+pub fn simulate_dkg(threshold: u8, participants: u8) -> Result<DkgOutput, ShareError> {
+    if threshold == 0 || threshold > participants {
+        return Err(ShareError::InvalidThreshold);
+    }
+    let polynomials: Vec<Vec<SecretKey>> = (0..participants)
+        .map(|_| random_polynomial(SecretKey::new(), threshold))
+        .collect();
+    // The commitments of all dealers add up to a commitment to the summed
+    // polynomial, whose constant term is the group public key.
+    let commitments: Vec<G1Projective> = (0..usize::from(threshold))
+        .map(|degree| {
+            polynomials
+                .iter()
+                .map(|polynomial| G1Projective::generator() * polynomial[degree].0)
+                .sum()
+        })
+        .collect();
+
+    let shares: Vec<SecretKeyShare> = (1..=participants)
+        .map(|index| {
+            let value = polynomials.iter().fold(Scalar::ZERO, |acc, polynomial| {
+                acc + evaluate_polynomial(polynomial, index).0
+            });
+            SecretKeyShare {
+                threshold,
+                index,
+                value: SecretKey(value),
+            }
+        })
+        .collect();
+    let pubkey_shares = (1..=participants)
+        .map(|index| {
+            let x = Scalar::from(u64::from(index));
+            let pubkey = commitments
+                .iter()
+                .rev()
+                .fold(G1Projective::identity(), |acc, commitment| {
+                    acc * x + commitment
+                });
+            PubkeyShare {
+                threshold,
+                index,
+                pubkey: PubkeyProjective(pubkey),
+            }
+        })
+        .collect();
+
+    let group_pubkey = PubkeyProjective(commitments[0]);
+    let proof_of_possession = combine_proof_of_possession(&shares, &group_pubkey)?;
+    let group_pubkey = group_pubkey.verify_and_wrap_pop(&proof_of_possession, None)?;
+    Ok(DkgOutput {
+        group_pubkey,
+        proof_of_possession,
+        shares,
+        pubkey_shares,
+    })
+}
+
+/// Produces a proof of possession of `group_pubkey` from its secret key shares
+#[allow(clippy::arithmetic_side_effects)]
+fn combine_proof_of_possession(
+    shares: &[SecretKeyShare],
+    group_pubkey: &PubkeyProjective,
+) -> Result<ProofOfPossessionProjective, ShareError> {
+    let shares = select_shares(shares, SecretKeyShare::threshold, SecretKeyShare::index)?;
+    let hashed_point = hash_bound_pop_to_projective(&[], &group_pubkey.to_bytes_compressed());
+    let points: Vec<G2Projective> = shares
+        .iter()
+        .map(|share| hashed_point * share.value.0)
+        .collect();
+    let indexes: Vec<u8> = shares.iter().map(SecretKeyShare::index).collect();
+    Ok(ProofOfPossessionProjective(G2Projective::multi_exp(
+        &points,
+        &lagrange_coefficients(&indexes),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            pubkey::Pubkey,
+            shamir::{recover_secret_key, split_secret_key},
+            signature::VerifiableSignature,
+        },
+    };
+
+    const MESSAGE: &[u8] = b"threshold message";
+
+    #[test]
+    fn test_split_sign_and_combine() {
+        let secret_key = SecretKey::new();
+        let pubkey = PubkeyProjective::from_secret(&secret_key)
+            .verify_and_wrap_pop(&secret_key.proof_of_possession(None), None)
+            .unwrap();
+        let shares = split_secret_key(&secret_key, 3, 5).unwrap();
+        let partials: Vec<PartialSignature> =
+            shares.iter().map(|share| share.sign(MESSAGE)).collect();
+        for (share, partial) in shares.iter().zip(&partials) {
+            let pubkey_share = share.pubkey_share();
+            pubkey_share.verify(partial, MESSAGE).unwrap();
+            assert_eq!(
+                pubkey_share.verify(partial, b"other message"),
+                Err(BlsError::VerificationFailed)
+            );
+        }
+        assert_eq!(
+            shares[0].pubkey_share().verify(&partials[1], MESSAGE),
+            Err(BlsError::VerificationFailed)
+        );
+
+        // Signatures are deterministic, so any subset combines to the same one.
+        let expected = secret_key.sign(MESSAGE);
+        for subset in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+            let subset: Vec<_> = subset.iter().map(|i| partials[*i]).collect();
+            let signature = combine_partial_signatures(&subset).unwrap();
+            assert_eq!(signature, expected);
+            signature.verify(&pubkey, MESSAGE).unwrap();
+        }
+        assert_eq!(
+            combine_partial_signatures(&partials[..2]),
+            Err(ShareError::NotEnoughShares)
+        );
+        assert_eq!(
+            combine_partial_signatures(&[partials[0], partials[0], partials[1]]),
+            Err(ShareError::DuplicateIndex(1))
+        );
+    }
+
+    #[test]
+    fn test_simulate_dkg() {
+        let output = simulate_dkg(3, 4).unwrap();
+        assert_eq!(output.shares.len(), 4);
+        for (share, pubkey_share) in output.shares.iter().zip(&output.pubkey_shares) {
+            assert_eq!(share.pubkey_share(), *pubkey_share);
+        }
+        let group_pubkey: Pubkey = output.group_pubkey.into_inner().into();
+        group_pubkey
+            .verify_proof_of_possession(&output.proof_of_possession, None)
+            .unwrap();
+
+        let partials: Vec<PartialSignature> = output.shares[1..]
+            .iter()
+            .map(|share| share.sign(MESSAGE))
+            .collect();
+        let signature = combine_partial_signatures(&partials).unwrap();
+        signature.verify(&output.group_pubkey, MESSAGE).unwrap();
+
+        // The shares also recover the group secret key.
+        let secret_key = recover_secret_key(&output.shares, &group_pubkey).unwrap();
+        assert_eq!(secret_key.sign(MESSAGE), signature);
+    }
+
+    #[test]
+    fn test_invalid_threshold() {
+        assert_eq!(
+            simulate_dkg(0, 3).unwrap_err(),
+            ShareError::InvalidThreshold
+        );
+        assert_eq!(
+            simulate_dkg(4, 3).unwrap_err(),
+            ShareError::InvalidThreshold
+        );
+        let partial = PartialSignature::new(2, 1, SignatureProjective::identity()).unwrap();
+        let other = PartialSignature::new(3, 2, SignatureProjective::identity()).unwrap();
+        assert_eq!(
+            combine_partial_signatures(&[partial, other]),
+            Err(ShareError::ThresholdMismatch)
+        );
+        assert_eq!(
+            PartialSignature::new(2, 0, SignatureProjective::identity()),
+            Err(ShareError::InvalidIndex)
+        );
+        assert_eq!(
+            PubkeyShare::new(0, 1, PubkeyProjective::identity()),
+            Err(ShareError::InvalidThreshold)
+        );
+    }
+}
@@ -10,6 +10,9 @@
 //! with 501 being the Solana coin type.
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+#[cfg(feature = "slip10")]
+pub mod slip10;
+
 use {
     core::{iter::IntoIterator, slice::Iter},
     derivation_path::{ChildIndex, DerivationPath as DerivationPathInner},
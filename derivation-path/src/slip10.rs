@@ -0,0 +1,550 @@
+//! [SLIP-0010] ed25519 key derivation.
+//!
+//! [SLIP-0010]: https://github.com/satoshilabs/slips/blob/master/slip-0010.md
+//!
+//! Ed25519 only supports hardened derivation, so every segment of a path
+//! must be hardened. Derivation fails with
+//! [`Slip10Error::NonHardenedSegment`] otherwise, rather than silently
+//! hardening the segment.
+//!
+//! [`AccountPaths`] enumerates the standard Solana account paths
+//! `m/44'/501'/N'/0'`, and [`discover_accounts`] scans them for used
+//! accounts, for instance to restore a wallet offline.
+
+use {
+    crate::{Bip44, DerivationPath, Solana},
+    derivation_path::ChildIndex,
+    ed25519_dalek::SigningKey,
+    hmac::{Hmac, Mac},
+    ripemd::Ripemd160,
+    sha2::{Digest, Sha256, Sha512},
+    std::{fmt, str::FromStr},
+    zeroize::Zeroizing,
+};
+
+type HmacSha512 = Hmac<Sha512>;
+
+/// HMAC key of the master key derivation for ed25519.
+const ED25519_SEED_KEY: &[u8] = b"ed25519 seed";
+const HARDENED_BIT: u32 = 1 << 31;
+
+/// Size of a serialized [`ExtendedPublicKey`].
+pub const EXTENDED_PUBLIC_KEY_LENGTH: usize = 1 + 4 + 4 + 32 + 33;
+
+/// SLIP-0010 derivation error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Slip10Error {
+    /// The seed is shorter than 16 or longer than 64 bytes.
+    InvalidSeedLength(usize),
+    /// The path segment at `position` is not hardened.
+    NonHardenedSegment { position: usize, index: u32 },
+    /// The child index is 2^31 or more, so it already has the hardened bit
+    /// set.
+    InvalidIndex(u32),
+    /// The path is deeper than 255 segments.
+    MaxDepthExceeded,
+    /// The serialized extended public key is malformed.
+    InvalidExtendedKey,
+}
+
+impl core::error::Error for Slip10Error {}
+
+impl fmt::Display for Slip10Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Slip10Error::InvalidSeedLength(len) => write!(f, "invalid seed length: {len}"),
+            Slip10Error::NonHardenedSegment { position, index } => write!(
+                f,
+                "non-hardened segment {index} at position {position}, ed25519 only supports \
+                 hardened derivation"
+            ),
+            Slip10Error::InvalidIndex(index) => write!(
+                f,
+                "invalid child index {index}, indexes must be less than 2^31"
+            ),
+            Slip10Error::MaxDepthExceeded => f.write_str("maximum derivation depth exceeded"),
+            Slip10Error::InvalidExtendedKey => f.write_str("invalid extended public key"),
+        }
+    }
+}
+
+/// Returns the BIP-32 fingerprint of an ed25519 public key.
+fn fingerprint(public_key: &[u8; 32]) -> [u8; 4] {
+    let sha = Sha256::new()
+        .chain_update([0])
+        .chain_update(public_key)
+        .finalize();
+    let hash = Ripemd160::digest(sha);
+    let mut fingerprint = [0; 4];
+    fingerprint.copy_from_slice(&hash[..4]);
+    fingerprint
+}
+
+/// An ed25519 secret key with its SLIP-0010 chain code.
+#[derive(Clone)]
+pub struct ExtendedSigningKey {
+    depth: u8,
+    parent_fingerprint: [u8; 4],
+    child_index: u32,
+    chain_code: [u8; 32],
+    secret_key: Zeroizing<[u8; 32]>,
+}
+
+impl fmt::Debug for ExtendedSigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtendedSigningKey")
+            .field("depth", &self.depth)
+            .field("parent_fingerprint", &self.parent_fingerprint)
+            .field("child_index", &self.child_index)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ExtendedSigningKey {
+    /// Derives the master key from a seed of 16 to 64 bytes.
+    pub fn from_seed(seed: &[u8]) -> Result<Self, Slip10Error> {
+        if !(16..=64).contains(&seed.len()) {
+            return Err(Slip10Error::InvalidSeedLength(seed.len()));
+        }
+        let (secret_key, chain_code) = Self::hmac(ED25519_SEED_KEY, &[seed]);
+        Ok(Self {
+            depth: 0,
+            parent_fingerprint: [0; 4],
+            child_index: 0,
+            chain_code,
+            secret_key,
+        })
+    }
+
+    /// Derives the key at `path` from a seed.
+    pub fn from_seed_and_path<P: AsRef<[ChildIndex]> + ?Sized>(
+        seed: &[u8],
+        path: &P,
+    ) -> Result<Self, Slip10Error> {
+        Self::from_seed(seed)?.derive(path)
+    }
+
+    fn hmac(key: &[u8], data: &[&[u8]]) -> (Zeroizing<[u8; 32]>, [u8; 32]) {
+        let mut mac = HmacSha512::new_from_slice(key).expect("HMAC accepts keys of any length");
+        for data in data {
+            mac.update(data);
+        }
+        let output = Zeroizing::new(<[u8; 64]>::from(mac.finalize().into_bytes()));
+        let mut secret_key = Zeroizing::new([0; 32]);
+        let mut chain_code = [0; 32];
+        secret_key.copy_from_slice(&output[..32]);
+        chain_code.copy_from_slice(&output[32..]);
+        (secret_key, chain_code)
+    }
+
+    /// Derives the hardened child key at `index`.
+    ///
+    /// `index` is the index without the hardened bit, as in
+    /// [`ChildIndex::Hardened`], and must be less than 2^31.
+    pub fn derive_child(&self, index: u32) -> Result<Self, Slip10Error> {
+        if index >= HARDENED_BIT {
+            return Err(Slip10Error::InvalidIndex(index));
+        }
+        let depth = self
+            .depth
+            .checked_add(1)
+            .ok_or(Slip10Error::MaxDepthExceeded)?;
+        let child_index = index | HARDENED_BIT;
+        let (secret_key, chain_code) = Self::hmac(
+            &self.chain_code,
+            &[&[0], self.secret_key.as_ref(), &child_index.to_be_bytes()],
+        );
+        Ok(Self {
+            depth,
+            parent_fingerprint: fingerprint(&self.public_key()),
+            child_index,
+            chain_code,
+            secret_key,
+        })
+    }
+
+    /// Derives the key at `path`, relative to this key.
+    ///
+    /// Fails if any segment of `path` is not hardened.
+    pub fn derive<P: AsRef<[ChildIndex]> + ?Sized>(&self, path: &P) -> Result<Self, Slip10Error> {
+        let path = path.as_ref();
+        if let Some((position, index)) =
+            path.iter()
+                .enumerate()
+                .find_map(|(position, index)| match index {
+                    ChildIndex::Normal(index) => Some((position, *index)),
+                    ChildIndex::Hardened(_) => None,
+                })
+        {
+            return Err(Slip10Error::NonHardenedSegment { position, index });
+        }
+        path.iter()
+            .try_fold(self.clone(), |key, index| key.derive_child(index.to_u32()))
+    }
+
+    /// Returns the number of derivations from the master key.
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    /// Returns the fingerprint of the parent key, or zeros for the master
+    /// key.
+    pub fn parent_fingerprint(&self) -> [u8; 4] {
+        self.parent_fingerprint
+    }
+
+    /// Returns the index of this key, including the hardened bit, or zero for
+    /// the master key.
+    pub fn child_index(&self) -> u32 {
+        self.child_index
+    }
+
+    pub fn chain_code(&self) -> &[u8; 32] {
+        &self.chain_code
+    }
+
+    /// Returns the ed25519 secret key, which can be used as the secret key of
+    /// a Solana keypair.
+    pub fn secret_key(&self) -> &[u8; 32] {
+        &self.secret_key
+    }
+
+    /// Returns the ed25519 public key.
+    pub fn public_key(&self) -> [u8; 32] {
+        SigningKey::from_bytes(&self.secret_key)
+            .verifying_key()
+            .to_bytes()
+    }
+
+    /// Returns the public part of this key, for export.
+    pub fn to_extended_public_key(&self) -> ExtendedPublicKey {
+        ExtendedPublicKey {
+            depth: self.depth,
+            parent_fingerprint: self.parent_fingerprint,
+            child_index: self.child_index,
+            chain_code: self.chain_code,
+            public_key: self.public_key(),
+        }
+    }
+}
+
+/// An ed25519 public key with its SLIP-0010 chain code and position.
+///
+/// This is serialized like a BIP-32 extended public key without the version
+/// bytes: depth, parent fingerprint, child index, chain code and the public
+/// key prefixed with a zero byte, and displayed in base58 with a checksum.
+/// Ed25519 does not support public derivation, so it identifies an account
+/// but cannot derive its children.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtendedPublicKey {
+    pub depth: u8,
+    pub parent_fingerprint: [u8; 4],
+    pub child_index: u32,
+    pub chain_code: [u8; 32],
+    pub public_key: [u8; 32],
+}
+
+impl ExtendedPublicKey {
+    /// Returns the fingerprint of this key.
+    pub fn fingerprint(&self) -> [u8; 4] {
+        fingerprint(&self.public_key)
+    }
+
+    pub fn to_bytes(&self) -> [u8; EXTENDED_PUBLIC_KEY_LENGTH] {
+        let mut bytes = [0; EXTENDED_PUBLIC_KEY_LENGTH];
+        bytes[0] = self.depth;
+        bytes[1..5].copy_from_slice(&self.parent_fingerprint);
+        bytes[5..9].copy_from_slice(&self.child_index.to_be_bytes());
+        bytes[9..41].copy_from_slice(&self.chain_code);
+        // bytes[41] is the zero prefix of the public key
+        bytes[42..].copy_from_slice(&self.public_key);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Slip10Error> {
+        let bytes: &[u8; EXTENDED_PUBLIC_KEY_LENGTH] = bytes
+            .try_into()
+            .map_err(|_| Slip10Error::InvalidExtendedKey)?;
+        if bytes[41] != 0 {
+            return Err(Slip10Error::InvalidExtendedKey);
+        }
+        let mut key = Self {
+            depth: bytes[0],
+            parent_fingerprint: [0; 4],
+            child_index: 0,
+            chain_code: [0; 32],
+            public_key: [0; 32],
+        };
+        key.parent_fingerprint.copy_from_slice(&bytes[1..5]);
+        key.child_index = u32::from_be_bytes([bytes[5], bytes[6], bytes[7], bytes[8]]);
+        key.chain_code.copy_from_slice(&bytes[9..41]);
+        key.public_key.copy_from_slice(&bytes[42..]);
+        Ok(key)
+    }
+}
+
+impl fmt::Display for ExtendedPublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&bs58::encode(self.to_bytes()).with_check().into_string())
+    }
+}
+
+impl FromStr for ExtendedPublicKey {
+    type Err = Slip10Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = bs58::decode(s)
+            .with_check(None)
+            .into_vec()
+            .map_err(|_| Slip10Error::InvalidExtendedKey)?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+/// An iterator over the standard Solana account paths `m/44'/501'/N'/0'`.
+#[derive(Debug, Clone)]
+pub struct AccountPaths {
+    next: Option<u32>,
+}
+
+impl AccountPaths {
+    /// Enumerates the account paths from account `start`.
+    pub fn new(start: u32) -> Self {
+        Self {
+            next: (start < HARDENED_BIT).then_some(start),
+        }
+    }
+}
+
+impl Default for AccountPaths {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Iterator for AccountPaths {
+    type Item = DerivationPath;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let account = self.next?;
+        self.next = account.checked_add(1).filter(|next| *next < HARDENED_BIT);
+        Some(DerivationPath::new_bip44(Some(account), Some(0)))
+    }
+}
+
+/// An account found by [`discover_accounts`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredAccount {
+    pub account: u32,
+    pub path: DerivationPath,
+    pub public_key: [u8; 32],
+}
+
+/// Scans the accounts `m/44'/501'/N'/0'` of a seed, from account 0, and
+/// returns those for which `is_used` returns true.
+///
+/// The scan stops after `gap_limit` consecutive unused accounts, so accounts
+/// after a larger gap are not found.
+pub fn discover_accounts<F>(
+    seed: &[u8],
+    gap_limit: u32,
+    mut is_used: F,
+) -> Result<Vec<DiscoveredAccount>, Slip10Error>
+where
+    F: FnMut(&DerivationPath, &[u8; 32]) -> bool,
+{
+    let coin = ExtendedSigningKey::from_seed(seed)?.derive(&Solana.base_indexes())?;
+    let mut accounts = Vec::new();
+    let mut gap = 0;
+    for path in AccountPaths::default() {
+        if gap >= gap_limit {
+            break;
+        }
+        let account = path
+            .account()
+            .map(|index| index.to_u32())
+            .unwrap_or_default();
+        let public_key = coin.derive_child(account)?.derive_child(0)?.public_key();
+        if is_used(&path, &public_key) {
+            gap = 0;
+            accounts.push(DiscoveredAccount {
+                account,
+                path,
+                public_key,
+            });
+        } else {
+            gap = gap.saturating_add(1);
+        }
+    }
+    Ok(accounts)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, hex::decode};
+
+    fn hex(s: &str) -> Vec<u8> {
+        decode(s).unwrap()
+    }
+
+    #[test]
+    fn test_slip10_vector_1() {
+        let seed = hex("000102030405060708090a0b0c0d0e0f");
+        let master = ExtendedSigningKey::from_seed(&seed).unwrap();
+        assert_eq!(master.depth(), 0);
+        assert_eq!(master.parent_fingerprint(), [0; 4]);
+        assert_eq!(
+            master.chain_code().to_vec(),
+            hex("90046a93de5380a72b5e45010748567d5ea02bbf6522f979e05c0d8d8ca9fffb")
+        );
+        assert_eq!(
+            master.secret_key().to_vec(),
+            hex("2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7")
+        );
+        assert_eq!(
+            master.public_key().to_vec(),
+            hex("a4b2856bfec510abab89753fac1ac0e1112364e7d250545963f135f2a33188ed")
+        );
+
+        let path = DerivationPath::from_absolute_path_str("m/0'/1'").unwrap();
+        let child = master.derive(&path).unwrap();
+        assert_eq!(child.depth(), 2);
+        assert_eq!(child.parent_fingerprint().to_vec(), hex("13dab143"));
+        assert_eq!(child.child_index(), 1 | HARDENED_BIT);
+        assert_eq!(
+            child.chain_code().to_vec(),
+            hex("a320425f77d1b5c2505a6b1b27382b37368ee640e3557c315416801243552f14")
+        );
+        assert_eq!(
+            child.secret_key().to_vec(),
+            hex("b1d0bad404bf35da785a64ca1ac54b2617211d2777696fbffaf208f746ae84f2")
+        );
+        assert_eq!(
+            child.public_key().to_vec(),
+            hex("1932a5270f335bed617d5b935c80aedb1a35bd9fc1e31acafd5372c30f5c1187")
+        );
+
+        let first = master.derive_child(0).unwrap();
+        assert_eq!(first.parent_fingerprint().to_vec(), hex("ddebc675"));
+        assert_eq!(
+            first.to_extended_public_key().fingerprint(),
+            child.parent_fingerprint()
+        );
+    }
+
+    #[test]
+    fn test_non_hardened_segment() {
+        let seed = [0; 32];
+        let path = DerivationPath::_from_absolute_path_insecure_str("m/44'/501'/0/0'").unwrap();
+        assert_eq!(
+            ExtendedSigningKey::from_seed_and_path(&seed, &path).unwrap_err(),
+            Slip10Error::NonHardenedSegment {
+                position: 2,
+                index: 0
+            }
+        );
+        let master = ExtendedSigningKey::from_seed(&seed).unwrap();
+        assert_eq!(
+            master.derive_child(HARDENED_BIT).unwrap_err(),
+            Slip10Error::InvalidIndex(HARDENED_BIT)
+        );
+        assert_eq!(
+            master
+                .derive(&[ChildIndex::Hardened(44), ChildIndex::Hardened(u32::MAX)][..])
+                .unwrap_err(),
+            Slip10Error::InvalidIndex(u32::MAX)
+        );
+        assert_eq!(
+            ExtendedSigningKey::from_seed(&[0; 15]).unwrap_err(),
+            Slip10Error::InvalidSeedLength(15)
+        );
+        assert_eq!(
+            ExtendedSigningKey::from_seed(&[0; 65]).unwrap_err(),
+            Slip10Error::InvalidSeedLength(65)
+        );
+    }
+
+    #[test]
+    fn test_extended_public_key() {
+        let seed = [7; 64];
+        let key = ExtendedSigningKey::from_seed_and_path(&seed, &DerivationPath::default())
+            .unwrap()
+            .to_extended_public_key();
+        assert_eq!(key.depth, 2);
+        assert_eq!(key.child_index, 501 | HARDENED_BIT);
+        let encoded = key.to_string();
+        assert_eq!(encoded.parse::<ExtendedPublicKey>().unwrap(), key);
+
+        let mut bytes = key.to_bytes();
+        bytes[41] = 1;
+        assert_eq!(
+            ExtendedPublicKey::from_bytes(&bytes).unwrap_err(),
+            Slip10Error::InvalidExtendedKey
+        );
+        let mut corrupted = encoded.into_bytes();
+        corrupted[10] = if corrupted[10] == b'1' { b'2' } else { b'1' };
+        assert_eq!(
+            String::from_utf8(corrupted)
+                .unwrap()
+                .parse::<ExtendedPublicKey>()
+                .unwrap_err(),
+            Slip10Error::InvalidExtendedKey
+        );
+    }
+
+    #[test]
+    fn test_account_paths() {
+        let paths: Vec<String> = AccountPaths::default()
+            .take(3)
+            .map(|path| format!("{path:?}"))
+            .collect();
+        assert_eq!(
+            paths,
+            vec!["m/44'/501'/0'/0'", "m/44'/501'/1'/0'", "m/44'/501'/2'/0'"]
+        );
+        assert_eq!(AccountPaths::new(HARDENED_BIT - 1).count(), 1);
+        assert_eq!(AccountPaths::new(HARDENED_BIT).count(), 0);
+    }
+
+    #[test]
+    fn test_discover_accounts() {
+        let seed = [42; 64];
+        let keys: Vec<[u8; 32]> = AccountPaths::default()
+            .take(10)
+            .map(|path| {
+                ExtendedSigningKey::from_seed_and_path(&seed, &path)
+                    .unwrap()
+                    .public_key()
+            })
+            .collect();
+
+        // accounts 0, 1, 4 and 7 are used, but the gap of 2 after account 1
+        // ends the scan after 4 probes, so only 0 and 1 are found
+        let used = [&keys[0], &keys[1], &keys[4], &keys[7]];
+        let mut scanned = 0;
+        let accounts = discover_accounts(&seed, 2, |_, key| {
+            scanned += 1;
+            used.contains(&key)
+        })
+        .unwrap();
+        assert_eq!(
+            accounts.iter().map(|a| a.account).collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+        assert_eq!(scanned, 4);
+
+        // a gap limit of 3 reaches accounts 4 and 7
+        let accounts = discover_accounts(&seed, 3, |_, key| used.contains(&key)).unwrap();
+        assert_eq!(
+            accounts.iter().map(|a| a.account).collect::<Vec<_>>(),
+            vec![0, 1, 4, 7]
+        );
+        assert_eq!(accounts[2].public_key, keys[4]);
+        assert_eq!(
+            accounts[2].path,
+            DerivationPath::new_bip44(Some(4), Some(0))
+        );
+
+        assert!(discover_accounts(&seed, 0, |_, _| true).unwrap().is_empty());
+    }
+}
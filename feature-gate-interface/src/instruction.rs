@@ -1,3 +1,4 @@
+use solana_program_error::ProgramError;
 #[cfg(feature = "bincode")]
 use {
     crate::state::Feature,
@@ -8,6 +9,42 @@ use {
     solana_system_interface::instruction as system_instruction,
 };
 
+/// Feature Gate program instructions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeatureGateInstruction {
+    /// Revoke a pending feature activation.
+    ///
+    /// A "pending" feature activation is a feature account that has been
+    /// allocated and assigned, but hasn't yet been updated by the runtime
+    /// with an `activation_slot`.
+    ///
+    /// Features that _have_ been activated by the runtime cannot be revoked.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[w+s]`  Feature account
+    ///   1. `[w]`    Incinerator
+    ///   2. `[ ]`    System program
+    RevokePendingActivation,
+}
+
+impl FeatureGateInstruction {
+    /// Unpacks a byte buffer into a [`FeatureGateInstruction`].
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        match input {
+            [0] => Ok(Self::RevokePendingActivation),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+
+    /// Packs a [`FeatureGateInstruction`] into a byte buffer.
+    pub fn pack(&self) -> Vec<u8> {
+        match self {
+            Self::RevokePendingActivation => vec![0],
+        }
+    }
+}
+
 /// Activate a feature
 #[cfg(feature = "bincode")]
 #[deprecated(
@@ -47,6 +84,30 @@ pub fn revoke_pending_activation(feature_id: &Pubkey) -> Instruction {
     Instruction {
         program_id: crate::id(),
         accounts,
-        data: vec![0],
+        data: FeatureGateInstruction::RevokePendingActivation.pack(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_unpack() {
+        let instruction = FeatureGateInstruction::RevokePendingActivation;
+        assert_eq!(instruction.pack(), vec![0]);
+        assert_eq!(FeatureGateInstruction::unpack(&[0]), Ok(instruction));
+        assert_eq!(
+            FeatureGateInstruction::unpack(&[]),
+            Err(ProgramError::InvalidInstructionData)
+        );
+        assert_eq!(
+            FeatureGateInstruction::unpack(&[1]),
+            Err(ProgramError::InvalidInstructionData)
+        );
+        assert_eq!(
+            FeatureGateInstruction::unpack(&[0, 0]),
+            Err(ProgramError::InvalidInstructionData)
+        );
     }
 }
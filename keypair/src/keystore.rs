@@ -0,0 +1,588 @@
+//! Passphrase-encrypted keypair files.
+//!
+//! An [`EncryptedKeypair`] holds a secret key encrypted with
+//! XChaCha20-Poly1305 under a key derived from a passphrase with Argon2id.
+//! It is stored as the base64 encoding of:
+//!
+//! | Field          | Size     | Notes                                   |
+//! |----------------|----------|-----------------------------------------|
+//! | magic          | 8        | `SOLANAKS`                              |
+//! | version        | 1        | currently `1`                           |
+//! | kdf            | 1        | `1` for Argon2id                        |
+//! | memory (KiB)   | 4        | little-endian                           |
+//! | iterations     | 4        | little-endian                           |
+//! | parallelism    | 4        | little-endian                           |
+//! | salt           | 16       |                                         |
+//! | nonce          | 24       |                                         |
+//! | pubkey hint    | 1 or 33  | `0`, or `1` followed by the public key  |
+//! | ciphertext     | 48       | encrypted secret key and tag            |
+//!
+//! Everything before the ciphertext is authenticated as associated data, so
+//! the header and the clear text pubkey hint cannot be changed without
+//! decryption failing.
+
+use {
+    crate::Keypair,
+    argon2::{Algorithm, Argon2, Params, Version},
+    base64::{prelude::BASE64_STANDARD, Engine},
+    chacha20poly1305::{
+        aead::{Aead, KeyInit, Payload},
+        XChaCha20Poly1305, XNonce,
+    },
+    solana_address::Address,
+    solana_signer::{EncodableKey, Signer},
+    std::{
+        error, fmt, fs,
+        io::{Read, Write},
+        path::Path,
+    },
+    zeroize::Zeroizing,
+};
+
+/// Magic bytes at the start of an encrypted keypair.
+pub const KEYSTORE_MAGIC: [u8; 8] = *b"SOLANAKS";
+
+/// Current version of the encrypted keypair format.
+pub const KEYSTORE_VERSION: u8 = 1;
+
+const KDF_ARGON2ID: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const SECRET_KEY_LEN: usize = Keypair::SECRET_KEY_LENGTH;
+const TAG_LEN: usize = 16;
+const CIPHERTEXT_LEN: usize = SECRET_KEY_LEN + TAG_LEN;
+
+/// Largest accepted Argon2 memory cost, 1 GiB.
+///
+/// The cost parameters are read from the file and used before the passphrase
+/// can be checked, so all three are bounded to keep a crafted file from
+/// exhausting memory or stalling the reader.
+pub const MAX_MEMORY_KIB: u32 = 1024 * 1024;
+
+/// Largest accepted number of Argon2 iterations.
+pub const MAX_ITERATIONS: u32 = 16;
+
+/// Largest accepted Argon2 parallelism.
+pub const MAX_PARALLELISM: u32 = 16;
+
+/// Errors produced when encrypting or decrypting a keypair.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeystoreError {
+    /// The data is not an encrypted keypair.
+    InvalidFormat,
+    /// The format version is not supported.
+    UnsupportedVersion(u8),
+    /// The key derivation function is not supported.
+    UnsupportedKdf(u8),
+    /// The key derivation parameters are out of range.
+    InvalidKdfParams,
+    /// The passphrase is wrong or the data has been modified.
+    DecryptionFailed,
+    /// The decrypted keypair does not match the pubkey hint.
+    PubkeyMismatch,
+    /// The keypair is encrypted and a passphrase is required to read it.
+    PassphraseRequired,
+}
+
+impl error::Error for KeystoreError {}
+
+impl fmt::Display for KeystoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidFormat => f.write_str("invalid encrypted keypair format"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported encrypted keypair version {version}")
+            }
+            Self::UnsupportedKdf(kdf) => write!(f, "unsupported key derivation function {kdf}"),
+            Self::InvalidKdfParams => f.write_str("invalid key derivation parameters"),
+            Self::DecryptionFailed => f.write_str("wrong passphrase or corrupted keypair"),
+            Self::PubkeyMismatch => f.write_str("decrypted keypair does not match pubkey hint"),
+            Self::PassphraseRequired => {
+                f.write_str("keypair is encrypted, a passphrase is required")
+            }
+        }
+    }
+}
+
+/// Argon2id cost parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    /// Memory cost in KiB.
+    pub memory_kib: u32,
+    /// Number of passes over the memory.
+    pub iterations: u32,
+    /// Degree of parallelism.
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    /// 64 MiB, 3 iterations and a parallelism of 1.
+    fn default() -> Self {
+        Self {
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 1,
+        }
+    }
+}
+
+impl KdfParams {
+    /// Checks that the parameters are within [`MAX_MEMORY_KIB`],
+    /// [`MAX_ITERATIONS`] and [`MAX_PARALLELISM`].
+    fn check_bounds(&self) -> Result<(), KeystoreError> {
+        if self.memory_kib > MAX_MEMORY_KIB
+            || self.iterations > MAX_ITERATIONS
+            || self.parallelism > MAX_PARALLELISM
+        {
+            return Err(KeystoreError::InvalidKdfParams);
+        }
+        Ok(())
+    }
+
+    fn derive_key(
+        &self,
+        passphrase: &str,
+        salt: &[u8; SALT_LEN],
+    ) -> Result<Zeroizing<[u8; 32]>, KeystoreError> {
+        self.check_bounds()?;
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, Some(32))
+            .map_err(|_| KeystoreError::InvalidKdfParams)?;
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+            .map_err(|_| KeystoreError::InvalidKdfParams)?;
+        Ok(key)
+    }
+}
+
+/// A keypair encrypted with a passphrase.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedKeypair {
+    params: KdfParams,
+    salt: [u8; SALT_LEN],
+    nonce: [u8; NONCE_LEN],
+    pubkey_hint: Option<Address>,
+    ciphertext: [u8; CIPHERTEXT_LEN],
+}
+
+impl EncryptedKeypair {
+    /// Encrypts `keypair` with `passphrase` using the default key derivation
+    /// parameters.
+    ///
+    /// If `pubkey_hint` is true, the public key is stored in clear text so
+    /// that the file can be identified without the passphrase.
+    pub fn encrypt(
+        keypair: &Keypair,
+        passphrase: &str,
+        pubkey_hint: bool,
+    ) -> Result<Self, KeystoreError> {
+        Self::encrypt_with_params(keypair, passphrase, pubkey_hint, KdfParams::default())
+    }
+
+    /// Encrypts `keypair` with `passphrase` using the given key derivation
+    /// parameters.
+    pub fn encrypt_with_params(
+        keypair: &Keypair,
+        passphrase: &str,
+        pubkey_hint: bool,
+        params: KdfParams,
+    ) -> Result<Self, KeystoreError> {
+        let mut encrypted = Self {
+            params,
+            salt: rand::random(),
+            nonce: rand::random(),
+            pubkey_hint: pubkey_hint.then(|| keypair.pubkey()),
+            ciphertext: [0; CIPHERTEXT_LEN],
+        };
+        let key = params.derive_key(passphrase, &encrypted.salt)?;
+        let ciphertext = XChaCha20Poly1305::new(key.as_ref().into())
+            .encrypt(
+                XNonce::from_slice(&encrypted.nonce),
+                Payload {
+                    msg: keypair.secret_bytes(),
+                    aad: &encrypted.header(),
+                },
+            )
+            .map_err(|_| KeystoreError::InvalidFormat)?;
+        encrypted.ciphertext = ciphertext
+            .try_into()
+            .map_err(|_| KeystoreError::InvalidFormat)?;
+        Ok(encrypted)
+    }
+
+    /// Decrypts the keypair with `passphrase`.
+    pub fn decrypt(&self, passphrase: &str) -> Result<Keypair, KeystoreError> {
+        let key = self.params.derive_key(passphrase, &self.salt)?;
+        let secret_key = Zeroizing::new(
+            XChaCha20Poly1305::new(key.as_ref().into())
+                .decrypt(
+                    XNonce::from_slice(&self.nonce),
+                    Payload {
+                        msg: &self.ciphertext,
+                        aad: &self.header(),
+                    },
+                )
+                .map_err(|_| KeystoreError::DecryptionFailed)?,
+        );
+        let secret_key: [u8; SECRET_KEY_LEN] = secret_key
+            .as_slice()
+            .try_into()
+            .map_err(|_| KeystoreError::DecryptionFailed)?;
+        let keypair = Keypair::new_from_array(secret_key);
+        if self
+            .pubkey_hint
+            .is_some_and(|pubkey| pubkey != keypair.pubkey())
+        {
+            return Err(KeystoreError::PubkeyMismatch);
+        }
+        Ok(keypair)
+    }
+
+    /// Decrypts the keypair with `passphrase` and encrypts it again with
+    /// `new_passphrase`, keeping the key derivation parameters and the pubkey
+    /// hint.
+    pub fn reencrypt(&self, passphrase: &str, new_passphrase: &str) -> Result<Self, KeystoreError> {
+        let keypair = self.decrypt(passphrase)?;
+        Self::encrypt_with_params(
+            &keypair,
+            new_passphrase,
+            self.pubkey_hint.is_some(),
+            self.params,
+        )
+    }
+
+    /// Returns the public key stored in clear text, if any.
+    ///
+    /// The hint is only checked against the secret key on decryption.
+    pub fn pubkey_hint(&self) -> Option<Address> {
+        self.pubkey_hint
+    }
+
+    /// Returns the key derivation parameters.
+    pub fn params(&self) -> KdfParams {
+        self.params
+    }
+
+    fn header(&self) -> Vec<u8> {
+        let mut header = Vec::with_capacity(96);
+        header.extend_from_slice(&KEYSTORE_MAGIC);
+        header.push(KEYSTORE_VERSION);
+        header.push(KDF_ARGON2ID);
+        header.extend_from_slice(&self.params.memory_kib.to_le_bytes());
+        header.extend_from_slice(&self.params.iterations.to_le_bytes());
+        header.extend_from_slice(&self.params.parallelism.to_le_bytes());
+        header.extend_from_slice(&self.salt);
+        header.extend_from_slice(&self.nonce);
+        match &self.pubkey_hint {
+            Some(pubkey) => {
+                header.push(1);
+                header.extend_from_slice(pubkey.as_ref());
+            }
+            None => header.push(0),
+        }
+        header
+    }
+
+    /// Serializes the encrypted keypair in binary form.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.header();
+        bytes.extend_from_slice(&self.ciphertext);
+        bytes
+    }
+
+    /// Deserializes an encrypted keypair from its binary form.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, KeystoreError> {
+        let mut reader = ByteReader(bytes);
+        if reader.take::<8>()? != KEYSTORE_MAGIC {
+            return Err(KeystoreError::InvalidFormat);
+        }
+        let [version] = reader.take()?;
+        if version != KEYSTORE_VERSION {
+            return Err(KeystoreError::UnsupportedVersion(version));
+        }
+        let [kdf] = reader.take()?;
+        if kdf != KDF_ARGON2ID {
+            return Err(KeystoreError::UnsupportedKdf(kdf));
+        }
+        let params = KdfParams {
+            memory_kib: u32::from_le_bytes(reader.take()?),
+            iterations: u32::from_le_bytes(reader.take()?),
+            parallelism: u32::from_le_bytes(reader.take()?),
+        };
+        params.check_bounds()?;
+        let salt = reader.take()?;
+        let nonce = reader.take()?;
+        let pubkey_hint = match reader.take()? {
+            [0] => None,
+            [1] => Some(Address::from(reader.take::<32>()?)),
+            _ => return Err(KeystoreError::InvalidFormat),
+        };
+        let ciphertext = reader.take()?;
+        if !reader.0.is_empty() {
+            return Err(KeystoreError::InvalidFormat);
+        }
+        Ok(Self {
+            params,
+            salt,
+            nonce,
+            pubkey_hint,
+            ciphertext,
+        })
+    }
+
+    /// Returns true if `contents` looks like an encoded encrypted keypair.
+    pub fn is_encrypted(contents: &[u8]) -> bool {
+        BASE64_STANDARD
+            .decode(contents.trim_ascii())
+            .is_ok_and(|bytes| bytes.starts_with(&KEYSTORE_MAGIC))
+    }
+}
+
+struct ByteReader<'a>(&'a [u8]);
+
+impl ByteReader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], KeystoreError> {
+        let (bytes, rest) = self
+            .0
+            .split_first_chunk::<N>()
+            .ok_or(KeystoreError::InvalidFormat)?;
+        self.0 = rest;
+        Ok(*bytes)
+    }
+}
+
+impl EncodableKey for EncryptedKeypair {
+    fn read<R: Read>(reader: &mut R) -> Result<Self, Box<dyn error::Error>> {
+        let mut contents = String::new();
+        reader.read_to_string(&mut contents)?;
+        let bytes = BASE64_STANDARD
+            .decode(contents.trim())
+            .map_err(|_| KeystoreError::InvalidFormat)?;
+        Ok(Self::from_bytes(&bytes)?)
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> Result<String, Box<dyn error::Error>> {
+        let contents = BASE64_STANDARD.encode(self.to_bytes());
+        writer.write_all(contents.as_bytes())?;
+        Ok(contents)
+    }
+}
+
+/// Reads a `Keypair` from a file that is either JSON-encoded or encrypted.
+///
+/// `passphrase` is only used if the file holds an [`EncryptedKeypair`].
+pub fn read_keypair_file_with_passphrase<F: AsRef<Path>>(
+    path: F,
+    passphrase: &str,
+) -> Result<Keypair, Box<dyn error::Error>> {
+    let contents = fs::read_to_string(path)?;
+    if EncryptedKeypair::is_encrypted(contents.as_bytes()) {
+        let encrypted = EncryptedKeypair::read(&mut contents.as_bytes())?;
+        Ok(encrypted.decrypt(passphrase)?)
+    } else {
+        crate::parse_keypair(&contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{read_keypair_file, write_keypair_file},
+        std::io::Cursor,
+    };
+
+    // Cheap parameters to keep the tests fast.
+    const TEST_PARAMS: KdfParams = KdfParams {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    fn encrypt(keypair: &Keypair, passphrase: &str, pubkey_hint: bool) -> EncryptedKeypair {
+        EncryptedKeypair::encrypt_with_params(keypair, passphrase, pubkey_hint, TEST_PARAMS)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let keypair = Keypair::new();
+        for pubkey_hint in [false, true] {
+            let encrypted = encrypt(&keypair, "hunter2", pubkey_hint);
+            assert_eq!(encrypted.params(), TEST_PARAMS);
+            assert_eq!(
+                encrypted.pubkey_hint(),
+                pubkey_hint.then(|| keypair.pubkey())
+            );
+            assert_eq!(encrypted.decrypt("hunter2").unwrap(), keypair);
+            assert_eq!(
+                encrypted.decrypt("hunter3"),
+                Err(KeystoreError::DecryptionFailed)
+            );
+
+            let mut buffer = Vec::new();
+            let contents = encrypted.write(&mut buffer).unwrap();
+            assert_eq!(contents.as_bytes(), buffer);
+            assert!(EncryptedKeypair::is_encrypted(&buffer));
+            let read = EncryptedKeypair::read(&mut Cursor::new(buffer)).unwrap();
+            assert_eq!(read, encrypted);
+        }
+    }
+
+    #[test]
+    fn test_header_is_authenticated() {
+        let keypair = Keypair::new();
+        let encrypted = encrypt(&keypair, "hunter2", true);
+
+        let mut tampered = encrypted.clone();
+        tampered.pubkey_hint = Some(Keypair::new().pubkey());
+        assert_eq!(
+            tampered.decrypt("hunter2"),
+            Err(KeystoreError::DecryptionFailed)
+        );
+
+        let mut tampered = encrypted.clone();
+        tampered.params.iterations = 2;
+        assert_eq!(
+            tampered.decrypt("hunter2"),
+            Err(KeystoreError::DecryptionFailed)
+        );
+
+        let mut tampered = encrypted;
+        tampered.ciphertext[0] ^= 1;
+        assert_eq!(
+            tampered.decrypt("hunter2"),
+            Err(KeystoreError::DecryptionFailed)
+        );
+    }
+
+    #[test]
+    fn test_reencrypt() {
+        let keypair = Keypair::new();
+        let encrypted = encrypt(&keypair, "old", true);
+        assert_eq!(
+            encrypted.reencrypt("wrong", "new"),
+            Err(KeystoreError::DecryptionFailed)
+        );
+        let reencrypted = encrypted.reencrypt("old", "new").unwrap();
+        assert_ne!(reencrypted.salt, encrypted.salt);
+        assert_eq!(reencrypted.params(), encrypted.params());
+        assert_eq!(reencrypted.pubkey_hint(), Some(keypair.pubkey()));
+        assert_eq!(reencrypted.decrypt("new").unwrap(), keypair);
+        assert_eq!(
+            reencrypted.decrypt("old"),
+            Err(KeystoreError::DecryptionFailed)
+        );
+    }
+
+    #[test]
+    fn test_invalid_bytes() {
+        let encrypted = encrypt(&Keypair::new(), "hunter2", false);
+        let bytes = encrypted.to_bytes();
+        assert_eq!(EncryptedKeypair::from_bytes(&bytes).unwrap(), encrypted);
+
+        assert_eq!(
+            EncryptedKeypair::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(KeystoreError::InvalidFormat)
+        );
+        let mut extended = bytes.clone();
+        extended.push(0);
+        assert_eq!(
+            EncryptedKeypair::from_bytes(&extended),
+            Err(KeystoreError::InvalidFormat)
+        );
+
+        let mut bad = bytes.clone();
+        bad[0] = b'X';
+        assert_eq!(
+            EncryptedKeypair::from_bytes(&bad),
+            Err(KeystoreError::InvalidFormat)
+        );
+        let mut bad = bytes.clone();
+        bad[8] = 2;
+        assert_eq!(
+            EncryptedKeypair::from_bytes(&bad),
+            Err(KeystoreError::UnsupportedVersion(2))
+        );
+        let mut bad = bytes;
+        bad[9] = 7;
+        assert_eq!(
+            EncryptedKeypair::from_bytes(&bad),
+            Err(KeystoreError::UnsupportedKdf(7))
+        );
+
+        assert!(!EncryptedKeypair::is_encrypted(b"[1,2,3]"));
+    }
+
+    #[test]
+    fn test_kdf_param_bounds() {
+        let bytes = encrypt(&Keypair::new(), "hunter2", false).to_bytes();
+        // Offsets of the memory, iterations and parallelism fields.
+        for (offset, max) in [
+            (10, MAX_MEMORY_KIB),
+            (14, MAX_ITERATIONS),
+            (18, MAX_PARALLELISM),
+        ] {
+            let mut params = TEST_PARAMS;
+            let field = match offset {
+                10 => &mut params.memory_kib,
+                14 => &mut params.iterations,
+                _ => &mut params.parallelism,
+            };
+            *field = max + 1;
+            assert_eq!(
+                EncryptedKeypair::encrypt_with_params(&Keypair::new(), "", false, params),
+                Err(KeystoreError::InvalidKdfParams)
+            );
+
+            // Out of range values are rejected when parsing, before any
+            // hashing.
+            for value in [max + 1, u32::MAX] {
+                let mut bad = bytes.clone();
+                bad[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+                assert_eq!(
+                    EncryptedKeypair::from_bytes(&bad),
+                    Err(KeystoreError::InvalidKdfParams)
+                );
+            }
+            let mut good = bytes.clone();
+            good[offset..offset + 4].copy_from_slice(&max.to_le_bytes());
+            assert!(EncryptedKeypair::from_bytes(&good).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_read_keypair_file_auto_detect() {
+        let dir = std::env::temp_dir().join(format!("keystore-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let keypair = Keypair::new();
+
+        let plain = dir.join("plain.json");
+        write_keypair_file(&keypair, &plain).unwrap();
+        assert_eq!(
+            read_keypair_file_with_passphrase(&plain, "unused").unwrap(),
+            keypair
+        );
+
+        let encrypted = dir.join("encrypted.json");
+        encrypt(&keypair, "hunter2", true)
+            .write_to_file(&encrypted)
+            .unwrap();
+        assert_eq!(
+            read_keypair_file_with_passphrase(&encrypted, "hunter2").unwrap(),
+            keypair
+        );
+        let err = read_keypair_file_with_passphrase(&encrypted, "wrong").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<KeystoreError>(),
+            Some(&KeystoreError::DecryptionFailed)
+        );
+        let err = read_keypair_file(&encrypted).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<KeystoreError>(),
+            Some(&KeystoreError::PassphraseRequired)
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
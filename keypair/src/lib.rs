@@ -2,7 +2,7 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 use {
     ed25519_dalek::Signer as DalekSigner,
-    solana_seed_phrase::generate_seed_from_seed_phrase_and_passphrase,
+    solana_seed_phrase::{generate_seed_from_seed_phrase_and_passphrase, Language, Mnemonic},
     solana_signer::SignerError,
     std::{
         error,
@@ -16,9 +16,15 @@ pub use {
     solana_signer::{EncodableKey, EncodableKeypair, Signer},
 };
 
+#[cfg(feature = "keystore")]
+pub mod keystore;
 #[cfg(feature = "seed-derivable")]
 pub mod seed_derivable;
+#[cfg(feature = "shamir")]
+pub mod shamir;
 pub mod signable;
+#[cfg(feature = "vanity")]
+pub mod vanity;
 
 /// A vanilla Ed25519 key pair
 #[derive(Debug)]
@@ -164,10 +170,21 @@ impl EncodableKeypair for Keypair {
 }
 
 /// Reads a JSON-encoded `Keypair` from a `Reader` implementor
+///
+/// With the `keystore` feature, an encrypted keypair is detected and
+/// reported as [`keystore::KeystoreError::PassphraseRequired`].
 pub fn read_keypair<R: Read>(reader: &mut R) -> Result<Keypair, Box<dyn error::Error>> {
     let mut buffer = String::new();
     reader.read_to_string(&mut buffer)?;
+    parse_keypair(&buffer)
+}
+
+pub(crate) fn parse_keypair(buffer: &str) -> Result<Keypair, Box<dyn error::Error>> {
     let trimmed = buffer.trim();
+    #[cfg(feature = "keystore")]
+    if keystore::EncryptedKeypair::is_encrypted(trimmed.as_bytes()) {
+        return Err(keystore::KeystoreError::PassphraseRequired.into());
+    }
     if !trimmed.starts_with('[') || !trimmed.ends_with(']') {
         return Err(std::io::Error::new(
             std::io::ErrorKind::InvalidData,
@@ -259,6 +276,32 @@ pub fn keypair_from_seed_phrase_and_passphrase(
     ))
 }
 
+/// Constructs a `Keypair` from a BIP-39 seed phrase and passphrase, rejecting
+/// phrases that are not valid mnemonics.
+///
+/// Unlike [`keypair_from_seed_phrase_and_passphrase`], which accepts any
+/// string, this checks that every word is in the word list and that the
+/// checksum matches, so that a mistyped phrase is not silently turned into a
+/// different keypair. The phrase is checked against each word list enabled in
+/// `solana-seed-phrase` and the first one whose checksum matches is used. The
+/// seed is derived from the normalized phrase and passphrase as in BIP-39, so
+/// extra whitespace or precomposed characters give the same keypair.
+pub fn keypair_from_seed_phrase_and_passphrase_strict(
+    seed_phrase: &str,
+    passphrase: &str,
+) -> Result<Keypair, Box<dyn core::error::Error>> {
+    let mnemonic = Language::ALL
+        .iter()
+        .find_map(|language| Mnemonic::from_phrase(seed_phrase, *language).ok())
+        .map(Ok)
+        .unwrap_or_else(|| {
+            // Report the error of the language the words belong to.
+            let language = Language::detect(seed_phrase).unwrap_or(Language::English);
+            Mnemonic::from_phrase(seed_phrase, language)
+        })?;
+    keypair_from_seed(&mnemonic.to_seed(passphrase))
+}
+
 #[cfg(test)]
 mod tests {
     use {
@@ -427,6 +470,80 @@ mod tests {
         assert_eq!(keypair.pubkey(), expected_keypair.pubkey());
     }
 
+    #[test]
+    fn test_keypair_from_seed_phrase_and_passphrase_strict() {
+        let mnemonic = Mnemonic::new(MnemonicType::Words24, Language::English);
+        let passphrase = "42";
+        let keypair =
+            keypair_from_seed_phrase_and_passphrase_strict(mnemonic.phrase(), passphrase).unwrap();
+        assert_eq!(
+            keypair.pubkey(),
+            keypair_from_seed_phrase_and_passphrase(mnemonic.phrase(), passphrase)
+                .unwrap()
+                .pubkey()
+        );
+
+        // Swapping two words keeps every word valid but breaks the checksum.
+        let bad_phrase =
+            "legal winner thank year wave sausage worth useful legal winner yellow thank";
+        assert!(keypair_from_seed_phrase_and_passphrase(bad_phrase, passphrase).is_ok());
+        assert!(keypair_from_seed_phrase_and_passphrase_strict(bad_phrase, passphrase).is_err());
+        assert!(keypair_from_seed_phrase_and_passphrase_strict("not a seed phrase", "").is_err());
+    }
+
+    #[test]
+    fn test_keypair_from_seed_phrase_and_passphrase_strict_normalizes() {
+        let phrase = "legal winner thank year wave sausage worth useful legal winner thank yellow";
+        let expected = keypair_from_seed_phrase_and_passphrase(phrase, "").unwrap();
+
+        let spaced =
+            "legal  winner thank year wave sausage worth useful legal winner\tthank yellow\n";
+        assert_eq!(
+            keypair_from_seed_phrase_and_passphrase_strict(spaced, "").unwrap(),
+            expected
+        );
+
+        // BIP-39 derives the seed from the NFKD form of the passphrase.
+        let decomposed = keypair_from_seed_phrase_and_passphrase(phrase, "cafe\u{301}").unwrap();
+        assert_eq!(
+            keypair_from_seed_phrase_and_passphrase_strict(phrase, "caf\u{e9}").unwrap(),
+            decomposed
+        );
+    }
+
+    #[test]
+    fn test_keypair_from_seed_phrase_and_passphrase_strict_shared_words() {
+        use solana_seed_phrase::{Language, Mnemonic};
+
+        // Build a French phrase whose words are all in the English word list
+        // too, but whose checksum only matches in French.
+        let shared: Vec<_> = Language::French
+            .word_list()
+            .iter()
+            .copied()
+            .filter(|word| Language::English.index_of(word).is_some())
+            .collect();
+        let phrase = shared
+            .iter()
+            .map(|last| {
+                let mut words = shared[..11].to_vec();
+                words.push(last);
+                words.join(" ")
+            })
+            .find(|phrase| {
+                Mnemonic::validate(phrase, Language::French).is_ok()
+                    && Mnemonic::validate(phrase, Language::English).is_err()
+            })
+            .unwrap();
+        assert_eq!(Language::detect(&phrase), Some(Language::English));
+
+        let mnemonic = Mnemonic::from_phrase(&phrase, Language::French).unwrap();
+        assert_eq!(
+            keypair_from_seed_phrase_and_passphrase_strict(&phrase, "").unwrap(),
+            keypair_from_seed(&mnemonic.to_seed("")).unwrap()
+        );
+    }
+
     #[test]
     fn test_base58() {
         let keypair = keypair_from_seed(&[0u8; 32]).unwrap();
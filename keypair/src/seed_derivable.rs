@@ -47,3 +47,31 @@ fn bip32_derived_keypair(
         .and_then(|extended| extended.derive(&derivation_path))?;
     Ok(Keypair(extended.signing_key))
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        solana_derivation_path::slip10::{discover_accounts, ExtendedSigningKey},
+        solana_signer::Signer,
+    };
+
+    #[test]
+    fn test_slip10_matches_bip32_derivation() {
+        let seed = [42; 64];
+        let accounts =
+            discover_accounts(&seed, 1, |path, _| path.account().unwrap().to_u32() < 5).unwrap();
+        assert_eq!(accounts.len(), 5);
+        for account in accounts {
+            let keypair =
+                keypair_from_seed_and_derivation_path(&seed, Some(account.path.clone())).unwrap();
+            assert_eq!(keypair.pubkey().to_bytes(), account.public_key);
+            assert_eq!(
+                ExtendedSigningKey::from_seed_and_path(&seed, &account.path)
+                    .unwrap()
+                    .public_key(),
+                account.public_key
+            );
+        }
+    }
+}
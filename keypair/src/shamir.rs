@@ -0,0 +1,402 @@
+//! Shamir secret sharing of keypairs.
+//!
+//! [`split_keypair`] splits the secret key of a [`Keypair`] into shares, any
+//! `threshold` of which recover it with [`recover_keypair`]. Fewer shares
+//! reveal nothing about the secret key.
+//!
+//! Each byte of the secret key is shared independently over GF(2^8). A share
+//! is encoded as:
+//!
+//! | Field     | Size | Notes                                           |
+//! |-----------|------|-------------------------------------------------|
+//! | threshold | 1    | number of shares needed to recover the secret   |
+//! | index     | 1    | x coordinate of the share, from 1 to 255        |
+//! | value     | 32   | the shared secret key bytes                     |
+//! | checksum  | 4    | first bytes of the SHA-256 of the fields above  |
+
+use {
+    crate::Keypair,
+    base64::{prelude::BASE64_STANDARD, Engine},
+    sha2::{Digest, Sha256},
+    solana_address::Address,
+    solana_signer::Signer,
+    std::{error, fmt, str::FromStr},
+    zeroize::{Zeroize, Zeroizing},
+};
+
+const VALUE_LEN: usize = Keypair::SECRET_KEY_LENGTH;
+const CHECKSUM_LEN: usize = 4;
+
+/// Size of an encoded share in bytes.
+pub const KEYPAIR_SHARE_LENGTH: usize = 2 + VALUE_LEN + CHECKSUM_LEN;
+
+/// Domain separator of the share checksum, so that shares of other kinds of
+/// secrets are rejected.
+const CHECKSUM_DOMAIN: &[u8] = b"solana-keypair-share";
+
+/// Errors produced when splitting or recovering a keypair.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShareError {
+    /// The threshold is zero or larger than the number of shares.
+    InvalidThreshold,
+    /// The encoded share does not have the expected length.
+    InvalidLength,
+    /// The encoded share is not valid base64.
+    InvalidEncoding,
+    /// The share checksum does not match.
+    InvalidChecksum,
+    /// The share index is zero.
+    InvalidIndex,
+    /// Fewer shares than the threshold were provided.
+    NotEnoughShares,
+    /// Two shares have the same index.
+    DuplicateIndex(u8),
+    /// The shares were split with different thresholds.
+    ThresholdMismatch,
+    /// The recovered keypair does not match the expected pubkey.
+    PubkeyMismatch,
+}
+
+impl error::Error for ShareError {}
+
+impl fmt::Display for ShareError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidThreshold => f.write_str("invalid share threshold"),
+            Self::InvalidLength => f.write_str("invalid share length"),
+            Self::InvalidEncoding => f.write_str("invalid share encoding"),
+            Self::InvalidChecksum => f.write_str("invalid share checksum"),
+            Self::InvalidIndex => f.write_str("invalid share index"),
+            Self::NotEnoughShares => f.write_str("not enough shares to recover the keypair"),
+            Self::DuplicateIndex(index) => write!(f, "duplicate share index {index}"),
+            Self::ThresholdMismatch => f.write_str("shares have different thresholds"),
+            Self::PubkeyMismatch => f.write_str("recovered keypair does not match the pubkey"),
+        }
+    }
+}
+
+/// One share of a keypair's secret key.
+#[derive(Clone, PartialEq, Eq)]
+pub struct KeypairShare {
+    threshold: u8,
+    index: u8,
+    value: [u8; VALUE_LEN],
+}
+
+impl fmt::Debug for KeypairShare {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("KeypairShare")
+            .field("threshold", &self.threshold)
+            .field("index", &self.index)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for KeypairShare {
+    fn drop(&mut self) {
+        self.value.zeroize();
+    }
+}
+
+impl KeypairShare {
+    /// Returns the number of shares needed to recover the keypair.
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// Returns the index of the share, from 1 to 255.
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    fn checksum(bytes: &[u8]) -> [u8; CHECKSUM_LEN] {
+        let hash = Sha256::new()
+            .chain_update(CHECKSUM_DOMAIN)
+            .chain_update(bytes)
+            .finalize();
+        let mut checksum = [0; CHECKSUM_LEN];
+        checksum.copy_from_slice(&hash[..CHECKSUM_LEN]);
+        checksum
+    }
+
+    /// Encodes the share with its checksum.
+    pub fn to_bytes(&self) -> Zeroizing<[u8; KEYPAIR_SHARE_LENGTH]> {
+        let mut bytes = Zeroizing::new([0; KEYPAIR_SHARE_LENGTH]);
+        let (body, checksum) = bytes.split_at_mut(KEYPAIR_SHARE_LENGTH - CHECKSUM_LEN);
+        body[0] = self.threshold;
+        body[1] = self.index;
+        body[2..].copy_from_slice(&self.value);
+        checksum.copy_from_slice(&Self::checksum(body));
+        bytes
+    }
+
+    /// Decodes a share, verifying its checksum.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ShareError> {
+        let bytes: &[u8; KEYPAIR_SHARE_LENGTH] =
+            bytes.try_into().map_err(|_| ShareError::InvalidLength)?;
+        let (body, checksum) = bytes.split_at(KEYPAIR_SHARE_LENGTH - CHECKSUM_LEN);
+        if Self::checksum(body) != checksum {
+            return Err(ShareError::InvalidChecksum);
+        }
+        let share = Self {
+            threshold: body[0],
+            index: body[1],
+            value: body[2..]
+                .try_into()
+                .map_err(|_| ShareError::InvalidLength)?,
+        };
+        if share.threshold == 0 {
+            return Err(ShareError::InvalidThreshold);
+        }
+        if share.index == 0 {
+            return Err(ShareError::InvalidIndex);
+        }
+        Ok(share)
+    }
+}
+
+/// Displays the share as base64.
+impl fmt::Display for KeypairShare {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&Zeroizing::new(BASE64_STANDARD.encode(*self.to_bytes())))
+    }
+}
+
+impl FromStr for KeypairShare {
+    type Err = ShareError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = Zeroizing::new(
+            BASE64_STANDARD
+                .decode(s.trim())
+                .map_err(|_| ShareError::InvalidEncoding)?,
+        );
+        Self::from_bytes(&bytes)
+    }
+}
+
+/// Multiplies two elements of GF(2^8) with the AES polynomial, without
+/// branching on their values.
+#[allow(clippy::arithmetic_side_effects)]
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    for _ in 0..8 {
+        product ^= a & (b & 1).wrapping_neg();
+        let carry = (a >> 7).wrapping_neg();
+        a = (a << 1) ^ (carry & 0x1b);
+        b >>= 1;
+    }
+    product
+}
+
+/// Inverts a non-zero element of GF(2^8), as `a^254`.
+fn gf_inv(a: u8) -> u8 {
+    let a2 = gf_mul(a, a);
+    let a4 = gf_mul(a2, a2);
+    let a8 = gf_mul(a4, a4);
+    let a16 = gf_mul(a8, a8);
+    let a32 = gf_mul(a16, a16);
+    let a64 = gf_mul(a32, a32);
+    let a128 = gf_mul(a64, a64);
+    [a2, a4, a8, a16, a32, a64].into_iter().fold(a128, gf_mul)
+}
+
+/// Splits the secret key of `keypair` into `shares` shares, any `threshold`
+/// of which recover it.
+pub fn split_keypair(
+    keypair: &Keypair,
+    threshold: u8,
+    shares: u8,
+) -> Result<Vec<KeypairShare>, ShareError> {
+    if threshold == 0 || threshold > shares {
+        return Err(ShareError::InvalidThreshold);
+    }
+    let secret = keypair.secret_bytes();
+    // One polynomial per secret byte, with the byte as constant term.
+    let coefficients: Vec<Zeroizing<[u8; VALUE_LEN]>> = (1..threshold)
+        .map(|_| Zeroizing::new(rand::random()))
+        .collect();
+    Ok((1..=shares)
+        .map(|index| {
+            let mut value = [0; VALUE_LEN];
+            for (byte, (value, secret)) in value.iter_mut().zip(secret).enumerate() {
+                // Horner's method, from the highest degree coefficient.
+                *value = coefficients
+                    .iter()
+                    .rev()
+                    .fold(0, |acc, coefficient| gf_mul(acc, index) ^ coefficient[byte]);
+                *value = gf_mul(*value, index) ^ secret;
+            }
+            KeypairShare {
+                threshold,
+                index,
+                value,
+            }
+        })
+        .collect())
+}
+
+/// Recovers a keypair from at least `threshold` of its shares and checks that
+/// it matches `expected_pubkey`.
+pub fn recover_keypair(
+    shares: &[KeypairShare],
+    expected_pubkey: &Address,
+) -> Result<Keypair, ShareError> {
+    let threshold = shares.first().ok_or(ShareError::NotEnoughShares)?.threshold;
+    if shares.iter().any(|share| share.threshold != threshold) {
+        return Err(ShareError::ThresholdMismatch);
+    }
+    for (position, share) in shares.iter().enumerate() {
+        if shares[..position]
+            .iter()
+            .any(|other| other.index == share.index)
+        {
+            return Err(ShareError::DuplicateIndex(share.index));
+        }
+    }
+    let shares = shares
+        .get(..usize::from(threshold))
+        .ok_or(ShareError::NotEnoughShares)?;
+
+    // Lagrange interpolation at zero, where subtraction is xor.
+    let mut secret = Zeroizing::new([0; VALUE_LEN]);
+    for share in shares {
+        let (numerator, denominator) = shares
+            .iter()
+            .filter(|other| other.index != share.index)
+            .fold((1, 1), |(numerator, denominator), other| {
+                (
+                    gf_mul(numerator, other.index),
+                    gf_mul(denominator, other.index ^ share.index),
+                )
+            });
+        let basis = gf_mul(numerator, gf_inv(denominator));
+        for (secret, value) in secret.iter_mut().zip(&share.value) {
+            *secret ^= gf_mul(basis, *value);
+        }
+    }
+
+    let keypair = Keypair::new_from_array(*secret);
+    if keypair.pubkey() != *expected_pubkey {
+        return Err(ShareError::PubkeyMismatch);
+    }
+    Ok(keypair)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gf_arithmetic() {
+        // Multiplication example from FIPS-197.
+        assert_eq!(gf_mul(0x57, 0x83), 0xc1);
+        for a in 1..=u8::MAX {
+            assert_eq!(gf_mul(a, gf_inv(a)), 1);
+            assert_eq!(gf_mul(a, 1), a);
+            assert_eq!(gf_mul(a, 0), 0);
+        }
+    }
+
+    #[test]
+    fn test_split_and_recover() {
+        let keypair = Keypair::new();
+        let pubkey = keypair.pubkey();
+        let shares = split_keypair(&keypair, 3, 5).unwrap();
+        assert_eq!(shares.len(), 5);
+        assert!(shares.iter().all(|share| share.threshold() == 3));
+        assert_eq!(
+            shares.iter().map(KeypairShare::index).collect::<Vec<_>>(),
+            vec![1, 2, 3, 4, 5]
+        );
+
+        // Any three shares, in any order.
+        for subset in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+            let subset: Vec<_> = subset.iter().map(|i| shares[*i].clone()).collect();
+            assert_eq!(recover_keypair(&subset, &pubkey).unwrap(), keypair);
+        }
+        assert_eq!(recover_keypair(&shares, &pubkey).unwrap(), keypair);
+        assert_eq!(
+            recover_keypair(&shares[..2], &pubkey).unwrap_err(),
+            ShareError::NotEnoughShares
+        );
+        assert_eq!(
+            recover_keypair(&shares, &Keypair::new().pubkey()).unwrap_err(),
+            ShareError::PubkeyMismatch
+        );
+
+        let single = split_keypair(&keypair, 1, 1).unwrap();
+        assert_eq!(recover_keypair(&single, &pubkey).unwrap(), keypair);
+    }
+
+    #[test]
+    fn test_invalid_split_and_recover() {
+        let keypair = Keypair::new();
+        let pubkey = keypair.pubkey();
+        assert_eq!(
+            split_keypair(&keypair, 0, 3).unwrap_err(),
+            ShareError::InvalidThreshold
+        );
+        assert_eq!(
+            split_keypair(&keypair, 4, 3).unwrap_err(),
+            ShareError::InvalidThreshold
+        );
+        let shares = split_keypair(&keypair, 2, u8::MAX).unwrap();
+        assert_eq!(shares.last().unwrap().index(), u8::MAX);
+        assert_eq!(recover_keypair(&shares[253..], &pubkey).unwrap(), keypair);
+
+        let shares = split_keypair(&keypair, 2, 3).unwrap();
+        assert_eq!(
+            recover_keypair(&[], &pubkey).unwrap_err(),
+            ShareError::NotEnoughShares
+        );
+        assert_eq!(
+            recover_keypair(&[shares[1].clone(), shares[1].clone()], &pubkey).unwrap_err(),
+            ShareError::DuplicateIndex(2)
+        );
+        let other = split_keypair(&keypair, 3, 3).unwrap();
+        assert_eq!(
+            recover_keypair(&[shares[0].clone(), other[1].clone()], &pubkey).unwrap_err(),
+            ShareError::ThresholdMismatch
+        );
+    }
+
+    #[test]
+    fn test_encoding() {
+        let keypair = Keypair::new();
+        let shares = split_keypair(&keypair, 2, 3).unwrap();
+        let encoded: Vec<String> = shares.iter().map(ToString::to_string).collect();
+        let decoded: Vec<KeypairShare> = encoded.iter().map(|s| s.parse().unwrap()).collect();
+        assert_eq!(decoded, shares);
+        assert_eq!(
+            recover_keypair(&decoded[1..], &keypair.pubkey()).unwrap(),
+            keypair
+        );
+        assert!(!format!("{:?}", shares[0]).contains(&format!("{:?}", shares[0].value)));
+
+        let bytes = shares[0].to_bytes();
+        let mut corrupted = *bytes;
+        corrupted[5] ^= 1;
+        assert_eq!(
+            KeypairShare::from_bytes(&corrupted).unwrap_err(),
+            ShareError::InvalidChecksum
+        );
+        assert_eq!(
+            KeypairShare::from_bytes(&bytes[1..]).unwrap_err(),
+            ShareError::InvalidLength
+        );
+        assert_eq!(
+            "not base64!".parse::<KeypairShare>().unwrap_err(),
+            ShareError::InvalidEncoding
+        );
+
+        let mut zero_index = *bytes;
+        zero_index[1] = 0;
+        let checksum = KeypairShare::checksum(&zero_index[..KEYPAIR_SHARE_LENGTH - 4]);
+        zero_index[KEYPAIR_SHARE_LENGTH - 4..].copy_from_slice(&checksum);
+        assert_eq!(
+            KeypairShare::from_bytes(&zero_index).unwrap_err(),
+            ShareError::InvalidIndex
+        );
+    }
+}
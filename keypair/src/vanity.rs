@@ -0,0 +1,67 @@
+//! Multi-threaded search for keypairs with a vanity address.
+//!
+//! [`search_keypair`] generates keypairs until the base58 encoding of one's
+//! public key matches a [`VanityPattern`]. Patterns and search options are
+//! shared with the seed and program address searches in
+//! [`solana_address::vanity`], which are re-exported here.
+
+pub use solana_address::vanity::{
+    search_create_with_seed, search_program_address, ProgramAddressMatch, SearchOptions,
+    SearchProgress, SeedMatch, VanityError, VanityPattern,
+};
+use {crate::Keypair, solana_signer::Signer};
+
+/// Searches for a keypair whose public key matches `pattern`.
+///
+/// Each candidate's secret key is derived from the search seed, so a search
+/// run with [`SearchOptions::deterministic`] yields a keypair that anyone who
+/// knows the seed can recreate. Only use that mode in tests.
+pub fn search_keypair(
+    pattern: &VanityPattern,
+    options: &SearchOptions,
+) -> Result<Keypair, VanityError> {
+    solana_address::vanity::search(pattern, options, |bytes| {
+        let keypair = Keypair::new_from_array(*bytes);
+        Some((keypair.pubkey(), keypair))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        core::num::NonZeroUsize,
+        std::sync::atomic::{AtomicBool, Ordering},
+    };
+
+    #[test]
+    fn test_search_keypair() {
+        let pattern = VanityPattern::new("s", "", true).unwrap();
+        let options = SearchOptions::default().deterministic([42; 32]);
+
+        let keypair = search_keypair(&pattern, &options).unwrap();
+        assert!(pattern.matches(&keypair.pubkey()));
+        for threads in [1, 3] {
+            let options = options.threads(NonZeroUsize::new(threads).unwrap());
+            assert_eq!(search_keypair(&pattern, &options).unwrap(), keypair);
+        }
+
+        // Random searches still match the pattern.
+        let random = search_keypair(&pattern, &SearchOptions::default()).unwrap();
+        assert!(pattern.matches(&random.pubkey()));
+    }
+
+    #[test]
+    fn test_search_keypair_cancel() {
+        let pattern = VanityPattern::starts_with("SoLanaSoLana").unwrap();
+        let cancel = AtomicBool::new(false);
+        let progress = |_: SearchProgress| cancel.store(true, Ordering::Relaxed);
+        let options = SearchOptions::default()
+            .cancel_flag(&cancel)
+            .on_progress(&progress);
+        assert_eq!(
+            search_keypair(&pattern, &options).map(|keypair| keypair.pubkey()),
+            Err(VanityError::Cancelled)
+        );
+    }
+}
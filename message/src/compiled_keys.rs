@@ -168,6 +168,29 @@ impl CompiledKeys {
         )))
     }
 
+    /// Returns the number of writable and readonly keys that
+    /// `try_extract_table_lookup` would load from the lookup table.
+    pub(crate) fn count_keys_found_in_lookup_table(
+        &self,
+        lookup_table_account: &AddressLookupTableAccount,
+    ) -> (usize, usize) {
+        self.key_meta_map
+            .iter()
+            .filter(|(key, meta)| {
+                !meta.is_signer
+                    && !meta.is_invoked
+                    && !meta.is_nonce
+                    && lookup_table_account.addresses.contains(key)
+            })
+            .fold((0, 0), |(writable, readonly), (_, meta)| {
+                if meta.is_writable {
+                    (writable.saturating_add(1), readonly)
+                } else {
+                    (writable, readonly.saturating_add(1))
+                }
+            })
+    }
+
     fn try_drain_keys_found_in_lookup_table(
         &mut self,
         lookup_table_addresses: &[Address],
@@ -556,6 +579,52 @@ mod tests {
         assert!(!compiled_keys.key_meta_map.contains_key(&keys[3]));
     }
 
+    #[test]
+    fn test_count_keys_found_in_lookup_table() {
+        let keys = vec![
+            Address::new_unique(),
+            Address::new_unique(),
+            Address::new_unique(),
+            Address::new_unique(),
+            Address::new_unique(),
+            Address::new_unique(),
+            Address::new_unique(),
+        ];
+
+        let compiled_keys = CompiledKeys {
+            payer: None,
+            key_meta_map: BTreeMap::from([
+                (keys[0], (KeyFlags::SIGNER | KeyFlags::WRITABLE).into()),
+                (keys[1], KeyFlags::SIGNER.into()),
+                (keys[2], KeyFlags::WRITABLE.into()),
+                (keys[3], KeyFlags::empty().into()),
+                (keys[4], (KeyFlags::INVOKED | KeyFlags::WRITABLE).into()),
+                (keys[5], (KeyFlags::NONCE | KeyFlags::WRITABLE).into()),
+                (keys[6], KeyFlags::empty().into()),
+            ]),
+        };
+
+        let lookup_table_account = AddressLookupTableAccount {
+            key: Address::new_unique(),
+            addresses: [keys.clone(), keys[..4].to_vec()].concat(),
+        };
+
+        assert_eq!(
+            compiled_keys.count_keys_found_in_lookup_table(&lookup_table_account),
+            (1, 2)
+        );
+
+        let lookup_table_account = AddressLookupTableAccount {
+            key: Address::new_unique(),
+            addresses: keys[..2].to_vec(),
+        };
+
+        assert_eq!(
+            compiled_keys.count_keys_found_in_lookup_table(&lookup_table_account),
+            (0, 0)
+        );
+    }
+
     #[test]
     fn test_try_extract_table_lookup_returns_none() {
         let mut compiled_keys = CompiledKeys {
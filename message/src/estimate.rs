@@ -0,0 +1,431 @@
+//! Serialized transaction size estimation across message formats.
+//!
+//! A set of [`Instruction`]s compiles to a differently sized transaction
+//! depending on the message format used to encode it: [`legacy`] and [`v0`]
+//! transactions must fit in a single network packet ([`PACKET_DATA_SIZE`]),
+//! while [`v1`] transactions are limited to [`v1::MAX_TRANSACTION_SIZE`] and
+//! a number of per-message counts.
+//!
+//! [`TransactionSizeEstimate`] compiles the instructions for every format with
+//! the same key compilation used by the message constructors and reports the
+//! exact serialized size of each resulting transaction, together with the
+//! limits that it exceeds.
+//!
+//! [`Instruction`]: solana_instruction::Instruction
+//! [`legacy`]: crate::legacy
+//! [`v0`]: crate::v0
+//! [`v1`]: crate::v1
+
+use {
+    crate::{
+        compiled_instruction::CompiledInstruction, legacy::Message as LegacyMessage, v0, v1,
+        AddressLookupTableAccount, CompileError, MessageHeader,
+    },
+    alloc::vec::Vec,
+    core::mem::size_of,
+    solana_address::Address,
+    solana_hash::Hash,
+    solana_instruction::Instruction,
+};
+
+/// Maximum over-the-wire size of a legacy or v0 transaction.
+///
+/// Inlined to avoid a `solana_packet` dependency.
+pub const PACKET_DATA_SIZE: usize = 1280 - 40 - 8;
+
+/// A limit exceeded by a compiled transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeLimit {
+    /// The serialized transaction is larger than the maximum transaction size
+    /// of its format.
+    TransactionSize,
+    /// The message references more than [`v1::MAX_ADDRESSES`] accounts.
+    TooManyAddresses,
+    /// The message requires more than [`v1::MAX_SIGNATURES`] signatures.
+    TooManySignatures,
+    /// The message has more than [`v1::MAX_INSTRUCTIONS`] instructions.
+    TooManyInstructions,
+    /// An instruction references more than `u8::MAX` accounts.
+    InstructionAccountsTooLarge,
+    /// An instruction has more than `u16::MAX` bytes of data.
+    InstructionDataTooLarge,
+}
+
+/// Size of a transaction compiled for a single message format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatEstimate {
+    /// Number of signatures required by the message.
+    pub num_signatures: usize,
+    /// Size of the serialized message in bytes, including any version prefix.
+    pub message_size: usize,
+    /// Size of the serialized transaction in bytes, including signatures.
+    pub transaction_size: usize,
+    /// Maximum transaction size allowed for the format.
+    pub max_transaction_size: usize,
+    /// Limits exceeded by the transaction.
+    pub exceeded_limits: Vec<SizeLimit>,
+}
+
+impl FormatEstimate {
+    /// Estimates the transaction size of a compiled legacy message.
+    pub fn legacy(message: &LegacyMessage) -> Self {
+        let message_size = legacy_message_size(message);
+        packet_estimate(&message.header, message_size)
+    }
+
+    /// Estimates the transaction size of a compiled v0 message.
+    pub fn v0(message: &v0::Message) -> Self {
+        let message_size = v0_message_size(message);
+        packet_estimate(&message.header, message_size)
+    }
+
+    /// Estimates the transaction size of a compiled v1 message.
+    pub fn v1(message: &v1::Message) -> Self {
+        let num_signatures = usize::from(message.header.num_required_signatures);
+        // version prefix + message
+        let message_size = message.size().saturating_add(size_of::<u8>());
+        // v1 signatures are written as a fixed length array without a length prefix
+        let transaction_size =
+            message_size.saturating_add(num_signatures.saturating_mul(v1::SIGNATURE_SIZE));
+
+        let mut exceeded_limits = Vec::new();
+        if transaction_size > v1::MAX_TRANSACTION_SIZE {
+            exceeded_limits.push(SizeLimit::TransactionSize);
+        }
+        if message.account_keys.len() > usize::from(v1::MAX_ADDRESSES) {
+            exceeded_limits.push(SizeLimit::TooManyAddresses);
+        }
+        if num_signatures > usize::from(v1::MAX_SIGNATURES) {
+            exceeded_limits.push(SizeLimit::TooManySignatures);
+        }
+        if message.instructions.len() > usize::from(v1::MAX_INSTRUCTIONS) {
+            exceeded_limits.push(SizeLimit::TooManyInstructions);
+        }
+        if message
+            .instructions
+            .iter()
+            .any(|ix| ix.accounts.len() > usize::from(u8::MAX))
+        {
+            exceeded_limits.push(SizeLimit::InstructionAccountsTooLarge);
+        }
+        if message
+            .instructions
+            .iter()
+            .any(|ix| ix.data.len() > usize::from(u16::MAX))
+        {
+            exceeded_limits.push(SizeLimit::InstructionDataTooLarge);
+        }
+
+        Self {
+            num_signatures,
+            message_size,
+            transaction_size,
+            max_transaction_size: v1::MAX_TRANSACTION_SIZE,
+            exceeded_limits,
+        }
+    }
+
+    /// Returns `true` if the transaction does not exceed any limit.
+    pub fn fits(&self) -> bool {
+        self.exceeded_limits.is_empty()
+    }
+
+    /// Number of bytes left before reaching the maximum transaction size, or
+    /// `None` if the transaction is already larger than the maximum.
+    pub fn remaining_bytes(&self) -> Option<usize> {
+        self.max_transaction_size.checked_sub(self.transaction_size)
+    }
+}
+
+/// Serialized sizes of a set of instructions compiled for each message format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionSizeEstimate {
+    /// Estimate for a legacy transaction.
+    pub legacy: Result<FormatEstimate, CompileError>,
+    /// Estimate for a v0 transaction using the provided address lookup tables.
+    pub v0: Result<FormatEstimate, CompileError>,
+    /// Estimate for a v1 transaction.
+    pub v1: Result<FormatEstimate, CompileError>,
+}
+
+impl TransactionSizeEstimate {
+    /// Estimates the transaction sizes of `instructions` paid by `payer`.
+    ///
+    /// The `address_lookup_table_accounts` are only used for the v0 estimate;
+    /// an empty slice estimates a v0 transaction without table lookups.
+    pub fn new(
+        payer: &Address,
+        instructions: &[Instruction],
+        address_lookup_table_accounts: &[AddressLookupTableAccount],
+    ) -> Self {
+        Self::new_with_config(
+            payer,
+            instructions,
+            address_lookup_table_accounts,
+            v1::TransactionConfig::empty(),
+        )
+    }
+
+    /// Estimates the transaction sizes of `instructions` paid by `payer`,
+    /// using `config` for the v1 estimate.
+    pub fn new_with_config(
+        payer: &Address,
+        instructions: &[Instruction],
+        address_lookup_table_accounts: &[AddressLookupTableAccount],
+        config: v1::TransactionConfig,
+    ) -> Self {
+        Self {
+            legacy: LegacyMessage::try_compile(payer, instructions, Hash::default())
+                .map(|message| FormatEstimate::legacy(&message)),
+            v0: v0::Message::try_compile(
+                payer,
+                instructions,
+                address_lookup_table_accounts,
+                Hash::default(),
+            )
+            .map(|message| FormatEstimate::v0(&message)),
+            v1: v1::Message::try_compile_with_config(payer, instructions, Hash::default(), config)
+                .map(|message| FormatEstimate::v1(&message)),
+        }
+    }
+}
+
+fn packet_estimate(header: &MessageHeader, message_size: usize) -> FormatEstimate {
+    let num_signatures = usize::from(header.num_required_signatures);
+    let transaction_size = short_vec_encoded_len(num_signatures)
+        .saturating_add(num_signatures.saturating_mul(v1::SIGNATURE_SIZE))
+        .saturating_add(message_size);
+
+    let mut exceeded_limits = Vec::new();
+    if transaction_size > PACKET_DATA_SIZE {
+        exceeded_limits.push(SizeLimit::TransactionSize);
+    }
+
+    FormatEstimate {
+        num_signatures,
+        message_size,
+        transaction_size,
+        max_transaction_size: PACKET_DATA_SIZE,
+        exceeded_limits,
+    }
+}
+
+/// Number of bytes used by a `ShortU16` length prefix.
+pub(crate) const fn short_vec_encoded_len(len: usize) -> usize {
+    if len < 0x80 {
+        1
+    } else if len < 0x4000 {
+        2
+    } else {
+        3
+    }
+}
+
+/// Serialized size of a slice of `ShortU16` length prefixed items.
+fn short_vec_size(len: usize, item_size: usize) -> usize {
+    short_vec_encoded_len(len).saturating_add(len.saturating_mul(item_size))
+}
+
+fn compiled_instructions_size(instructions: &[CompiledInstruction]) -> usize {
+    instructions
+        .iter()
+        .fold(short_vec_encoded_len(instructions.len()), |size, ix| {
+            size.saturating_add(size_of::<u8>()) // program id index
+                .saturating_add(short_vec_size(ix.accounts.len(), size_of::<u8>()))
+                .saturating_add(short_vec_size(ix.data.len(), size_of::<u8>()))
+        })
+}
+
+/// Serialized size of a legacy message.
+pub(crate) fn legacy_message_size(message: &LegacyMessage) -> usize {
+    size_of::<MessageHeader>()
+        .saturating_add(short_vec_size(
+            message.account_keys.len(),
+            size_of::<Address>(),
+        ))
+        .saturating_add(size_of::<Hash>())
+        .saturating_add(compiled_instructions_size(&message.instructions))
+}
+
+/// Serialized size of a v0 message, including the version prefix.
+pub(crate) fn v0_message_size(message: &v0::Message) -> usize {
+    let address_table_lookups_size = message.address_table_lookups.iter().fold(
+        short_vec_encoded_len(message.address_table_lookups.len()),
+        |size, lookup| {
+            size.saturating_add(size_of::<Address>())
+                .saturating_add(short_vec_size(
+                    lookup.writable_indexes.len(),
+                    size_of::<u8>(),
+                ))
+                .saturating_add(short_vec_size(
+                    lookup.readonly_indexes.len(),
+                    size_of::<u8>(),
+                ))
+        },
+    );
+
+    size_of::<u8>() // version prefix
+        .saturating_add(size_of::<MessageHeader>())
+        .saturating_add(short_vec_size(
+            message.account_keys.len(),
+            size_of::<Address>(),
+        ))
+        .saturating_add(size_of::<Hash>())
+        .saturating_add(compiled_instructions_size(&message.instructions))
+        .saturating_add(address_table_lookups_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::VersionedMessage, alloc::vec, solana_instruction::AccountMeta};
+
+    static_assertions::const_assert_eq!(PACKET_DATA_SIZE, solana_packet::PACKET_DATA_SIZE);
+
+    /// Serialized size of a transaction with default signatures, as written by
+    /// the transaction encoders.
+    #[allow(clippy::arithmetic_side_effects)]
+    fn serialized_transaction_size(message: VersionedMessage) -> usize {
+        let num_signatures = usize::from(message.header().num_required_signatures);
+        let message_size = message.serialize().len();
+        match message {
+            VersionedMessage::V1(_) => message_size + num_signatures * v1::SIGNATURE_SIZE,
+            _ => {
+                short_vec_encoded_len(num_signatures)
+                    + num_signatures * v1::SIGNATURE_SIZE
+                    + message_size
+            }
+        }
+    }
+
+    fn transfer_like(num_accounts: usize, data_len: usize) -> Instruction {
+        Instruction::new_with_bytes(
+            Address::new_unique(),
+            &vec![7; data_len],
+            (0..num_accounts)
+                .map(|_| AccountMeta::new(Address::new_unique(), false))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_short_vec_encoded_len() {
+        assert_eq!(short_vec_encoded_len(0), 1);
+        assert_eq!(short_vec_encoded_len(0x7f), 1);
+        assert_eq!(short_vec_encoded_len(0x80), 2);
+        assert_eq!(short_vec_encoded_len(0x3fff), 2);
+        assert_eq!(short_vec_encoded_len(0x4000), 3);
+    }
+
+    #[test]
+    fn test_estimate_matches_serialized_sizes() {
+        let payer = Address::new_unique();
+        let signer = Address::new_unique();
+        let mut instructions = vec![transfer_like(3, 10), transfer_like(20, 200)];
+        instructions[0]
+            .accounts
+            .push(AccountMeta::new_readonly(signer, true));
+        let lookup_table = AddressLookupTableAccount {
+            key: Address::new_unique(),
+            addresses: instructions[1]
+                .accounts
+                .iter()
+                .map(|meta| meta.pubkey)
+                .collect(),
+        };
+        let config = v1::TransactionConfig::empty()
+            .with_compute_unit_limit(10_000)
+            .with_priority_fee(5);
+
+        let estimate = TransactionSizeEstimate::new_with_config(
+            &payer,
+            &instructions,
+            core::slice::from_ref(&lookup_table),
+            config,
+        );
+
+        let legacy = LegacyMessage::new(&instructions, Some(&payer));
+        let v0 = v0::Message::try_compile(&payer, &instructions, &[lookup_table], Hash::default())
+            .unwrap();
+        let v1 =
+            v1::Message::try_compile_with_config(&payer, &instructions, Hash::default(), config)
+                .unwrap();
+
+        let legacy_estimate = estimate.legacy.unwrap();
+        assert_eq!(legacy_estimate.num_signatures, 2);
+        assert_eq!(legacy_estimate.message_size, legacy.serialize().len());
+        assert_eq!(
+            legacy_estimate.transaction_size,
+            serialized_transaction_size(VersionedMessage::Legacy(legacy))
+        );
+
+        let v0_estimate = estimate.v0.unwrap();
+        assert_eq!(v0_estimate.message_size, v0.serialize().len());
+        assert_eq!(
+            v0_estimate.transaction_size,
+            serialized_transaction_size(VersionedMessage::V0(v0))
+        );
+        assert!(v0_estimate.transaction_size < legacy_estimate.transaction_size);
+
+        let v1_estimate = estimate.v1.unwrap();
+        assert_eq!(v1_estimate.message_size, v1.serialize().len());
+        assert_eq!(
+            v1_estimate.transaction_size,
+            serialized_transaction_size(VersionedMessage::V1(v1))
+        );
+        assert!(v1_estimate.fits());
+    }
+
+    #[test]
+    fn test_estimate_reports_packet_size_limit() {
+        let payer = Address::new_unique();
+        let instructions = vec![transfer_like(2, 1200)];
+
+        let estimate = TransactionSizeEstimate::new(&payer, &instructions, &[]);
+
+        let legacy = estimate.legacy.unwrap();
+        assert_eq!(legacy.exceeded_limits, vec![SizeLimit::TransactionSize]);
+        assert_eq!(legacy.remaining_bytes(), None);
+        assert!(!estimate.v0.unwrap().fits());
+
+        let v1 = estimate.v1.unwrap();
+        assert!(v1.fits());
+        assert_eq!(
+            v1.remaining_bytes(),
+            Some(v1::MAX_TRANSACTION_SIZE - v1.transaction_size)
+        );
+    }
+
+    #[test]
+    fn test_estimate_reports_v1_limits() {
+        let payer = Address::new_unique();
+        let mut instructions: Vec<Instruction> = (0..65).map(|_| transfer_like(0, 0)).collect();
+        instructions[0].accounts = (0..12)
+            .map(|_| AccountMeta::new(Address::new_unique(), true))
+            .collect();
+
+        let estimate = TransactionSizeEstimate::new(&payer, &instructions, &[]);
+
+        let v1 = estimate.v1.unwrap();
+        assert_eq!(v1.num_signatures, 13);
+        assert_eq!(
+            v1.exceeded_limits,
+            vec![
+                SizeLimit::TooManyAddresses,
+                SizeLimit::TooManySignatures,
+                SizeLimit::TooManyInstructions,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_estimate_reports_compile_errors() {
+        let payer = Address::new_unique();
+        let instructions = vec![transfer_like(256, 0)];
+
+        let estimate = TransactionSizeEstimate::new(&payer, &instructions, &[]);
+
+        assert_eq!(estimate.legacy, Err(CompileError::AccountIndexOverflow));
+        assert_eq!(estimate.v0, Err(CompileError::AccountIndexOverflow));
+        assert_eq!(estimate.v1, Err(CompileError::AccountIndexOverflow));
+    }
+}
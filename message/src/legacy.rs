@@ -19,8 +19,10 @@ use solana_frozen_abi_macro::{frozen_abi, AbiExample, StableAbi, StableAbiSample
 use std::collections::HashSet;
 use {
     crate::{
-        compiled_instruction::CompiledInstruction, compiled_keys::CompiledKeys,
-        inline_nonce::advance_nonce_account_instruction, MessageHeader,
+        compiled_instruction::CompiledInstruction,
+        compiled_keys::{CompileError, CompiledKeys},
+        inline_nonce::advance_nonce_account_instruction,
+        AccountKeys, MessageHeader,
     },
     alloc::vec::Vec,
     core::convert::TryFrom,
@@ -477,6 +479,27 @@ impl Message {
         Self::new(&instructions, payer)
     }
 
+    /// Create a message from a `payer` public key, `recent_blockhash` and a
+    /// list of `instructions`, returning an error instead of panicking when
+    /// the account keys overflow.
+    pub(crate) fn try_compile(
+        payer: &Address,
+        instructions: &[Instruction],
+        recent_blockhash: Hash,
+    ) -> Result<Self, CompileError> {
+        let compiled_keys = CompiledKeys::compile(instructions, Some(*payer));
+        let (header, account_keys) = compiled_keys.try_into_message_components()?;
+        let instructions =
+            AccountKeys::new(&account_keys, None).try_compile_instructions(instructions)?;
+
+        Ok(Self {
+            header,
+            account_keys,
+            recent_blockhash,
+            instructions,
+        })
+    }
+
     pub fn new_with_compiled_instructions(
         num_required_signatures: u8,
         num_readonly_signed_accounts: u8,
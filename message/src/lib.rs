@@ -63,9 +63,11 @@ mod account_keys;
 mod address_loader;
 pub mod compiled_instruction;
 mod compiled_keys;
+pub mod estimate;
 pub mod inline_nonce;
 pub mod inner_instruction;
 pub mod legacy;
+pub mod pack;
 #[cfg(feature = "std")]
 mod sanitized;
 mod versions;
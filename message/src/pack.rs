@@ -0,0 +1,447 @@
+//! Greedy packing of instructions into as few messages as possible.
+//!
+//! [`InstructionPacker`] takes a list of instruction groups and splits them
+//! into consecutive messages. Each group is kept together in a single
+//! message, so related instructions can be executed atomically. A new message
+//! is started whenever adding the next group would exceed the transaction
+//! size, account or signature limits of the target [`PackFormat`].
+//!
+//! Sizes are computed with [`FormatEstimate`], so a packed message always
+//! serializes to a transaction that fits its format.
+
+use {
+    crate::{
+        estimate::FormatEstimate, legacy::Message as LegacyMessage, v0, v1,
+        AddressLookupTableAccount, CompileError, VersionedMessage,
+    },
+    alloc::vec::Vec,
+    core::{fmt, ops::Range},
+    solana_address::Address,
+    solana_hash::Hash,
+    solana_instruction::Instruction,
+};
+
+/// Default maximum number of accounts loaded by a packed message.
+///
+/// This matches the default transaction account lock limit of the runtime.
+pub const DEFAULT_MAX_ACCOUNTS: usize = 64;
+
+/// Message format used for packed messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackFormat<'a> {
+    /// Legacy messages.
+    Legacy,
+    /// V0 messages loading accounts from the provided address lookup tables.
+    V0 {
+        address_lookup_table_accounts: &'a [AddressLookupTableAccount],
+    },
+    /// V1 messages sharing the same transaction config.
+    V1 { config: v1::TransactionConfig },
+}
+
+/// Errors that can occur when packing instructions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PackError {
+    /// An instruction group does not fit in a message on its own.
+    GroupTooLarge(usize),
+    /// An instruction group cannot be compiled into a message.
+    Compile(usize, CompileError),
+}
+
+impl core::error::Error for PackError {}
+
+impl fmt::Display for PackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PackError::GroupTooLarge(index) => f.write_fmt(format_args!(
+                "instruction group {index} does not fit in a single message",
+            )),
+            PackError::Compile(index, err) => f.write_fmt(format_args!(
+                "instruction group {index} failed to compile: {err}",
+            )),
+        }
+    }
+}
+
+/// A message produced by [`InstructionPacker::pack`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackedMessage {
+    /// The compiled message.
+    pub message: VersionedMessage,
+    /// Addresses that must sign the message, starting with the fee payer.
+    pub signers: Vec<Address>,
+    /// Range of instruction groups included in the message.
+    pub groups: Range<usize>,
+}
+
+/// Packs instruction groups into the smallest number of messages.
+#[derive(Debug, Clone)]
+pub struct InstructionPacker<'a> {
+    payer: Address,
+    format: PackFormat<'a>,
+    max_accounts: usize,
+    max_signatures: Option<usize>,
+    groups: Vec<Vec<Instruction>>,
+}
+
+impl<'a> InstructionPacker<'a> {
+    /// Create a new packer for messages paid by `payer`.
+    pub fn new(payer: Address, format: PackFormat<'a>) -> Self {
+        Self {
+            payer,
+            format,
+            max_accounts: DEFAULT_MAX_ACCOUNTS,
+            max_signatures: None,
+            groups: Vec::new(),
+        }
+    }
+
+    /// Sets the maximum number of accounts, including the ones loaded from
+    /// address lookup tables, of each packed message.
+    #[must_use]
+    pub fn with_max_accounts(mut self, max_accounts: usize) -> Self {
+        self.max_accounts = max_accounts;
+        self
+    }
+
+    /// Sets the maximum number of signatures of each packed message.
+    ///
+    /// V1 messages are always limited to [`v1::MAX_SIGNATURES`].
+    #[must_use]
+    pub fn with_max_signatures(mut self, max_signatures: usize) -> Self {
+        self.max_signatures = Some(max_signatures);
+        self
+    }
+
+    /// Adds a single instruction as its own group.
+    pub fn push(&mut self, instruction: Instruction) -> &mut Self {
+        self.groups.push(Vec::from([instruction]));
+        self
+    }
+
+    /// Adds a group of instructions that must be included in the same message.
+    ///
+    /// Empty groups are ignored.
+    pub fn push_group(&mut self, instructions: impl Into<Vec<Instruction>>) -> &mut Self {
+        let instructions = instructions.into();
+        if !instructions.is_empty() {
+            self.groups.push(instructions);
+        }
+        self
+    }
+
+    /// Returns the number of instruction groups added to the packer.
+    pub fn num_groups(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// Greedily packs the instruction groups, in order, into messages using
+    /// `recent_blockhash` as their lifetime.
+    ///
+    /// Each group is sized by recompiling the message being filled with the
+    /// group added. The cost of a compilation is bounded by the size limits of
+    /// a message, so packing is linear in the number of groups, but quadratic
+    /// in the number of groups that fit in a single message.
+    pub fn pack(&self, recent_blockhash: &Hash) -> Result<Vec<PackedMessage>, PackError> {
+        let mut packed = Vec::new();
+        let mut start = 0;
+        let mut current: Option<VersionedMessage> = None;
+        let mut instructions = Vec::new();
+
+        for (index, group) in self.groups.iter().enumerate() {
+            let len = instructions.len();
+            instructions.extend_from_slice(group);
+
+            if current.is_some() {
+                if let Ok(message) = self.try_compile(&instructions, recent_blockhash) {
+                    current = Some(message);
+                    continue;
+                }
+                instructions.drain(..len);
+                if let Some(message) = current.take() {
+                    packed.push(Self::packed_message(message, start..index));
+                }
+                start = index;
+            }
+
+            let message =
+                self.try_compile(&instructions, recent_blockhash)
+                    .map_err(|err| match err {
+                        Some(err) => PackError::Compile(index, err),
+                        None => PackError::GroupTooLarge(index),
+                    })?;
+            current = Some(message);
+        }
+
+        if let Some(message) = current {
+            packed.push(Self::packed_message(message, start..self.groups.len()));
+        }
+
+        Ok(packed)
+    }
+
+    fn packed_message(message: VersionedMessage, groups: Range<usize>) -> PackedMessage {
+        let num_signers = usize::from(message.header().num_required_signatures);
+        let signers = message.static_account_keys()[..num_signers].to_vec();
+        PackedMessage {
+            message,
+            signers,
+            groups,
+        }
+    }
+
+    /// Compiles `instructions` into a message, returning `Err(None)` if the
+    /// message exceeds any limit.
+    fn try_compile(
+        &self,
+        instructions: &[Instruction],
+        recent_blockhash: &Hash,
+    ) -> Result<VersionedMessage, Option<CompileError>> {
+        let recent_blockhash = *recent_blockhash;
+        let (message, estimate, num_accounts) = match self.format {
+            PackFormat::Legacy => {
+                let message =
+                    LegacyMessage::try_compile(&self.payer, instructions, recent_blockhash)?;
+                let estimate = FormatEstimate::legacy(&message);
+                let num_accounts = message.account_keys.len();
+                (VersionedMessage::Legacy(message), estimate, num_accounts)
+            }
+            PackFormat::V0 {
+                address_lookup_table_accounts,
+            } => {
+                let message = v0::Message::try_compile(
+                    &self.payer,
+                    instructions,
+                    address_lookup_table_accounts,
+                    recent_blockhash,
+                )?;
+                let estimate = FormatEstimate::v0(&message);
+                let num_accounts = message.address_table_lookups.iter().fold(
+                    message.account_keys.len(),
+                    |num_accounts, lookup| {
+                        num_accounts
+                            .saturating_add(lookup.writable_indexes.len())
+                            .saturating_add(lookup.readonly_indexes.len())
+                    },
+                );
+                (VersionedMessage::V0(message), estimate, num_accounts)
+            }
+            PackFormat::V1 { config } => {
+                let message = v1::Message::try_compile_with_config(
+                    &self.payer,
+                    instructions,
+                    recent_blockhash,
+                    config,
+                )?;
+                let estimate = FormatEstimate::v1(&message);
+                let num_accounts = message.account_keys.len();
+                (VersionedMessage::V1(message), estimate, num_accounts)
+            }
+        };
+
+        let exceeds_max_signatures = self
+            .max_signatures
+            .is_some_and(|max_signatures| estimate.num_signatures > max_signatures);
+
+        if !estimate.fits() || num_accounts > self.max_accounts || exceeds_max_signatures {
+            return Err(None);
+        }
+
+        Ok(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*, crate::estimate::PACKET_DATA_SIZE, alloc::vec, solana_instruction::AccountMeta,
+        solana_sdk_ids::system_program,
+    };
+
+    fn instruction(num_accounts: usize, data_len: usize) -> Instruction {
+        Instruction::new_with_bytes(
+            system_program::id(),
+            &vec![1; data_len],
+            (0..num_accounts)
+                .map(|_| AccountMeta::new(Address::new_unique(), false))
+                .collect(),
+        )
+    }
+
+    fn transaction_size(message: &VersionedMessage) -> usize {
+        match message {
+            VersionedMessage::Legacy(message) => FormatEstimate::legacy(message),
+            VersionedMessage::V0(message) => FormatEstimate::v0(message),
+            VersionedMessage::V1(message) => FormatEstimate::v1(message),
+        }
+        .transaction_size
+    }
+
+    #[test]
+    fn test_pack_empty() {
+        let packer = InstructionPacker::new(Address::new_unique(), PackFormat::Legacy);
+        assert_eq!(packer.pack(&Hash::default()), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_pack_legacy_splits_on_size() {
+        let payer = Address::new_unique();
+        let mut packer = InstructionPacker::new(payer, PackFormat::Legacy);
+        for _ in 0..10 {
+            packer.push(instruction(1, 300));
+        }
+
+        let packed = packer.pack(&Hash::new_unique()).unwrap();
+
+        // each instruction takes ~336 bytes, so three fit in a packet
+        assert_eq!(packed.len(), 4);
+        assert_eq!(packed[0].groups, 0..3);
+        assert_eq!(packed[3].groups, 9..10);
+        for packed_message in &packed {
+            assert!(transaction_size(&packed_message.message) <= PACKET_DATA_SIZE);
+            assert_eq!(packed_message.signers, vec![payer]);
+        }
+    }
+
+    #[test]
+    fn test_pack_keeps_groups_together() {
+        let payer = Address::new_unique();
+        let mut packer = InstructionPacker::new(payer, PackFormat::Legacy);
+        packer
+            .push(instruction(1, 500))
+            .push_group(vec![instruction(1, 300), instruction(1, 300)])
+            .push(instruction(1, 10));
+
+        let packed = packer.pack(&Hash::default()).unwrap();
+
+        assert_eq!(packed.len(), 2);
+        assert_eq!(packed[0].groups, 0..1);
+        assert_eq!(packed[0].message.instructions().len(), 1);
+        assert_eq!(packed[1].groups, 1..3);
+        assert_eq!(packed[1].message.instructions().len(), 3);
+    }
+
+    #[test]
+    fn test_pack_group_too_large() {
+        let mut packer = InstructionPacker::new(Address::new_unique(), PackFormat::Legacy);
+        packer
+            .push(instruction(1, 10))
+            .push_group(vec![instruction(1, 700), instruction(1, 700)]);
+
+        assert_eq!(
+            packer.pack(&Hash::default()),
+            Err(PackError::GroupTooLarge(1))
+        );
+    }
+
+    #[test]
+    fn test_pack_compile_error() {
+        let mut packer = InstructionPacker::new(Address::new_unique(), PackFormat::Legacy);
+        packer.push(instruction(256, 0));
+
+        assert_eq!(
+            packer.pack(&Hash::default()),
+            Err(PackError::Compile(0, CompileError::AccountIndexOverflow))
+        );
+    }
+
+    #[test]
+    fn test_pack_max_accounts() {
+        let mut packer =
+            InstructionPacker::new(Address::new_unique(), PackFormat::Legacy).with_max_accounts(8);
+        for _ in 0..6 {
+            packer.push(instruction(2, 0));
+        }
+
+        let packed = packer.pack(&Hash::default()).unwrap();
+
+        // payer + system program + 2 accounts per instruction
+        assert_eq!(packed.len(), 2);
+        assert_eq!(packed[0].groups, 0..3);
+        assert_eq!(packed[0].message.static_account_keys().len(), 8);
+        assert_eq!(packed[1].groups, 3..6);
+    }
+
+    #[test]
+    fn test_pack_max_signatures_lists_signers() {
+        let payer = Address::new_unique();
+        let signers: Vec<Address> = (0..4).map(|_| Address::new_unique()).collect();
+        let mut packer = InstructionPacker::new(payer, PackFormat::Legacy).with_max_signatures(3);
+        for signer in &signers {
+            let mut ix = instruction(0, 0);
+            ix.accounts.push(AccountMeta::new_readonly(*signer, true));
+            packer.push(ix);
+        }
+
+        let packed = packer.pack(&Hash::default()).unwrap();
+
+        assert_eq!(packed.len(), 2);
+        assert_eq!(packed[0].signers.len(), 3);
+        assert_eq!(packed[0].signers[0], payer);
+        assert!(packed[0].signers[1..]
+            .iter()
+            .all(|s| signers[..2].contains(s)));
+        assert_eq!(packed[1].signers.len(), 3);
+        assert_eq!(packed[1].signers[0], payer);
+        assert!(packed[1].signers[1..]
+            .iter()
+            .all(|s| signers[2..].contains(s)));
+    }
+
+    #[test]
+    fn test_pack_v0_with_lookup_tables() {
+        let payer = Address::new_unique();
+        let instructions: Vec<Instruction> = (0..20).map(|_| instruction(3, 8)).collect();
+        let lookup_table = AddressLookupTableAccount {
+            key: Address::new_unique(),
+            addresses: instructions
+                .iter()
+                .flat_map(|ix| ix.accounts.iter().map(|meta| meta.pubkey))
+                .collect(),
+        };
+
+        let mut legacy_packer = InstructionPacker::new(payer, PackFormat::Legacy);
+        let mut v0_packer = InstructionPacker::new(
+            payer,
+            PackFormat::V0 {
+                address_lookup_table_accounts: core::slice::from_ref(&lookup_table),
+            },
+        )
+        .with_max_accounts(256);
+        for ix in instructions {
+            legacy_packer.push(ix.clone());
+            v0_packer.push(ix);
+        }
+
+        let legacy = legacy_packer.pack(&Hash::default()).unwrap();
+        let v0 = v0_packer.pack(&Hash::default()).unwrap();
+
+        assert!(v0.len() < legacy.len());
+        assert_eq!(v0.len(), 1);
+        assert!(matches!(v0[0].message, VersionedMessage::V0(_)));
+        assert_eq!(v0[0].message.address_table_lookups().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_pack_v1_shares_config() {
+        let payer = Address::new_unique();
+        let config = v1::TransactionConfig::empty()
+            .with_compute_unit_limit(50_000)
+            .with_priority_fee(1);
+        let mut packer = InstructionPacker::new(payer, PackFormat::V1 { config });
+        for _ in 0..10 {
+            packer.push(instruction(1, 1000));
+        }
+
+        let packed = packer.pack(&Hash::default()).unwrap();
+
+        assert_eq!(packed.len(), 4);
+        for packed_message in &packed {
+            let VersionedMessage::V1(message) = &packed_message.message else {
+                panic!("expected a v1 message");
+            };
+            assert_eq!(message.config, config);
+            assert!(transaction_size(&packed_message.message) <= v1::MAX_TRANSACTION_SIZE);
+        }
+    }
+}
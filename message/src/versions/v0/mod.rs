@@ -18,9 +18,11 @@ use {
     crate::{
         compiled_instruction::CompiledInstruction,
         compiled_keys::{CompileError, CompiledKeys},
+        estimate::short_vec_encoded_len,
         AccountKeys, AddressLookupTableAccount, MessageHeader,
     },
     alloc::vec::Vec,
+    core::mem::size_of,
     solana_address::Address,
     solana_hash::Hash,
     solana_instruction::Instruction,
@@ -67,6 +69,50 @@ pub struct MessageAddressTableLookup {
     pub readonly_indexes: Vec<u8>,
 }
 
+/// Strategy used to pick the address lookup tables of a compiled message.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LookupTableSelection {
+    /// Use every table that loads at least one account, in the provided
+    /// order.
+    ///
+    /// This is the strategy used by [`Message::try_compile`].
+    #[default]
+    All,
+    /// Greedily pick the table that saves the most bytes until no remaining
+    /// table reduces the serialized message size.
+    ///
+    /// Ties are broken by the position of the table in the provided list, so
+    /// the selection is deterministic.
+    MinimizeSize,
+}
+
+/// An address lookup table used by a compiled message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LookupTableUsage {
+    /// Address lookup table account key.
+    pub account_key: Address,
+    /// Number of accounts loaded from the table.
+    pub num_loaded_addresses: usize,
+    /// Number of bytes saved by loading the accounts from the table instead
+    /// of including them in the static account keys, net of the size of the
+    /// table lookup entry. This is negative when the lookup entry is larger
+    /// than the static keys it replaces.
+    pub bytes_saved: isize,
+}
+
+/// Returns the number of bytes saved by loading the given number of accounts
+/// from an address lookup table.
+fn lookup_bytes_saved(num_writable: usize, num_readonly: usize) -> isize {
+    let num_loaded = num_writable.saturating_add(num_readonly);
+    // each loaded account replaces a static key with a one byte table index
+    let saved = num_loaded.saturating_mul(size_of::<Address>());
+    let cost = size_of::<Address>()
+        .saturating_add(short_vec_encoded_len(num_writable))
+        .saturating_add(short_vec_encoded_len(num_readonly))
+        .saturating_add(num_loaded);
+    (saved as isize).saturating_sub(cost as isize)
+}
+
 /// A Solana transaction message (v0).
 ///
 /// This message format supports succinct account loading with
@@ -309,16 +355,81 @@ impl Message {
         address_lookup_table_accounts: &[AddressLookupTableAccount],
         recent_blockhash: Hash,
     ) -> Result<Self, CompileError> {
+        Self::try_compile_with_lookup_table_selection(
+            payer,
+            instructions,
+            address_lookup_table_accounts,
+            recent_blockhash,
+            LookupTableSelection::All,
+        )
+        .map(|(message, _)| message)
+    }
+
+    /// Create a signable transaction message from a `payer` public key,
+    /// `recent_blockhash`, list of `instructions`, and the subset of
+    /// `address_lookup_table_accounts` picked by `selection`.
+    ///
+    /// Returns the message together with the lookup tables it uses, in the
+    /// order of its address table lookups.
+    pub fn try_compile_with_lookup_table_selection(
+        payer: &Address,
+        instructions: &[Instruction],
+        address_lookup_table_accounts: &[AddressLookupTableAccount],
+        recent_blockhash: Hash,
+        selection: LookupTableSelection,
+    ) -> Result<(Self, Vec<LookupTableUsage>), CompileError> {
         let mut compiled_keys = CompiledKeys::compile(instructions, Some(*payer));
 
         let mut address_table_lookups = Vec::with_capacity(address_lookup_table_accounts.len());
         let mut loaded_addresses_list = Vec::with_capacity(address_lookup_table_accounts.len());
-        for lookup_table_account in address_lookup_table_accounts {
-            if let Some((lookup, loaded_addresses)) =
-                compiled_keys.try_extract_table_lookup(lookup_table_account)?
-            {
-                address_table_lookups.push(lookup);
-                loaded_addresses_list.push(loaded_addresses);
+        let mut lookup_table_usages = Vec::with_capacity(address_lookup_table_accounts.len());
+        let mut extract_table_lookup =
+            |compiled_keys: &mut CompiledKeys, lookup_table_account: &AddressLookupTableAccount| {
+                if let Some((lookup, loaded_addresses)) =
+                    compiled_keys.try_extract_table_lookup(lookup_table_account)?
+                {
+                    lookup_table_usages.push(LookupTableUsage {
+                        account_key: lookup.account_key,
+                        num_loaded_addresses: loaded_addresses.len(),
+                        bytes_saved: lookup_bytes_saved(
+                            lookup.writable_indexes.len(),
+                            lookup.readonly_indexes.len(),
+                        ),
+                    });
+                    address_table_lookups.push(lookup);
+                    loaded_addresses_list.push(loaded_addresses);
+                }
+                Ok::<(), CompileError>(())
+            };
+
+        match selection {
+            LookupTableSelection::All => {
+                for lookup_table_account in address_lookup_table_accounts {
+                    extract_table_lookup(&mut compiled_keys, lookup_table_account)?;
+                }
+            }
+            LookupTableSelection::MinimizeSize => {
+                let mut candidates: Vec<&AddressLookupTableAccount> =
+                    address_lookup_table_accounts.iter().collect();
+
+                loop {
+                    let mut best: Option<(usize, isize)> = None;
+                    for (index, lookup_table_account) in candidates.iter().enumerate() {
+                        let (num_writable, num_readonly) =
+                            compiled_keys.count_keys_found_in_lookup_table(lookup_table_account);
+                        let bytes_saved = lookup_bytes_saved(num_writable, num_readonly);
+                        // strict comparison keeps the first table on ties
+                        if bytes_saved > best.map_or(0, |(_, best_saved)| best_saved) {
+                            best = Some((index, bytes_saved));
+                        }
+                    }
+
+                    let Some((index, _)) = best else {
+                        break;
+                    };
+                    let lookup_table_account = candidates.remove(index);
+                    extract_table_lookup(&mut compiled_keys, lookup_table_account)?;
+                }
             }
         }
 
@@ -327,13 +438,16 @@ impl Message {
         let account_keys = AccountKeys::new(&static_keys, Some(&dynamic_keys));
         let instructions = account_keys.try_compile_instructions(instructions)?;
 
-        Ok(Self {
-            header,
-            account_keys: static_keys,
-            recent_blockhash,
-            instructions,
-            address_table_lookups,
-        })
+        Ok((
+            Self {
+                header,
+                account_keys: static_keys,
+                recent_blockhash,
+                instructions,
+                address_table_lookups,
+            },
+            lookup_table_usages,
+        ))
     }
 
     #[cfg(feature = "wincode")]
@@ -757,6 +871,121 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_lookup_bytes_saved() {
+        assert_eq!(lookup_bytes_saved(0, 0), -34);
+        assert_eq!(lookup_bytes_saved(1, 0), -3);
+        assert_eq!(lookup_bytes_saved(1, 1), 28);
+        assert_eq!(lookup_bytes_saved(10, 0), 10 * 31 - 34);
+    }
+
+    #[test]
+    fn test_try_compile_with_lookup_table_selection() {
+        let mut keys = vec![];
+        keys.resize_with(14, Address::new_unique);
+
+        let payer = Address::new_unique();
+        let program_id = Address::new_unique();
+        let instructions = vec![Instruction {
+            program_id,
+            accounts: keys
+                .iter()
+                .enumerate()
+                .map(|(i, key)| AccountMeta {
+                    pubkey: *key,
+                    is_signer: false,
+                    is_writable: i % 2 == 0,
+                })
+                .collect(),
+            data: vec![],
+        }];
+        let address_lookup_table_accounts = vec![
+            // only loads a single account, which costs more than it saves
+            AddressLookupTableAccount {
+                key: Address::new_unique(),
+                addresses: vec![keys[13]],
+            },
+            // overlaps with the larger table below
+            AddressLookupTableAccount {
+                key: Address::new_unique(),
+                addresses: keys[..6].to_vec(),
+            },
+            AddressLookupTableAccount {
+                key: Address::new_unique(),
+                addresses: keys[..10].to_vec(),
+            },
+            // identical to the previous table
+            AddressLookupTableAccount {
+                key: Address::new_unique(),
+                addresses: keys[..10].to_vec(),
+            },
+            AddressLookupTableAccount {
+                key: Address::new_unique(),
+                addresses: vec![keys[10], keys[11], keys[12], program_id, payer],
+            },
+        ];
+
+        let recent_blockhash = Hash::new_unique();
+        let (all, all_usages) = Message::try_compile_with_lookup_table_selection(
+            &payer,
+            &instructions,
+            &address_lookup_table_accounts,
+            recent_blockhash,
+            LookupTableSelection::All,
+        )
+        .unwrap();
+        assert_eq!(
+            Ok(all.clone()),
+            Message::try_compile(
+                &payer,
+                &instructions,
+                &address_lookup_table_accounts,
+                recent_blockhash
+            )
+        );
+        assert_eq!(
+            all_usages
+                .iter()
+                .map(|usage| (usage.account_key, usage.num_loaded_addresses))
+                .collect::<Vec<_>>(),
+            vec![
+                (address_lookup_table_accounts[0].key, 1),
+                (address_lookup_table_accounts[1].key, 6),
+                (address_lookup_table_accounts[2].key, 4),
+                (address_lookup_table_accounts[4].key, 3),
+            ]
+        );
+        assert_eq!(all_usages[0].bytes_saved, -3);
+
+        let (minimized, minimized_usages) = Message::try_compile_with_lookup_table_selection(
+            &payer,
+            &instructions,
+            &address_lookup_table_accounts,
+            recent_blockhash,
+            LookupTableSelection::MinimizeSize,
+        )
+        .unwrap();
+        assert_eq!(
+            minimized_usages,
+            vec![
+                LookupTableUsage {
+                    account_key: address_lookup_table_accounts[2].key,
+                    num_loaded_addresses: 10,
+                    bytes_saved: lookup_bytes_saved(5, 5),
+                },
+                LookupTableUsage {
+                    account_key: address_lookup_table_accounts[4].key,
+                    num_loaded_addresses: 3,
+                    bytes_saved: lookup_bytes_saved(1, 2),
+                },
+            ]
+        );
+        assert_eq!(minimized.account_keys, vec![payer, keys[13], program_id]);
+        assert!(
+            crate::estimate::v0_message_size(&minimized) < crate::estimate::v0_message_size(&all)
+        );
+    }
+
     #[test]
     fn test_is_maybe_writable() {
         let key0 = Address::new_unique();
@@ -0,0 +1,649 @@
+//! Conversions between legacy/v0 messages and V1 messages.
+//!
+//! V1 messages carry the compute budget in their [`TransactionConfig`]
+//! instead of compute budget instructions. Converting to V1 removes the
+//! compute budget instructions from the message and moves their values into
+//! the config; converting from V1 appends the equivalent compute budget
+//! instructions to the message.
+//!
+//! V1 priority fees are expressed in lamports while compute budget
+//! instructions set a compute unit price in micro-lamports, so the fee is
+//! converted using the compute unit limit of the message.
+
+use {
+    crate::{
+        compiled_instruction::CompiledInstruction,
+        legacy, v0,
+        v1::{ConversionError, Message, TransactionConfig},
+        MessageHeader,
+    },
+    alloc::vec::Vec,
+    solana_address::Address,
+    solana_hash::Hash,
+    solana_sdk_ids::compute_budget,
+};
+
+/// Inlined `ComputeBudgetInstruction` discriminators to avoid a
+/// solana_compute_budget_interface dep.
+const REQUEST_HEAP_FRAME: u8 = 1;
+const SET_COMPUTE_UNIT_LIMIT: u8 = 2;
+const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+const SET_LOADED_ACCOUNTS_DATA_SIZE_LIMIT: u8 = 4;
+
+const MICRO_LAMPORTS_PER_LAMPORT: u128 = 1_000_000;
+
+/// Compute budget values set by compute budget instructions.
+#[derive(Default)]
+struct ComputeBudgetValues {
+    heap_size: Option<u32>,
+    compute_unit_limit: Option<u32>,
+    compute_unit_price: Option<u64>,
+    loaded_accounts_data_size_limit: Option<u32>,
+}
+
+impl ComputeBudgetValues {
+    fn try_add_instruction(&mut self, data: &[u8]) -> Result<(), ConversionError> {
+        fn set<T>(value: &mut Option<T>, new_value: T) -> Result<(), ConversionError> {
+            if value.replace(new_value).is_some() {
+                return Err(ConversionError::DuplicateComputeBudgetInstruction);
+            }
+            Ok(())
+        }
+
+        let read_u32 = |bytes: &[u8]| {
+            <[u8; 4]>::try_from(bytes)
+                .map(u32::from_le_bytes)
+                .map_err(|_| ConversionError::InvalidComputeBudgetInstruction)
+        };
+
+        match data.split_first() {
+            Some((&REQUEST_HEAP_FRAME, value)) => set(&mut self.heap_size, read_u32(value)?),
+            Some((&SET_COMPUTE_UNIT_LIMIT, value)) => {
+                set(&mut self.compute_unit_limit, read_u32(value)?)
+            }
+            Some((&SET_COMPUTE_UNIT_PRICE, value)) => {
+                let value = <[u8; 8]>::try_from(value)
+                    .map(u64::from_le_bytes)
+                    .map_err(|_| ConversionError::InvalidComputeBudgetInstruction)?;
+                set(&mut self.compute_unit_price, value)
+            }
+            Some((&SET_LOADED_ACCOUNTS_DATA_SIZE_LIMIT, value)) => {
+                set(&mut self.loaded_accounts_data_size_limit, read_u32(value)?)
+            }
+            _ => Err(ConversionError::InvalidComputeBudgetInstruction),
+        }
+    }
+
+    fn try_into_config(self) -> Result<TransactionConfig, ConversionError> {
+        let priority_fee = self
+            .compute_unit_price
+            .map(|price| {
+                let limit = self
+                    .compute_unit_limit
+                    .ok_or(ConversionError::MissingComputeUnitLimit)?;
+                // the fee is rounded up, matching the runtime's prioritization fee
+                let fee = u128::from(price)
+                    .saturating_mul(u128::from(limit))
+                    .div_ceil(MICRO_LAMPORTS_PER_LAMPORT);
+                u64::try_from(fee).map_err(|_| ConversionError::PriorityFeeOverflow)
+            })
+            .transpose()?;
+
+        Ok(TransactionConfig {
+            priority_fee,
+            compute_unit_limit: self.compute_unit_limit,
+            loaded_accounts_data_size_limit: self.loaded_accounts_data_size_limit,
+            heap_size: self.heap_size,
+        })
+    }
+}
+
+fn compute_budget_instruction(
+    program_id_index: u8,
+    discriminator: u8,
+    value: &[u8],
+) -> CompiledInstruction {
+    let mut data = Vec::with_capacity(value.len().saturating_add(1));
+    data.push(discriminator);
+    data.extend_from_slice(value);
+    CompiledInstruction::new_from_raw_parts(program_id_index, data, Vec::new())
+}
+
+/// Converts the components of a legacy or v0 message into a V1 message.
+fn try_into_v1(
+    mut header: MessageHeader,
+    mut account_keys: Vec<Address>,
+    recent_blockhash: Hash,
+    instructions: Vec<CompiledInstruction>,
+) -> Result<Message, ConversionError> {
+    let compute_budget_index = account_keys
+        .iter()
+        .position(compute_budget::check_id)
+        .and_then(|index| u8::try_from(index).ok());
+
+    let mut values = ComputeBudgetValues::default();
+    let mut instructions_without_compute_budget = Vec::with_capacity(instructions.len());
+    for ix in instructions {
+        if Some(ix.program_id_index) == compute_budget_index {
+            values.try_add_instruction(&ix.data)?;
+        } else {
+            instructions_without_compute_budget.push(ix);
+        }
+    }
+    let mut instructions = instructions_without_compute_budget;
+    let config = values.try_into_config()?;
+
+    // Remove the compute budget program when it is no longer referenced,
+    // unless it is a signer since that would change the required signatures.
+    if let Some(index) = compute_budget_index {
+        let is_referenced = instructions
+            .iter()
+            .any(|ix| ix.program_id_index == index || ix.accounts.contains(&index));
+
+        if !is_referenced && index >= header.num_required_signatures {
+            let num_readonly_unsigned_start = account_keys
+                .len()
+                .saturating_sub(usize::from(header.num_readonly_unsigned_accounts));
+            if usize::from(index) >= num_readonly_unsigned_start {
+                header.num_readonly_unsigned_accounts =
+                    header.num_readonly_unsigned_accounts.saturating_sub(1);
+            }
+            account_keys.remove(usize::from(index));
+
+            let reindex = |i: &mut u8| {
+                if *i > index {
+                    *i = i.saturating_sub(1);
+                }
+            };
+            for ix in &mut instructions {
+                reindex(&mut ix.program_id_index);
+                ix.accounts.iter_mut().for_each(reindex);
+            }
+        }
+    }
+
+    let message = Message {
+        header,
+        config,
+        lifetime_specifier: recent_blockhash,
+        account_keys,
+        instructions,
+    };
+    message.validate()?;
+
+    Ok(message)
+}
+
+/// Converts a V1 message into the components of a legacy or v0 message,
+/// appending compute budget instructions for the values set in its config.
+fn try_from_v1(
+    message: Message,
+) -> Result<(MessageHeader, Vec<Address>, Hash, Vec<CompiledInstruction>), ConversionError> {
+    let Message {
+        mut header,
+        config,
+        lifetime_specifier,
+        mut account_keys,
+        mut instructions,
+    } = message;
+
+    let compute_unit_price = config
+        .priority_fee
+        .map(|fee| {
+            if fee == 0 {
+                return Ok(0);
+            }
+            let limit = config
+                .compute_unit_limit
+                .filter(|limit| *limit > 0)
+                .ok_or(ConversionError::MissingComputeUnitLimit)?;
+            let price = u128::from(fee)
+                .saturating_mul(MICRO_LAMPORTS_PER_LAMPORT)
+                .checked_div(u128::from(limit))
+                .ok_or(ConversionError::MissingComputeUnitLimit)?;
+            u64::try_from(price).map_err(|_| ConversionError::PriorityFeeOverflow)
+        })
+        .transpose()?;
+
+    let has_compute_budget = config.compute_unit_limit.is_some()
+        || compute_unit_price.is_some()
+        || config.loaded_accounts_data_size_limit.is_some()
+        || config.heap_size.is_some();
+
+    if has_compute_budget {
+        let program_id_index = match account_keys.iter().position(compute_budget::check_id) {
+            Some(index) => index,
+            None => {
+                // add the program as a readonly unsigned account
+                account_keys.push(compute_budget::id());
+                header.num_readonly_unsigned_accounts = header
+                    .num_readonly_unsigned_accounts
+                    .checked_add(1)
+                    .ok_or(ConversionError::AccountIndexOverflow)?;
+                account_keys.len().saturating_sub(1)
+            }
+        };
+        let program_id_index =
+            u8::try_from(program_id_index).map_err(|_| ConversionError::AccountIndexOverflow)?;
+
+        if let Some(limit) = config.compute_unit_limit {
+            instructions.push(compute_budget_instruction(
+                program_id_index,
+                SET_COMPUTE_UNIT_LIMIT,
+                &limit.to_le_bytes(),
+            ));
+        }
+        if let Some(price) = compute_unit_price {
+            instructions.push(compute_budget_instruction(
+                program_id_index,
+                SET_COMPUTE_UNIT_PRICE,
+                &price.to_le_bytes(),
+            ));
+        }
+        if let Some(heap_size) = config.heap_size {
+            instructions.push(compute_budget_instruction(
+                program_id_index,
+                REQUEST_HEAP_FRAME,
+                &heap_size.to_le_bytes(),
+            ));
+        }
+        if let Some(limit) = config.loaded_accounts_data_size_limit {
+            instructions.push(compute_budget_instruction(
+                program_id_index,
+                SET_LOADED_ACCOUNTS_DATA_SIZE_LIMIT,
+                &limit.to_le_bytes(),
+            ));
+        }
+    }
+
+    Ok((header, account_keys, lifetime_specifier, instructions))
+}
+
+impl TryFrom<legacy::Message> for Message {
+    type Error = ConversionError;
+
+    fn try_from(message: legacy::Message) -> Result<Self, Self::Error> {
+        try_into_v1(
+            message.header,
+            message.account_keys,
+            message.recent_blockhash,
+            message.instructions,
+        )
+    }
+}
+
+impl TryFrom<v0::Message> for Message {
+    type Error = ConversionError;
+
+    fn try_from(message: v0::Message) -> Result<Self, Self::Error> {
+        if !message.address_table_lookups.is_empty() {
+            return Err(ConversionError::AddressTableLookups);
+        }
+
+        try_into_v1(
+            message.header,
+            message.account_keys,
+            message.recent_blockhash,
+            message.instructions,
+        )
+    }
+}
+
+impl TryFrom<Message> for legacy::Message {
+    type Error = ConversionError;
+
+    fn try_from(message: Message) -> Result<Self, Self::Error> {
+        let (header, account_keys, recent_blockhash, instructions) = try_from_v1(message)?;
+
+        Ok(Self {
+            header,
+            account_keys,
+            recent_blockhash,
+            instructions,
+        })
+    }
+}
+
+impl TryFrom<Message> for v0::Message {
+    type Error = ConversionError;
+
+    fn try_from(message: Message) -> Result<Self, Self::Error> {
+        let (header, account_keys, recent_blockhash, instructions) = try_from_v1(message)?;
+
+        Ok(Self {
+            header,
+            account_keys,
+            recent_blockhash,
+            instructions,
+            address_table_lookups: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{v1::MessageError, AddressLookupTableAccount},
+        alloc::vec,
+        solana_compute_budget_interface::ComputeBudgetInstruction,
+        solana_instruction::{AccountMeta, Instruction},
+        solana_sanitize::Sanitize,
+    };
+
+    fn instruction() -> Instruction {
+        Instruction::new_with_bytes(
+            Address::new_unique(),
+            &[1, 2, 3],
+            vec![
+                AccountMeta::new(Address::new_unique(), false),
+                AccountMeta::new_readonly(Address::new_unique(), false),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_inline_compute_budget_instructions_match_program() {
+        let program_id_index = 3;
+        let to_compiled = |ix: Instruction| CompiledInstruction {
+            program_id_index,
+            accounts: vec![],
+            data: ix.data,
+        };
+
+        assert_eq!(
+            compute_budget_instruction(program_id_index, REQUEST_HEAP_FRAME, &[0, 4, 1, 0]),
+            to_compiled(ComputeBudgetInstruction::request_heap_frame(66_560)),
+        );
+        assert_eq!(
+            compute_budget_instruction(
+                program_id_index,
+                SET_COMPUTE_UNIT_LIMIT,
+                &200_000u32.to_le_bytes()
+            ),
+            to_compiled(ComputeBudgetInstruction::set_compute_unit_limit(200_000)),
+        );
+        assert_eq!(
+            compute_budget_instruction(
+                program_id_index,
+                SET_COMPUTE_UNIT_PRICE,
+                &u64::MAX.to_le_bytes()
+            ),
+            to_compiled(ComputeBudgetInstruction::set_compute_unit_price(u64::MAX)),
+        );
+        assert_eq!(
+            compute_budget_instruction(
+                program_id_index,
+                SET_LOADED_ACCOUNTS_DATA_SIZE_LIMIT,
+                &1024u32.to_le_bytes()
+            ),
+            to_compiled(ComputeBudgetInstruction::set_loaded_accounts_data_size_limit(1024)),
+        );
+    }
+
+    #[test]
+    fn test_legacy_to_v1() {
+        let payer = Address::new_unique();
+        let ix = instruction();
+        let recent_blockhash = Hash::new_unique();
+        let message = legacy::Message::new_with_blockhash(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(200_000),
+                ComputeBudgetInstruction::set_compute_unit_price(5_000),
+                ix.clone(),
+                ComputeBudgetInstruction::request_heap_frame(64 * 1024),
+                ComputeBudgetInstruction::set_loaded_accounts_data_size_limit(32 * 1024),
+            ],
+            Some(&payer),
+            &recent_blockhash,
+        );
+
+        let converted = Message::try_from(message).unwrap();
+
+        let expected_config = TransactionConfig::empty()
+            .with_compute_unit_limit(200_000)
+            .with_priority_fee(1_000)
+            .with_heap_size(64 * 1024)
+            .with_loaded_accounts_data_size_limit(32 * 1024);
+        assert_eq!(
+            converted,
+            Message::try_compile_with_config(&payer, &[ix], recent_blockhash, expected_config)
+                .unwrap()
+        );
+        assert!(!converted.account_keys.contains(&compute_budget::id()));
+    }
+
+    #[test]
+    fn test_legacy_to_v1_rounds_priority_fee_up() {
+        let payer = Address::new_unique();
+        let message = legacy::Message::new(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(3),
+                ComputeBudgetInstruction::set_compute_unit_price(1),
+                instruction(),
+            ],
+            Some(&payer),
+        );
+
+        let converted = Message::try_from(message).unwrap();
+
+        assert_eq!(converted.config.priority_fee, Some(1));
+    }
+
+    #[test]
+    fn test_legacy_to_v1_keeps_referenced_compute_budget_program() {
+        let payer = Address::new_unique();
+        let mut ix = instruction();
+        ix.accounts
+            .push(AccountMeta::new_readonly(compute_budget::id(), false));
+        let message = legacy::Message::new(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(1_000),
+                ix.clone(),
+            ],
+            Some(&payer),
+        );
+
+        let converted = Message::try_from(message).unwrap();
+
+        assert_eq!(
+            converted,
+            Message::try_compile_with_config(
+                &payer,
+                &[ix],
+                Hash::default(),
+                TransactionConfig::empty().with_compute_unit_limit(1_000),
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_legacy_to_v1_errors() {
+        let payer = Address::new_unique();
+        let convert = |instructions: &[Instruction]| {
+            Message::try_from(legacy::Message::new(instructions, Some(&payer)))
+        };
+
+        assert_eq!(
+            convert(&[
+                ComputeBudgetInstruction::set_compute_unit_limit(1),
+                ComputeBudgetInstruction::set_compute_unit_limit(2),
+            ]),
+            Err(ConversionError::DuplicateComputeBudgetInstruction)
+        );
+        assert_eq!(
+            convert(&[ComputeBudgetInstruction::set_compute_unit_price(1)]),
+            Err(ConversionError::MissingComputeUnitLimit)
+        );
+        assert_eq!(
+            convert(&[Instruction::new_with_bytes(
+                compute_budget::id(),
+                &[0],
+                vec![]
+            )]),
+            Err(ConversionError::InvalidComputeBudgetInstruction)
+        );
+        assert_eq!(
+            convert(&[Instruction::new_with_bytes(
+                compute_budget::id(),
+                &[SET_COMPUTE_UNIT_LIMIT, 1],
+                vec![]
+            )]),
+            Err(ConversionError::InvalidComputeBudgetInstruction)
+        );
+        assert_eq!(
+            convert(&[ComputeBudgetInstruction::request_heap_frame(1_000)]),
+            Err(ConversionError::InvalidMessage(
+                MessageError::InvalidHeapSize
+            ))
+        );
+
+        let program_id = Address::new_unique();
+        let instructions: Vec<Instruction> = (0..65)
+            .map(|_| Instruction::new_with_bytes(program_id, &[], vec![]))
+            .collect();
+        assert_eq!(
+            convert(&instructions),
+            Err(ConversionError::InvalidMessage(
+                MessageError::TooManyInstructions
+            ))
+        );
+    }
+
+    #[test]
+    fn test_v0_to_v1() {
+        let payer = Address::new_unique();
+        let ix = instruction();
+        let message = v0::Message::try_compile(
+            &payer,
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(10),
+                ix.clone(),
+            ],
+            &[],
+            Hash::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            Message::try_from(message),
+            Ok(Message::try_compile_with_config(
+                &payer,
+                core::slice::from_ref(&ix),
+                Hash::default(),
+                TransactionConfig::empty().with_compute_unit_limit(10),
+            )
+            .unwrap())
+        );
+
+        let lookup_table = AddressLookupTableAccount {
+            key: Address::new_unique(),
+            addresses: ix.accounts.iter().map(|meta| meta.pubkey).collect(),
+        };
+        let message =
+            v0::Message::try_compile(&payer, &[ix], &[lookup_table], Hash::default()).unwrap();
+
+        assert_eq!(
+            Message::try_from(message),
+            Err(ConversionError::AddressTableLookups)
+        );
+    }
+
+    #[test]
+    fn test_v1_to_legacy_and_v0() {
+        let payer = Address::new_unique();
+        let ix = instruction();
+        let recent_blockhash = Hash::new_unique();
+        let config = TransactionConfig::empty()
+            .with_compute_unit_limit(200_000)
+            .with_priority_fee(1_000)
+            .with_heap_size(64 * 1024)
+            .with_loaded_accounts_data_size_limit(32 * 1024);
+        let message = Message::try_compile_with_config(
+            &payer,
+            core::slice::from_ref(&ix),
+            recent_blockhash,
+            config,
+        )
+        .unwrap();
+
+        let legacy = legacy::Message::try_from(message.clone()).unwrap();
+
+        let num_keys = message.account_keys.len();
+        assert_eq!(legacy.account_keys[..num_keys], message.account_keys[..]);
+        assert_eq!(legacy.account_keys[num_keys], compute_budget::id());
+        assert_eq!(
+            legacy.header.num_readonly_unsigned_accounts,
+            message.header.num_readonly_unsigned_accounts + 1
+        );
+        assert_eq!(legacy.instructions[0], message.instructions[0]);
+        assert_eq!(
+            legacy.instructions[1..]
+                .iter()
+                .map(|ix| ix.data.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                ComputeBudgetInstruction::set_compute_unit_limit(200_000).data,
+                ComputeBudgetInstruction::set_compute_unit_price(5_000).data,
+                ComputeBudgetInstruction::request_heap_frame(64 * 1024).data,
+                ComputeBudgetInstruction::set_loaded_accounts_data_size_limit(32 * 1024).data,
+            ]
+        );
+        assert!(legacy.sanitize().is_ok());
+
+        // converting back yields the original message
+        assert_eq!(Message::try_from(legacy), Ok(message.clone()));
+
+        let v0 = v0::Message::try_from(message.clone()).unwrap();
+        assert!(v0.address_table_lookups.is_empty());
+        assert_eq!(Message::try_from(v0), Ok(message));
+    }
+
+    #[test]
+    fn test_v1_to_legacy_without_config() {
+        let payer = Address::new_unique();
+        let ix = instruction();
+        let message =
+            Message::try_compile(&payer, core::slice::from_ref(&ix), Hash::default()).unwrap();
+
+        assert_eq!(
+            legacy::Message::try_from(message),
+            Ok(legacy::Message::new(&[ix], Some(&payer)))
+        );
+    }
+
+    #[test]
+    fn test_v1_to_legacy_errors() {
+        let payer = Address::new_unique();
+        let message = |config| {
+            Message::try_compile_with_config(&payer, &[instruction()], Hash::default(), config)
+                .unwrap()
+        };
+
+        assert_eq!(
+            legacy::Message::try_from(message(TransactionConfig::empty().with_priority_fee(1))),
+            Err(ConversionError::MissingComputeUnitLimit)
+        );
+        assert_eq!(
+            legacy::Message::try_from(message(
+                TransactionConfig::empty()
+                    .with_priority_fee(1)
+                    .with_compute_unit_limit(0)
+            )),
+            Err(ConversionError::MissingComputeUnitLimit)
+        );
+        assert_eq!(
+            legacy::Message::try_from(message(
+                TransactionConfig::empty()
+                    .with_priority_fee(u64::MAX)
+                    .with_compute_unit_limit(1)
+            )),
+            Err(ConversionError::PriorityFeeOverflow)
+        );
+        assert!(legacy::Message::try_from(message(
+            TransactionConfig::empty().with_priority_fee(0)
+        ))
+        .is_ok());
+    }
+}
@@ -108,3 +108,52 @@ impl From<MessageError> for SanitizeError {
         }
     }
 }
+
+/// Errors that can occur when converting messages to or from V1 messages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConversionError {
+    /// Address table lookups are not supported by V1 messages.
+    AddressTableLookups,
+    /// A compute budget instruction could not be decoded.
+    InvalidComputeBudgetInstruction,
+    /// A compute budget instruction appears more than once.
+    DuplicateComputeBudgetInstruction,
+    /// A priority fee requires a non-zero compute unit limit.
+    MissingComputeUnitLimit,
+    /// The priority fee does not fit in a `u64`.
+    PriorityFeeOverflow,
+    /// Account index overflowed when adding the compute budget program.
+    AccountIndexOverflow,
+    /// The converted message is not a valid V1 message.
+    InvalidMessage(MessageError),
+}
+
+impl core::fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::AddressTableLookups => {
+                write!(f, "address table lookups are not supported by v1 messages")
+            }
+            Self::InvalidComputeBudgetInstruction => {
+                write!(f, "invalid compute budget instruction")
+            }
+            Self::DuplicateComputeBudgetInstruction => {
+                write!(f, "duplicate compute budget instruction")
+            }
+            Self::MissingComputeUnitLimit => {
+                write!(f, "priority fee requires a non-zero compute unit limit")
+            }
+            Self::PriorityFeeOverflow => write!(f, "priority fee overflow"),
+            Self::AccountIndexOverflow => write!(f, "account index overflow"),
+            Self::InvalidMessage(err) => write!(f, "invalid v1 message: {err}"),
+        }
+    }
+}
+
+impl core::error::Error for ConversionError {}
+
+impl From<MessageError> for ConversionError {
+    fn from(err: MessageError) -> Self {
+        Self::InvalidMessage(err)
+    }
+}
@@ -3,6 +3,7 @@ use crate::{MessageHeader, MESSAGE_VERSION_PREFIX};
 #[cfg(feature = "std")]
 mod cached;
 mod config;
+mod convert;
 mod error;
 mod message;
 
@@ -8,6 +8,9 @@ use {
     solana_signer::Signer,
 };
 
+#[cfg(feature = "siws")]
+pub mod siws;
+
 #[cfg(test)]
 static_assertions::const_assert_eq!(OffchainMessage::HEADER_LEN, 17);
 #[cfg(test)]
@@ -38,10 +41,52 @@ pub enum MessageFormat {
     ExtendedUtf8,
 }
 
+impl MessageFormat {
+    /// Detect the most restrictive format of a message, given the length
+    /// limits of the message version
+    fn detect(
+        message: &[u8],
+        max_len_ledger: usize,
+        max_len: usize,
+    ) -> Result<Self, SanitizeError> {
+        if message.is_empty() {
+            Err(SanitizeError::InvalidValue)
+        } else if message.len() <= max_len_ledger {
+            if is_printable_ascii(message) {
+                Ok(MessageFormat::RestrictedAscii)
+            } else if is_utf8(message) {
+                Ok(MessageFormat::LimitedUtf8)
+            } else {
+                Err(SanitizeError::InvalidValue)
+            }
+        } else if message.len() <= max_len {
+            if is_utf8(message) {
+                Ok(MessageFormat::ExtendedUtf8)
+            } else {
+                Err(SanitizeError::InvalidValue)
+            }
+        } else {
+            Err(SanitizeError::ValueOutOfBounds)
+        }
+    }
+
+    /// Check that a message is valid for this format, given the length limits
+    /// of the message version
+    fn is_valid(self, message: &[u8], max_len_ledger: usize, max_len: usize) -> bool {
+        match self {
+            MessageFormat::RestrictedAscii => {
+                (message.len() <= max_len_ledger) && is_printable_ascii(message)
+            }
+            MessageFormat::LimitedUtf8 => (message.len() <= max_len_ledger) && is_utf8(message),
+            MessageFormat::ExtendedUtf8 => (message.len() <= max_len) && is_utf8(message),
+        }
+    }
+}
+
 #[allow(clippy::arithmetic_side_effects)]
 pub mod v0 {
     use {
-        super::{is_printable_ascii, is_utf8, MessageFormat, OffchainMessage as Base},
+        super::{MessageFormat, OffchainMessage as Base},
         solana_hash::Hash,
         solana_packet::PACKET_DATA_SIZE,
         solana_sanitize::SanitizeError,
@@ -66,25 +111,7 @@ pub mod v0 {
 
         /// Construct a new OffchainMessage object from the given message
         pub fn new(message: &[u8]) -> Result<Self, SanitizeError> {
-            let format = if message.is_empty() {
-                return Err(SanitizeError::InvalidValue);
-            } else if message.len() <= OffchainMessage::MAX_LEN_LEDGER {
-                if is_printable_ascii(message) {
-                    MessageFormat::RestrictedAscii
-                } else if is_utf8(message) {
-                    MessageFormat::LimitedUtf8
-                } else {
-                    return Err(SanitizeError::InvalidValue);
-                }
-            } else if message.len() <= OffchainMessage::MAX_LEN {
-                if is_utf8(message) {
-                    MessageFormat::ExtendedUtf8
-                } else {
-                    return Err(SanitizeError::InvalidValue);
-                }
-            } else {
-                return Err(SanitizeError::ValueOutOfBounds);
-            };
+            let format = MessageFormat::detect(message, Self::MAX_LEN_LEDGER, Self::MAX_LEN)?;
             Ok(Self {
                 format,
                 message: message.to_vec(),
@@ -121,19 +148,179 @@ pub mod v0 {
             }
             let message = &data[Self::HEADER_LEN..];
             // check format
-            let is_valid = match format {
-                MessageFormat::RestrictedAscii => {
-                    (message.len() <= Self::MAX_LEN_LEDGER) && is_printable_ascii(message)
-                }
-                MessageFormat::LimitedUtf8 => {
-                    (message.len() <= Self::MAX_LEN_LEDGER) && is_utf8(message)
+            if format.is_valid(message, Self::MAX_LEN_LEDGER, Self::MAX_LEN) {
+                Ok(Self {
+                    format,
+                    message: message.to_vec(),
+                })
+            } else {
+                Err(SanitizeError::InvalidValue)
+            }
+        }
+
+        /// Compute the SHA256 hash of the serialized off-chain message
+        pub fn hash(serialized_message: &[u8]) -> Result<Hash, SanitizeError> {
+            let mut hasher = Hasher::default();
+            hasher.hash(serialized_message);
+            Ok(hasher.result())
+        }
+
+        pub fn get_format(&self) -> MessageFormat {
+            self.format
+        }
+
+        pub fn get_message(&self) -> &Vec<u8> {
+            &self.message
+        }
+    }
+}
+
+#[allow(clippy::arithmetic_side_effects)]
+pub mod v1 {
+    use {
+        super::{MessageFormat, OffchainMessage as Base},
+        solana_hash::Hash,
+        solana_packet::PACKET_DATA_SIZE,
+        solana_pubkey::{Pubkey, PUBKEY_BYTES},
+        solana_sanitize::SanitizeError,
+        solana_sha256_hasher::Hasher,
+        solana_signature::Signature,
+        solana_signer::{Signer, SignerError},
+    };
+
+    /// OffchainMessage Version 1.
+    /// Struct always contains a non-empty valid message and at least one
+    /// signer, with no duplicate signers.
+    #[derive(Debug, PartialEq, Eq, Clone)]
+    pub struct OffchainMessage {
+        application_domain: [u8; 32],
+        format: MessageFormat,
+        signers: Vec<Pubkey>,
+        message: Vec<u8>,
+    }
+
+    impl OffchainMessage {
+        pub const VERSION: u8 = 1;
+        pub const APPLICATION_DOMAIN_LEN: usize = 32;
+        // Max number of signers, limited by the signer count field
+        pub const MAX_SIGNERS: usize = u8::MAX as usize;
+
+        /// Header Length = Application Domain (32) + Message Format (1) +
+        /// Signer Count (1) + Signers (32 * num_signers) + Message Length (2)
+        pub const fn header_len(num_signers: usize) -> usize {
+            Self::APPLICATION_DOMAIN_LEN + 1 + 1 + PUBKEY_BYTES * num_signers + 2
+        }
+
+        /// Max length of the message with `num_signers` signers
+        pub const fn max_len(num_signers: usize) -> usize {
+            (u16::MAX as usize).saturating_sub(Base::HEADER_LEN + Self::header_len(num_signers))
+        }
+
+        /// Max length of the message with `num_signers` signers supported by
+        /// the Ledger
+        pub const fn max_len_ledger(num_signers: usize) -> usize {
+            PACKET_DATA_SIZE.saturating_sub(Base::HEADER_LEN + Self::header_len(num_signers))
+        }
+
+        fn check_signers(signers: &[Pubkey]) -> Result<(), SanitizeError> {
+            if signers.is_empty() {
+                return Err(SanitizeError::InvalidValue);
+            }
+            if signers.len() > Self::MAX_SIGNERS {
+                return Err(SanitizeError::ValueOutOfBounds);
+            }
+            for (i, signer) in signers.iter().enumerate() {
+                if signers[..i].contains(signer) {
+                    return Err(SanitizeError::InvalidValue);
                 }
-                MessageFormat::ExtendedUtf8 => (message.len() <= Self::MAX_LEN) && is_utf8(message),
-            };
+            }
+            Ok(())
+        }
 
-            if is_valid {
+        /// Construct a new OffchainMessage object for the given application
+        /// domain, signers and message
+        pub fn new(
+            application_domain: [u8; 32],
+            signers: &[Pubkey],
+            message: &[u8],
+        ) -> Result<Self, SanitizeError> {
+            Self::check_signers(signers)?;
+            let format = MessageFormat::detect(
+                message,
+                Self::max_len_ledger(signers.len()),
+                Self::max_len(signers.len()),
+            )?;
+            Ok(Self {
+                application_domain,
+                format,
+                signers: signers.to_vec(),
+                message: message.to_vec(),
+            })
+        }
+
+        /// Serialize the message to bytes, including the full header
+        pub fn serialize(&self, data: &mut Vec<u8>) -> Result<(), SanitizeError> {
+            // invalid messages shouldn't be possible, but a quick sanity check never hurts
+            assert!(!self.message.is_empty());
+            assert!(!self.signers.is_empty() && self.signers.len() <= Self::MAX_SIGNERS);
+            assert!(self.message.len() <= Self::max_len(self.signers.len()));
+            data.reserve(Self::header_len(self.signers.len()).saturating_add(self.message.len()));
+            // application domain
+            data.extend_from_slice(&self.application_domain);
+            // format
+            data.push(self.format.into());
+            // signers
+            data.push(self.signers.len() as u8);
+            for signer in &self.signers {
+                data.extend_from_slice(signer.as_ref());
+            }
+            // message length
+            data.extend_from_slice(&(self.message.len() as u16).to_le_bytes());
+            // message
+            data.extend_from_slice(&self.message);
+            Ok(())
+        }
+
+        /// Deserialize the message from bytes that include a full header
+        pub fn deserialize(data: &[u8]) -> Result<Self, SanitizeError> {
+            // validate data length, up to the signers
+            if data.len() <= Self::header_len(0) {
+                return Err(SanitizeError::ValueOutOfBounds);
+            }
+            // decode header
+            let application_domain: [u8; 32] = data[..Self::APPLICATION_DOMAIN_LEN]
+                .try_into()
+                .map_err(|_| SanitizeError::InvalidValue)?;
+            let format = MessageFormat::try_from(data[Self::APPLICATION_DOMAIN_LEN])
+                .map_err(|_| SanitizeError::InvalidValue)?;
+            let num_signers = usize::from(data[Self::APPLICATION_DOMAIN_LEN + 1]);
+            let header_len = Self::header_len(num_signers);
+            if data.len() <= header_len {
+                return Err(SanitizeError::ValueOutOfBounds);
+            }
+            let signers_start = Self::APPLICATION_DOMAIN_LEN + 2;
+            let signers: Vec<Pubkey> = data[signers_start..header_len - 2]
+                .chunks_exact(PUBKEY_BYTES)
+                .map(|signer| Pubkey::try_from(signer).map_err(|_| SanitizeError::InvalidValue))
+                .collect::<Result<_, _>>()?;
+            Self::check_signers(&signers)?;
+            let message_len =
+                u16::from_le_bytes([data[header_len - 2], data[header_len - 1]]) as usize;
+            // check header
+            if header_len.saturating_add(message_len) != data.len() {
+                return Err(SanitizeError::InvalidValue);
+            }
+            let message = &data[header_len..];
+            // check format
+            if format.is_valid(
+                message,
+                Self::max_len_ledger(num_signers),
+                Self::max_len(num_signers),
+            ) {
                 Ok(Self {
+                    application_domain,
                     format,
+                    signers,
                     message: message.to_vec(),
                 })
             } else {
@@ -148,10 +335,74 @@ pub mod v0 {
             Ok(hasher.result())
         }
 
+        /// Serialize the message with the signing domain and version, as it
+        /// is signed
+        fn serialize_signable(&self) -> Result<Vec<u8>, SanitizeError> {
+            let mut data = Base::SIGNING_DOMAIN.to_vec();
+            data.push(Self::VERSION);
+            self.serialize(&mut data)?;
+            Ok(data)
+        }
+
+        /// Sign the message with the provided signers
+        ///
+        /// Returns one result per message signer, in the order of
+        /// [`get_signers`](Self::get_signers). A message signer without a
+        /// matching signer in `signers` gets
+        /// [`SignerError::NotEnoughSigners`], and signers that are not
+        /// message signers are ignored.
+        pub fn sign(
+            &self,
+            signers: &[&dyn Signer],
+        ) -> Result<Vec<Result<Signature, SignerError>>, SanitizeError> {
+            let data = self.serialize_signable()?;
+            let pubkeys: Vec<Option<Pubkey>> = signers
+                .iter()
+                .map(|signer| signer.try_pubkey().ok())
+                .collect();
+            Ok(self
+                .signers
+                .iter()
+                .map(|expected| {
+                    pubkeys
+                        .iter()
+                        .position(|pubkey| pubkey.as_ref() == Some(expected))
+                        .ok_or(SignerError::NotEnoughSigners)
+                        .and_then(|i| signers[i].try_sign_message(&data))
+                })
+                .collect())
+        }
+
+        #[cfg(feature = "verify")]
+        /// Verify the message signatures, given in the order of
+        /// [`get_signers`](Self::get_signers)
+        ///
+        /// Returns whether each signature is valid for its signer.
+        pub fn verify(&self, signatures: &[Signature]) -> Result<Vec<bool>, SanitizeError> {
+            if signatures.len() != self.signers.len() {
+                return Err(SanitizeError::InvalidValue);
+            }
+            let data = self.serialize_signable()?;
+            Ok(self
+                .signers
+                .iter()
+                .zip(signatures)
+                .map(|(signer, signature)| signature.verify(signer.as_ref(), &data))
+                .collect())
+        }
+
+        pub fn get_application_domain(&self) -> &[u8; 32] {
+            &self.application_domain
+        }
+
         pub fn get_format(&self) -> MessageFormat {
             self.format
         }
 
+        pub fn get_signers(&self) -> &[Pubkey] {
+            &self.signers
+        }
+
         pub fn get_message(&self) -> &Vec<u8> {
             &self.message
         }
@@ -161,6 +412,7 @@ pub mod v0 {
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum OffchainMessage {
     V0(v0::OffchainMessage),
+    V1(v1::OffchainMessage),
 }
 
 impl OffchainMessage {
@@ -169,6 +421,9 @@ impl OffchainMessage {
     pub const HEADER_LEN: usize = Self::SIGNING_DOMAIN.len() + 1;
 
     /// Construct a new OffchainMessage object from the given version and message
+    ///
+    /// Only version 0 can be constructed from a message alone, version 1
+    /// messages are constructed with [`v1::OffchainMessage::new`].
     pub fn new(version: u8, message: &[u8]) -> Result<Self, SanitizeError> {
         match version {
             0 => Ok(Self::V0(v0::OffchainMessage::new(message)?)),
@@ -187,6 +442,10 @@ impl OffchainMessage {
                 data.push(0);
                 msg.serialize(&mut data)?;
             }
+            Self::V1(msg) => {
+                data.push(v1::OffchainMessage::VERSION);
+                msg.serialize(&mut data)?;
+            }
         }
         Ok(data)
     }
@@ -200,6 +459,7 @@ impl OffchainMessage {
         let data = &data[Self::SIGNING_DOMAIN.len().saturating_add(1)..];
         match version {
             0 => Ok(Self::V0(v0::OffchainMessage::deserialize(data)?)),
+            v1::OffchainMessage::VERSION => Ok(Self::V1(v1::OffchainMessage::deserialize(data)?)),
             _ => Err(SanitizeError::ValueOutOfBounds),
         }
     }
@@ -208,46 +468,73 @@ impl OffchainMessage {
     pub fn hash(&self) -> Result<Hash, SanitizeError> {
         match self {
             Self::V0(_) => v0::OffchainMessage::hash(&self.serialize()?),
+            Self::V1(_) => v1::OffchainMessage::hash(&self.serialize()?),
         }
     }
 
     pub fn get_version(&self) -> u8 {
         match self {
             Self::V0(_) => 0,
+            Self::V1(_) => v1::OffchainMessage::VERSION,
         }
     }
 
     pub fn get_format(&self) -> MessageFormat {
         match self {
             Self::V0(msg) => msg.get_format(),
+            Self::V1(msg) => msg.get_format(),
         }
     }
 
     pub fn get_message(&self) -> &Vec<u8> {
         match self {
             Self::V0(msg) => msg.get_message(),
+            Self::V1(msg) => msg.get_message(),
+        }
+    }
+
+    /// Check that `signer` may sign the message, which for version 1 means
+    /// that it is one of the message signers
+    fn check_signer(&self, signer: &solana_pubkey::Pubkey) -> Result<(), SanitizeError> {
+        match self {
+            Self::V1(msg) if !msg.get_signers().contains(signer) => {
+                Err(SanitizeError::InvalidValue)
+            }
+            _ => Ok(()),
         }
     }
 
     /// Sign the message with provided keypair
+    ///
+    /// Version 1 messages can only be signed by one of their signers.
     pub fn sign(&self, signer: &dyn Signer) -> Result<Signature, SanitizeError> {
+        let pubkey = signer
+            .try_pubkey()
+            .map_err(|_| SanitizeError::InvalidValue)?;
+        self.check_signer(&pubkey)?;
         Ok(signer.sign_message(&self.serialize()?))
     }
 
     #[cfg(feature = "verify")]
     /// Verify that the message signature is valid for the given public key
+    ///
+    /// Version 1 messages can only be verified for one of their signers.
     pub fn verify(
         &self,
         signer: &solana_pubkey::Pubkey,
         signature: &Signature,
     ) -> Result<bool, SanitizeError> {
+        self.check_signer(signer)?;
         Ok(signature.verify(signer.as_ref(), &self.serialize()?))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use {super::*, solana_keypair::Keypair, std::str::FromStr};
+    use {
+        super::*, solana_keypair::Keypair, solana_pubkey::Pubkey, solana_signer::SignerError,
+        std::str::FromStr,
+    };
 
     #[test]
     fn test_offchain_message_ascii() {
@@ -299,4 +586,131 @@ mod tests {
         let signature = message.sign(&keypair).unwrap();
         assert!(message.verify(&keypair.pubkey(), &signature).unwrap());
     }
+
+    #[test]
+    fn test_offchain_message_v1() {
+        let signers = [
+            Pubkey::new_from_array([1; 32]),
+            Pubkey::new_from_array([2; 32]),
+        ];
+        let v1_message = v1::OffchainMessage::new([7; 32], &signers, b"Test Message").unwrap();
+        assert_eq!(v1_message.get_application_domain(), &[7; 32]);
+        assert_eq!(v1_message.get_signers(), &signers);
+        let message = OffchainMessage::V1(v1_message);
+        assert_eq!(message.get_version(), 1);
+        assert_eq!(message.get_format(), MessageFormat::RestrictedAscii);
+        assert_eq!(message.get_message().as_slice(), b"Test Message");
+
+        let mut serialized = OffchainMessage::SIGNING_DOMAIN.to_vec();
+        serialized.push(1);
+        serialized.extend_from_slice(&[7; 32]);
+        serialized.extend_from_slice(&[0, 2]);
+        serialized.extend_from_slice(&[1; 32]);
+        serialized.extend_from_slice(&[2; 32]);
+        serialized.extend_from_slice(&[12, 0]);
+        serialized.extend_from_slice(b"Test Message");
+        assert_eq!(message.serialize().unwrap(), serialized);
+        assert_eq!(
+            message.hash().unwrap(),
+            v1::OffchainMessage::hash(&serialized).unwrap()
+        );
+        assert_eq!(message, OffchainMessage::deserialize(&serialized).unwrap());
+
+        // the signers reduce the length available to the message
+        let long_message = vec![b'a'; v1::OffchainMessage::max_len_ledger(2) + 1];
+        let message = v1::OffchainMessage::new([0; 32], &signers, &long_message).unwrap();
+        assert_eq!(message.get_format(), MessageFormat::ExtendedUtf8);
+        let message = v1::OffchainMessage::new([0; 32], &signers[..1], &long_message).unwrap();
+        assert_eq!(message.get_format(), MessageFormat::RestrictedAscii);
+        let too_long = vec![b'a'; v1::OffchainMessage::max_len(2) + 1];
+        assert_eq!(
+            v1::OffchainMessage::new([0; 32], &signers, &too_long),
+            Err(SanitizeError::ValueOutOfBounds)
+        );
+    }
+
+    #[test]
+    fn test_offchain_message_v1_invalid() {
+        let signer = Pubkey::new_from_array([1; 32]);
+        assert_eq!(
+            v1::OffchainMessage::new([0; 32], &[], b"Test Message"),
+            Err(SanitizeError::InvalidValue)
+        );
+        assert_eq!(
+            v1::OffchainMessage::new([0; 32], &[signer, signer], b"Test Message"),
+            Err(SanitizeError::InvalidValue)
+        );
+        assert_eq!(
+            v1::OffchainMessage::new([0; 32], &[signer], b""),
+            Err(SanitizeError::InvalidValue)
+        );
+        assert_eq!(
+            v1::OffchainMessage::new([0; 32], &[signer], &[0xff]),
+            Err(SanitizeError::InvalidValue)
+        );
+
+        let serialized = OffchainMessage::V1(
+            v1::OffchainMessage::new([0; 32], &[signer], b"Test Message").unwrap(),
+        )
+        .serialize()
+        .unwrap();
+        let signer_count = OffchainMessage::HEADER_LEN + 33;
+        // no signers
+        let mut invalid = serialized.clone();
+        invalid[signer_count] = 0;
+        assert!(OffchainMessage::deserialize(&invalid).is_err());
+        // more signers than the data holds
+        let mut invalid = serialized.clone();
+        invalid[signer_count] = 2;
+        assert!(OffchainMessage::deserialize(&invalid).is_err());
+        // restricted ascii with a non-ascii message
+        let mut invalid = serialized.clone();
+        *invalid.last_mut().unwrap() = 0x80;
+        assert_eq!(
+            OffchainMessage::deserialize(&invalid),
+            Err(SanitizeError::InvalidValue)
+        );
+        // truncated
+        assert_eq!(
+            OffchainMessage::deserialize(&serialized[..serialized.len() - 1]),
+            Err(SanitizeError::InvalidValue)
+        );
+    }
+
+    #[test]
+    fn test_offchain_message_v1_sign_and_verify() {
+        let keypairs = [Keypair::new(), Keypair::new()];
+        let signers: Vec<Pubkey> = keypairs.iter().map(|keypair| keypair.pubkey()).collect();
+        let message = v1::OffchainMessage::new([0; 32], &signers, b"Test Message").unwrap();
+
+        // signers in any order, with extra signers ignored
+        let extra = Keypair::new();
+        let results = message.sign(&[&keypairs[1], &extra, &keypairs[0]]).unwrap();
+        let signatures: Vec<Signature> = results.into_iter().map(Result::unwrap).collect();
+        assert_eq!(message.verify(&signatures).unwrap(), vec![true, true]);
+        assert!(OffchainMessage::V1(message.clone())
+            .verify(&signers[1], &signatures[1])
+            .unwrap());
+
+        // the enum-level API only accepts message signers
+        let enum_message = OffchainMessage::V1(message.clone());
+        assert_eq!(enum_message.sign(&keypairs[0]).unwrap(), signatures[0]);
+        assert_eq!(enum_message.sign(&extra), Err(SanitizeError::InvalidValue));
+        let extra_signature = extra.sign_message(&enum_message.serialize().unwrap());
+        assert_eq!(
+            enum_message.verify(&extra.pubkey(), &extra_signature),
+            Err(SanitizeError::InvalidValue)
+        );
+
+        let swapped = [signatures[1], signatures[0]];
+        assert_eq!(message.verify(&swapped).unwrap(), vec![false, false]);
+        assert_eq!(
+            message.verify(&signatures[..1]),
+            Err(SanitizeError::InvalidValue)
+        );
+
+        let results = message.sign(&[&keypairs[1]]).unwrap();
+        assert_eq!(results[0], Err(SignerError::NotEnoughSigners));
+        assert_eq!(results[1], Ok(signatures[1]));
+    }
 }
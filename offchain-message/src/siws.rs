@@ -0,0 +1,600 @@
+//! Sign-In With Solana (SIWS) messages.
+//!
+//! A [`SiwsMessage`] is rendered in the SIWS text format:
+//!
+//! ```text
+//! example.com wants you to sign in with your Solana account:
+//! <address>
+//!
+//! <statement>
+//!
+//! URI: https://example.com/login
+//! Version: 1
+//! Chain ID: mainnet
+//! Nonce: 32891756
+//! Issued At: 2021-09-30T16:25:24Z
+//! Expiration Time: 2021-09-30T16:35:24Z
+//! Not Before: 2021-09-30T16:25:24Z
+//! Request ID: 1
+//! Resources:
+//! - https://example.com/terms
+//! ```
+//!
+//! Everything after the address is optional. The text is signed as a
+//! [`v1::OffchainMessage`] whose only signer is the address and whose
+//! application domain is the SHA256 hash of the domain, so a signature for
+//! one site cannot be presented to another.
+//!
+//! This is not wire-compatible with wallets implementing Sign-In With
+//! Solana, which sign the raw UTF-8 text. Signatures made by such wallets
+//! are rejected by [`SignedSiwsMessage::verify`], and signatures made by
+//! [`SiwsMessage::sign`] are not accepted by verifiers expecting a signature
+//! over the raw text.
+
+use {
+    crate::{v1, OffchainMessage},
+    chrono::{DateTime, SecondsFormat, Utc},
+    solana_pubkey::Pubkey,
+    solana_sanitize::SanitizeError,
+    solana_sha256_hasher::hash,
+    solana_signature::Signature,
+    solana_signer::{Signer, SignerError},
+    std::{fmt, str::FromStr},
+};
+
+const HEADER_SUFFIX: &str = " wants you to sign in with your Solana account:";
+const URI: &str = "URI: ";
+const VERSION: &str = "Version: ";
+const CHAIN_ID: &str = "Chain ID: ";
+const NONCE: &str = "Nonce: ";
+const ISSUED_AT: &str = "Issued At: ";
+const EXPIRATION_TIME: &str = "Expiration Time: ";
+const NOT_BEFORE: &str = "Not Before: ";
+const REQUEST_ID: &str = "Request ID: ";
+const RESOURCES: &str = "Resources:";
+const RESOURCE: &str = "- ";
+/// The field tags, in the order they appear in a message after the
+/// statement.
+const TAGS: [&str; 9] = [
+    URI,
+    VERSION,
+    CHAIN_ID,
+    NONCE,
+    ISSUED_AT,
+    EXPIRATION_TIME,
+    NOT_BEFORE,
+    REQUEST_ID,
+    RESOURCES,
+];
+
+/// Errors produced when building, parsing or verifying a SIWS message
+#[derive(Debug, PartialEq, Eq)]
+pub enum SiwsError {
+    /// The first line is not a valid SIWS header
+    InvalidHeader,
+    /// The address is missing or is not a valid base58 address
+    InvalidAddress,
+    /// A field contains a line break or is otherwise malformed
+    InvalidField(&'static str),
+    /// A timestamp is not a valid RFC 3339 date and time
+    InvalidTimestamp(&'static str),
+    /// A line is not a known field, or is out of order, at the given line
+    /// number, starting from 1
+    UnexpectedLine(usize),
+    /// The message is too long or otherwise not a valid off-chain message
+    Sanitize(SanitizeError),
+    /// The signer does not match the message address
+    Signer(SignerError),
+    /// The signature does not match the message and address
+    InvalidSignature,
+    /// The message domain is not the expected domain
+    DomainMismatch,
+    /// The message nonce is not the expected nonce
+    NonceMismatch,
+    /// The message has expired
+    Expired,
+    /// The message is not valid yet
+    NotYetValid,
+}
+
+impl std::error::Error for SiwsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Sanitize(e) => Some(e),
+            Self::Signer(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SiwsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidHeader => f.write_str("invalid SIWS header"),
+            Self::InvalidAddress => f.write_str("invalid SIWS address"),
+            Self::InvalidField(field) => write!(f, "invalid SIWS field: {field}"),
+            Self::InvalidTimestamp(field) => write!(f, "invalid SIWS timestamp: {field}"),
+            Self::UnexpectedLine(line) => write!(f, "unexpected SIWS line {line}"),
+            Self::Sanitize(_) => f.write_str("invalid off-chain message"),
+            Self::Signer(_) => f.write_str("signer error"),
+            Self::InvalidSignature => f.write_str("invalid SIWS signature"),
+            Self::DomainMismatch => f.write_str("SIWS domain mismatch"),
+            Self::NonceMismatch => f.write_str("SIWS nonce mismatch"),
+            Self::Expired => f.write_str("SIWS message has expired"),
+            Self::NotYetValid => f.write_str("SIWS message is not valid yet"),
+        }
+    }
+}
+
+impl From<SanitizeError> for SiwsError {
+    fn from(e: SanitizeError) -> Self {
+        Self::Sanitize(e)
+    }
+}
+
+impl From<SignerError> for SiwsError {
+    fn from(e: SignerError) -> Self {
+        Self::Signer(e)
+    }
+}
+
+/// A Sign-In With Solana message
+///
+/// Timestamps are Unix timestamps in seconds.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct SiwsMessage {
+    pub domain: String,
+    pub address: Pubkey,
+    pub statement: Option<String>,
+    pub uri: Option<String>,
+    pub version: Option<String>,
+    pub chain_id: Option<String>,
+    pub nonce: Option<String>,
+    pub issued_at: Option<i64>,
+    pub expiration_time: Option<i64>,
+    pub not_before: Option<i64>,
+    pub request_id: Option<String>,
+    pub resources: Vec<String>,
+}
+
+fn check_field(name: &'static str, value: &str) -> Result<(), SiwsError> {
+    if value.is_empty() || value.contains(['\n', '\r']) {
+        Err(SiwsError::InvalidField(name))
+    } else {
+        Ok(())
+    }
+}
+
+fn format_timestamp(name: &'static str, timestamp: i64) -> Result<String, SiwsError> {
+    DateTime::<Utc>::from_timestamp(timestamp, 0)
+        .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .ok_or(SiwsError::InvalidTimestamp(name))
+}
+
+fn parse_timestamp(name: &'static str, value: &str) -> Result<i64, SiwsError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.timestamp())
+        .map_err(|_| SiwsError::InvalidTimestamp(name))
+}
+
+impl SiwsMessage {
+    /// Construct a message for `address` to sign in to `domain`, with no
+    /// optional fields
+    pub fn new(domain: &str, address: Pubkey) -> Self {
+        Self {
+            domain: domain.to_string(),
+            address,
+            ..Self::default()
+        }
+    }
+
+    /// Render the message in the SIWS text format
+    pub fn to_text(&self) -> Result<String, SiwsError> {
+        if self.domain.contains(char::is_whitespace) {
+            return Err(SiwsError::InvalidField("domain"));
+        }
+        check_field("domain", &self.domain)?;
+        let mut text = format!("{}{HEADER_SUFFIX}\n{}", self.domain, self.address);
+        if let Some(statement) = &self.statement {
+            check_field("statement", statement)?;
+            // A statement that looks like a field would be parsed as one.
+            if TAGS.iter().any(|tag| statement.starts_with(tag)) {
+                return Err(SiwsError::InvalidField("statement"));
+            }
+            text.push_str("\n\n");
+            text.push_str(statement);
+        }
+
+        let mut fields = Vec::new();
+        let text_fields = [
+            (URI, "uri", &self.uri),
+            (VERSION, "version", &self.version),
+            (CHAIN_ID, "chain_id", &self.chain_id),
+            (NONCE, "nonce", &self.nonce),
+        ];
+        for (tag, name, value) in text_fields {
+            if let Some(value) = value {
+                check_field(name, value)?;
+                fields.push(format!("{tag}{value}"));
+            }
+        }
+        let time_fields = [
+            (ISSUED_AT, "issued_at", self.issued_at),
+            (EXPIRATION_TIME, "expiration_time", self.expiration_time),
+            (NOT_BEFORE, "not_before", self.not_before),
+        ];
+        for (tag, name, value) in time_fields {
+            if let Some(value) = value {
+                fields.push(format!("{tag}{}", format_timestamp(name, value)?));
+            }
+        }
+        if let Some(request_id) = &self.request_id {
+            check_field("request_id", request_id)?;
+            fields.push(format!("{REQUEST_ID}{request_id}"));
+        }
+        if !self.resources.is_empty() {
+            fields.push(RESOURCES.to_string());
+            for resource in &self.resources {
+                check_field("resources", resource)?;
+                fields.push(format!("{RESOURCE}{resource}"));
+            }
+        }
+        if !fields.is_empty() {
+            text.push_str("\n\n");
+            text.push_str(&fields.join("\n"));
+        }
+        Ok(text)
+    }
+
+    /// Wrap the message text in the off-chain message that is signed
+    fn offchain_message(&self, text: &str) -> Result<OffchainMessage, SiwsError> {
+        let application_domain = hash(self.domain.as_bytes()).to_bytes();
+        Ok(OffchainMessage::V1(v1::OffchainMessage::new(
+            application_domain,
+            &[self.address],
+            text.as_bytes(),
+        )?))
+    }
+
+    /// Sign the message with the signer for its address
+    ///
+    /// The text is signed inside an off-chain message, not as raw text, see
+    /// the [module documentation](self).
+    pub fn sign(&self, signer: &dyn Signer) -> Result<SignedSiwsMessage, SiwsError> {
+        if signer.try_pubkey()? != self.address {
+            return Err(SignerError::KeypairPubkeyMismatch.into());
+        }
+        let text = self.to_text()?;
+        let signature = signer.try_sign_message(&self.offchain_message(&text)?.serialize()?)?;
+        Ok(SignedSiwsMessage {
+            message: self.clone(),
+            text,
+            signature,
+        })
+    }
+}
+
+impl FromStr for SiwsMessage {
+    type Err = SiwsError;
+
+    /// Parse a message in the SIWS text format
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        // Lines with their line numbers, starting from 1.
+        let mut lines = (1..).zip(text.split('\n')).peekable();
+        let (_, header) = lines.next().ok_or(SiwsError::InvalidHeader)?;
+        let domain = header
+            .strip_suffix(HEADER_SUFFIX)
+            .filter(|domain| !domain.is_empty() && !domain.contains(char::is_whitespace))
+            .ok_or(SiwsError::InvalidHeader)?;
+        let address = lines
+            .next()
+            .and_then(|(_, address)| Pubkey::from_str(address).ok())
+            .ok_or(SiwsError::InvalidAddress)?;
+        let mut message = Self::new(domain, address);
+
+        // Each section after the address is preceded by an empty line.
+        let next_section = |lines: &mut std::iter::Peekable<_>| match lines.next() {
+            None => Ok(false),
+            Some((_, "")) if lines.peek().is_some() => Ok(true),
+            Some((number, _)) => Err(SiwsError::UnexpectedLine(number)),
+        };
+        if !next_section(&mut lines)? {
+            return Ok(message);
+        }
+
+        let is_field = |line: &str| TAGS.iter().any(|tag| line.starts_with(tag));
+        if let Some((_, line)) = lines.next_if(|(_, line)| !is_field(line)) {
+            check_field("statement", line)?;
+            message.statement = Some(line.to_string());
+            if !next_section(&mut lines)? {
+                return Ok(message);
+            }
+        }
+
+        // Fields must appear in order, at most once each.
+        let mut tags = TAGS.iter();
+        for (number, line) in lines.by_ref() {
+            let tag = *tags
+                .find(|tag| line.starts_with(*tag))
+                .ok_or(SiwsError::UnexpectedLine(number))?;
+            let value = &line[tag.len()..];
+            let text_value = |name| check_field(name, value).map(|_| Some(value.to_string()));
+            match tag {
+                URI => message.uri = text_value("uri")?,
+                VERSION => message.version = text_value("version")?,
+                CHAIN_ID => message.chain_id = text_value("chain_id")?,
+                NONCE => message.nonce = text_value("nonce")?,
+                ISSUED_AT => message.issued_at = Some(parse_timestamp("issued_at", value)?),
+                EXPIRATION_TIME => {
+                    message.expiration_time = Some(parse_timestamp("expiration_time", value)?)
+                }
+                NOT_BEFORE => message.not_before = Some(parse_timestamp("not_before", value)?),
+                REQUEST_ID => message.request_id = text_value("request_id")?,
+                _ => {
+                    if !value.is_empty() {
+                        return Err(SiwsError::UnexpectedLine(number));
+                    }
+                    break;
+                }
+            }
+        }
+        for (number, line) in lines {
+            let resource = line
+                .strip_prefix(RESOURCE)
+                .ok_or(SiwsError::UnexpectedLine(number))?;
+            check_field("resources", resource)?;
+            message.resources.push(resource.to_string());
+        }
+        Ok(message)
+    }
+}
+
+/// A SIWS message with its signature
+///
+/// The signature covers the exact text of the message, which is kept as
+/// received.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SignedSiwsMessage {
+    message: SiwsMessage,
+    text: String,
+    signature: Signature,
+}
+
+impl SignedSiwsMessage {
+    /// Parse a signed message from its text and signature
+    ///
+    /// The signature is not verified.
+    pub fn parse(text: &str, signature: Signature) -> Result<Self, SiwsError> {
+        Ok(Self {
+            message: text.parse()?,
+            text: text.to_string(),
+            signature,
+        })
+    }
+
+    pub fn message(&self) -> &SiwsMessage {
+        &self.message
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    /// Verify the signature, that the message is for `domain` and, if given,
+    /// `nonce`, and that it is valid at the Unix timestamp `now`
+    ///
+    /// A login should only be accepted once per nonce, so the caller must
+    /// issue a fresh nonce for each login to reject replayed messages.
+    ///
+    /// The signature must be over the off-chain message made by
+    /// [`SiwsMessage::sign`]. A signature over the raw text, as made by
+    /// wallets, is rejected.
+    pub fn verify(&self, domain: &str, nonce: Option<&str>, now: i64) -> Result<(), SiwsError> {
+        let message = &self.message;
+        if message.domain != domain {
+            return Err(SiwsError::DomainMismatch);
+        }
+        if nonce.is_some_and(|nonce| message.nonce.as_deref() != Some(nonce)) {
+            return Err(SiwsError::NonceMismatch);
+        }
+        let data = message.offchain_message(&self.text)?.serialize()?;
+        if !self.signature.verify(message.address.as_ref(), &data) {
+            return Err(SiwsError::InvalidSignature);
+        }
+        if message
+            .expiration_time
+            .is_some_and(|expiration_time| now >= expiration_time)
+        {
+            return Err(SiwsError::Expired);
+        }
+        if message
+            .not_before
+            .is_some_and(|not_before| now < not_before)
+            || message.issued_at.is_some_and(|issued_at| now < issued_at)
+        {
+            return Err(SiwsError::NotYetValid);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, solana_keypair::Keypair};
+
+    const ADDRESS: &str = "4Nd1mBQtrMJVYVfKf2PJy9NZUZdTAsp7D4xWLs4gDB4T";
+
+    fn full_message() -> SiwsMessage {
+        SiwsMessage {
+            statement: Some("Sign in to Example".to_string()),
+            uri: Some("https://example.com/login".to_string()),
+            version: Some("1".to_string()),
+            chain_id: Some("mainnet".to_string()),
+            nonce: Some("32891756".to_string()),
+            issued_at: Some(1_633_019_124),
+            expiration_time: Some(1_633_019_724),
+            not_before: Some(1_633_019_124),
+            request_id: Some("1".to_string()),
+            resources: vec![
+                "https://example.com/terms".to_string(),
+                "ipfs://bafybeiemxf5abjwjbikoz4mc3a3dla6ual3jsgpdr4cjr3oz3evfyavhwq".to_string(),
+            ],
+            ..SiwsMessage::new("example.com", Pubkey::from_str(ADDRESS).unwrap())
+        }
+    }
+
+    #[test]
+    fn test_text_roundtrip() {
+        let message = full_message();
+        let text = message.to_text().unwrap();
+        assert_eq!(
+            text,
+            format!(
+                "example.com wants you to sign in with your Solana account:\n{ADDRESS}\n\nSign \
+                 in to Example\n\nURI: https://example.com/login\nVersion: 1\nChain ID: \
+                 mainnet\nNonce: 32891756\nIssued At: 2021-09-30T16:25:24Z\nExpiration Time: \
+                 2021-09-30T16:35:24Z\nNot Before: 2021-09-30T16:25:24Z\nRequest ID: \
+                 1\nResources:\n- https://example.com/terms\n- \
+                 ipfs://bafybeiemxf5abjwjbikoz4mc3a3dla6ual3jsgpdr4cjr3oz3evfyavhwq"
+            )
+        );
+        assert_eq!(text.parse::<SiwsMessage>().unwrap(), message);
+
+        let minimal = SiwsMessage::new("example.com", Pubkey::from_str(ADDRESS).unwrap());
+        let text = minimal.to_text().unwrap();
+        assert_eq!(
+            text,
+            format!("example.com wants you to sign in with your Solana account:\n{ADDRESS}")
+        );
+        assert_eq!(text.parse::<SiwsMessage>().unwrap(), minimal);
+
+        let without_statement = SiwsMessage {
+            statement: None,
+            resources: vec![],
+            ..full_message()
+        };
+        let text = without_statement.to_text().unwrap();
+        assert_eq!(text.parse::<SiwsMessage>().unwrap(), without_statement);
+
+        let statement_only = SiwsMessage {
+            statement: Some("Hello".to_string()),
+            ..minimal
+        };
+        let text = statement_only.to_text().unwrap();
+        assert_eq!(text.parse::<SiwsMessage>().unwrap(), statement_only);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let header = "example.com wants you to sign in with your Solana account:";
+        let parse = |text: &str| text.parse::<SiwsMessage>().unwrap_err();
+        assert_eq!(parse(""), SiwsError::InvalidHeader);
+        assert_eq!(
+            parse(&format!(
+                "evil site wants you to sign in with your Solana account:\n{ADDRESS}"
+            )),
+            SiwsError::InvalidHeader
+        );
+        assert_eq!(parse(header), SiwsError::InvalidAddress);
+        assert_eq!(
+            parse(&format!("{header}\nnot-an-address")),
+            SiwsError::InvalidAddress
+        );
+        assert_eq!(
+            parse(&format!("{header}\n{ADDRESS}\nNonce: 1")),
+            SiwsError::UnexpectedLine(3)
+        );
+        assert_eq!(
+            parse(&format!("{header}\n{ADDRESS}\n\n")),
+            SiwsError::InvalidField("statement")
+        );
+        // fields out of order
+        assert_eq!(
+            parse(&format!("{header}\n{ADDRESS}\n\nNonce: 1\nURI: https://a")),
+            SiwsError::UnexpectedLine(5)
+        );
+        // duplicate fields
+        assert_eq!(
+            parse(&format!("{header}\n{ADDRESS}\n\nNonce: 1\nNonce: 2")),
+            SiwsError::UnexpectedLine(5)
+        );
+        assert_eq!(
+            parse(&format!("{header}\n{ADDRESS}\n\nIssued At: yesterday")),
+            SiwsError::InvalidTimestamp("issued_at")
+        );
+        assert_eq!(
+            parse(&format!("{header}\n{ADDRESS}\n\nResources:\nhttps://a")),
+            SiwsError::UnexpectedLine(5)
+        );
+        assert_eq!(
+            parse(&format!("{header}\n{ADDRESS}\n\nNonce: ")),
+            SiwsError::InvalidField("nonce")
+        );
+
+        let invalid = SiwsMessage {
+            statement: Some("two\nlines".to_string()),
+            ..full_message()
+        };
+        assert_eq!(
+            invalid.to_text().unwrap_err(),
+            SiwsError::InvalidField("statement")
+        );
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let keypair = Keypair::new();
+        let message = SiwsMessage {
+            address: keypair.pubkey(),
+            ..full_message()
+        };
+        assert_eq!(
+            message.sign(&Keypair::new()).unwrap_err(),
+            SiwsError::Signer(SignerError::KeypairPubkeyMismatch)
+        );
+        let signed = message.sign(&keypair).unwrap();
+        assert_eq!(signed.message(), &message);
+
+        let parsed = SignedSiwsMessage::parse(signed.text(), *signed.signature()).unwrap();
+        assert_eq!(parsed, signed);
+        let now = 1_633_019_200;
+        assert_eq!(parsed.verify("example.com", Some("32891756"), now), Ok(()));
+        assert_eq!(parsed.verify("example.com", None, now), Ok(()));
+        assert_eq!(
+            parsed.verify("evil.com", Some("32891756"), now),
+            Err(SiwsError::DomainMismatch)
+        );
+        assert_eq!(
+            parsed.verify("example.com", Some("00000000"), now),
+            Err(SiwsError::NonceMismatch)
+        );
+        assert_eq!(
+            parsed.verify("example.com", None, 1_633_019_724),
+            Err(SiwsError::Expired)
+        );
+        assert_eq!(
+            parsed.verify("example.com", None, 1_633_019_123),
+            Err(SiwsError::NotYetValid)
+        );
+
+        // the signature covers the exact text
+        let tampered_text = signed.text().replace("32891756", "32891757");
+        let tampered = SignedSiwsMessage::parse(&tampered_text, *signed.signature()).unwrap();
+        assert_eq!(
+            tampered.verify("example.com", None, now),
+            Err(SiwsError::InvalidSignature)
+        );
+
+        // a raw signature of the text is not accepted
+        let raw_signature = keypair.sign_message(signed.text().as_bytes());
+        let raw = SignedSiwsMessage::parse(signed.text(), raw_signature).unwrap();
+        assert_eq!(
+            raw.verify("example.com", None, now),
+            Err(SiwsError::InvalidSignature)
+        );
+    }
+}
@@ -0,0 +1,424 @@
+//! A heap allocator that reuses freed memory.
+//!
+//! [`BumpAllocator`](crate::BumpAllocator) never frees, so a program that
+//! repeatedly allocates and drops values eventually runs out of heap.
+//! [`FreeListAllocator`] rounds every allocation up to a power-of-two size
+//! class and keeps a free list per class, so a freed block is handed out
+//! again to the next allocation of the same class.
+
+use {
+    crate::BPF_ALIGN_OF_U128,
+    core::{
+        mem::size_of,
+        ptr::{copy_nonoverlapping, null_mut, write_bytes},
+    },
+    std::alloc::{GlobalAlloc, Layout},
+};
+
+/// Size of the smallest size class.
+const MIN_BLOCK_SIZE: usize = 16;
+
+/// Number of size classes, from 16 bytes to 512 KiB.
+const NUM_SIZE_CLASSES: usize = 16;
+
+/// Minimum alignment of a block, which also fits the free list link.
+const MIN_ALIGN: usize = BPF_ALIGN_OF_U128;
+
+/// Allocator state, stored at the start of the arena.
+///
+/// The heap of a program is zero-initialized, so a zero `bump` marks a
+/// header that has not been initialized yet.
+#[repr(C)]
+struct Header {
+    /// Address of the first byte that has not been carved into a block.
+    bump: usize,
+    /// Highest value `bump` has reached.
+    peak_bump: usize,
+    /// Bytes in blocks currently allocated.
+    allocated: usize,
+    /// Highest value `allocated` has reached.
+    peak_allocated: usize,
+    /// Address of the first free block of each size class, or `0`.
+    free: [usize; NUM_SIZE_CLASSES],
+}
+
+/// Heap usage statistics of a [`FreeListAllocator`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HeapStats {
+    /// Length of the heap.
+    pub capacity: usize,
+    /// Bytes carved into blocks, including the allocator state.
+    pub reserved: usize,
+    /// Highest value `reserved` has reached. This is the heap length the
+    /// program has needed so far.
+    pub peak_reserved: usize,
+    /// Bytes in blocks currently allocated.
+    pub allocated: usize,
+    /// Highest value `allocated` has reached.
+    pub peak_allocated: usize,
+}
+
+#[cfg(feature = "log")]
+impl HeapStats {
+    /// Logs the statistics with `solana-program-log`.
+    pub fn log(&self) {
+        let mut logger = solana_program_log::Logger::<160>::default();
+        logger
+            .append("heap: capacity=")
+            .append(self.capacity)
+            .append(" reserved=")
+            .append(self.reserved)
+            .append(" peak_reserved=")
+            .append(self.peak_reserved)
+            .append(" allocated=")
+            .append(self.allocated)
+            .append(" peak_allocated=")
+            .append(self.peak_allocated);
+        logger.log();
+    }
+}
+
+/// A size-class free-list allocator for the program heap.
+///
+/// Allocations are rounded up to a power of two of at least 16 bytes. Each
+/// size class keeps a list of freed blocks; an allocation takes the most
+/// recently freed block of its class, or carves a new one from the unused
+/// end of the heap. Freed blocks are not split or merged, so memory freed
+/// in one size class is only reused by allocations of the same class, except
+/// for the most recently carved block, which is returned to the unused end.
+///
+/// The allocator keeps its state in the first bytes of the heap, so any
+/// instance created over the same address range shares it. A program can
+/// read the [`HeapStats`] of its global allocator by creating another
+/// instance with [`with_fixed_address_range`](Self::with_fixed_address_range).
+pub struct FreeListAllocator {
+    start: usize,
+    len: usize,
+}
+
+impl FreeListAllocator {
+    /// Creates the allocator tied to a provided slice.
+    ///
+    /// # Safety
+    /// As long as FreeListAllocator or any of its allocations are alive,
+    /// writing into or deallocating the arena will cause UB.
+    ///
+    /// The start of `arena` must be aligned to `align_of::<usize>()`, since
+    /// the allocator state is stored at the start of the arena. `arena` must
+    /// also be larger than the allocator state.
+    #[inline]
+    pub unsafe fn new(arena: &mut [u8]) -> Self {
+        debug_assert!(
+            arena.len() > size_of::<Header>(),
+            "Arena should be larger than the allocator state"
+        );
+        debug_assert!(
+            arena.as_ptr().align_offset(core::mem::align_of::<usize>()) == 0,
+            "Arena must be aligned to `align_of::<usize>()`"
+        );
+
+        write_bytes(arena.as_mut_ptr(), 0, size_of::<Header>());
+
+        Self {
+            start: arena.as_mut_ptr() as usize,
+            len: arena.len(),
+        }
+    }
+
+    /// Creates the allocator tied to specific range of addresses.
+    ///
+    /// # Safety
+    /// This is unsafe in most situations, unless you are totally sure that the
+    /// provided start address and length can be written to by the allocator,
+    /// and that the memory will be usable for the lifespan of the allocator.
+    /// The first bytes of the range must be zero until the first allocation.
+    ///
+    /// `start` must be aligned to `align_of::<usize>()`, because the allocator
+    /// stores its state at `start`.
+    ///
+    /// For Solana on-chain programs, `start` is [`HEAP_START_ADDRESS`] and
+    /// `len` is the heap frame requested by the transaction, either with
+    /// `ComputeBudgetInstruction::request_heap_frame` or the heap size of a
+    /// v1 transaction config. It defaults to [`HEAP_LENGTH`].
+    ///
+    /// [`HEAP_START_ADDRESS`]: crate::HEAP_START_ADDRESS
+    /// [`HEAP_LENGTH`]: crate::HEAP_LENGTH
+    pub const unsafe fn with_fixed_address_range(start: usize, len: usize) -> Self {
+        Self { start, len }
+    }
+
+    /// Returns the heap usage statistics.
+    ///
+    /// # Safety
+    /// The allocator state must not be accessed concurrently.
+    pub unsafe fn stats(&self) -> HeapStats {
+        let header = self.header();
+        HeapStats {
+            capacity: self.len,
+            reserved: header.bump.saturating_sub(self.start),
+            peak_reserved: header.peak_bump.saturating_sub(self.start),
+            allocated: header.allocated,
+            peak_allocated: header.peak_allocated,
+        }
+    }
+
+    #[allow(clippy::arithmetic_side_effects, clippy::mut_from_ref)]
+    #[inline(always)]
+    unsafe fn header(&self) -> &mut Header {
+        let header = &mut *(self.start as *mut Header);
+        if header.bump == 0 {
+            // First time, set starting position
+            header.bump = self.start + size_of::<Header>();
+            header.peak_bump = header.bump;
+        }
+        header
+    }
+}
+
+/// Returns the size class of `layout`, or `None` if it is too large.
+#[inline(always)]
+fn size_class(layout: Layout) -> Option<usize> {
+    let size = layout
+        .size()
+        .max(layout.align())
+        .max(MIN_BLOCK_SIZE)
+        .checked_next_power_of_two()?;
+    let class = size
+        .trailing_zeros()
+        .saturating_sub(MIN_BLOCK_SIZE.trailing_zeros()) as usize;
+    (class < NUM_SIZE_CLASSES).then_some(class)
+}
+
+/// Returns the size of the blocks in `class`.
+#[inline(always)]
+const fn class_size(class: usize) -> usize {
+    MIN_BLOCK_SIZE << class
+}
+
+/// Integer arithmetic in this global allocator implementation is safe when
+/// operating on the prescribed `HEAP_START_ADDRESS` and heap length. Any
+/// other use may overflow and is thus unsupported and at one's own risk.
+#[allow(clippy::arithmetic_side_effects)]
+unsafe impl GlobalAlloc for FreeListAllocator {
+    #[inline]
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let Some(class) = size_class(layout) else {
+            return null_mut();
+        };
+        let size = class_size(class);
+        let header = self.header();
+
+        // Reuse the most recently freed block of the class, unless it does
+        // not meet a larger than usual alignment.
+        let head = header.free[class];
+        let ptr = if head != 0 && head & (layout.align() - 1) == 0 {
+            header.free[class] = *(head as *const usize);
+            head
+        } else {
+            let align = layout.align().max(MIN_ALIGN);
+            let ptr = (header.bump + align - 1) & !(align - 1);
+            if ptr + size > self.start + self.len {
+                return null_mut();
+            }
+            header.bump = ptr + size;
+            header.peak_bump = header.peak_bump.max(header.bump);
+            ptr
+        };
+
+        header.allocated += size;
+        header.peak_allocated = header.peak_allocated.max(header.allocated);
+        ptr as *mut u8
+    }
+
+    #[inline]
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let Some(class) = size_class(layout) else {
+            return;
+        };
+        let size = class_size(class);
+        let header = self.header();
+        header.allocated -= size;
+
+        let ptr = ptr as usize;
+        if ptr + size == header.bump {
+            // Give the last carved block back to the unused end of the heap.
+            header.bump = ptr;
+        } else {
+            *(ptr as *mut usize) = header.free[class];
+            header.free[class] = ptr;
+        }
+    }
+
+    #[inline]
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        if size_class(layout) == size_class(new_layout) {
+            // The block already fits the new size.
+            return ptr;
+        }
+
+        let new_ptr = self.alloc(new_layout);
+        if !new_ptr.is_null() {
+            copy_nonoverlapping(ptr, new_ptr, layout.size().min(new_size));
+            self.dealloc(ptr, layout);
+        }
+        new_ptr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::BumpAllocator};
+
+    fn layout(size: usize, align: usize) -> Layout {
+        Layout::from_size_align(size, align).unwrap()
+    }
+
+    fn arena(heap: &mut [u64]) -> &mut [u8] {
+        unsafe {
+            std::slice::from_raw_parts_mut(
+                heap.as_mut_ptr() as *mut u8,
+                core::mem::size_of_val(heap),
+            )
+        }
+    }
+
+    #[test]
+    fn test_alignment() {
+        let mut heap = [0u64; 512];
+        let allocator = unsafe { FreeListAllocator::new(arena(&mut heap)) };
+        for align in [1, 2, 4, 8, 16, 32, 64, 128] {
+            for size in [1, 3, 8, 17, 100] {
+                let ptr = unsafe { allocator.alloc(layout(size, align)) };
+                assert!(!ptr.is_null());
+                assert_eq!(ptr.align_offset(align), 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_alignment_reuse() {
+        // A freed block is only reused for an alignment it meets. Shifting
+        // the arena by 8 bytes covers both a 16-byte aligned and a misaligned
+        // freed block.
+        for shift in [0, 8] {
+            let mut heap = [0u64; 128];
+            let allocator = unsafe { FreeListAllocator::new(&mut arena(&mut heap)[shift..]) };
+            let freed = unsafe { allocator.alloc(layout(8, 8)) };
+            let _guard = unsafe { allocator.alloc(layout(8, 8)) };
+            unsafe { allocator.dealloc(freed, layout(8, 8)) };
+
+            let aligned = unsafe { allocator.alloc(layout(16, 16)) };
+            assert_eq!(aligned.align_offset(16), 0);
+            assert_eq!(aligned == freed, freed.align_offset(16) == 0);
+        }
+    }
+
+    #[test]
+    fn test_reuse() {
+        let mut heap = [0u64; 128];
+        let allocator = unsafe { FreeListAllocator::new(arena(&mut heap)) };
+        let first = unsafe { allocator.alloc(layout(24, 8)) };
+        let second = unsafe { allocator.alloc(layout(32, 8)) };
+        let third = unsafe { allocator.alloc(layout(10, 8)) };
+        assert!(!first.is_null() && !second.is_null() && !third.is_null());
+
+        // Blocks in the middle of the heap go to their free list.
+        unsafe { allocator.dealloc(second, layout(32, 8)) };
+        assert_eq!(unsafe { allocator.alloc(layout(20, 8)) }, second);
+
+        // The last carved block goes back to the unused end.
+        unsafe { allocator.dealloc(third, layout(10, 8)) };
+        assert_eq!(unsafe { allocator.alloc(layout(100, 8)) }, third);
+
+        // Growing within the size class keeps the block in place.
+        let grown = unsafe { allocator.realloc(first, layout(24, 8), 32) };
+        assert_eq!(grown, first);
+    }
+
+    #[test]
+    fn test_exhaustion() {
+        let mut heap = [0u64; 64];
+        let allocator = unsafe { FreeListAllocator::new(arena(&mut heap)) };
+        let capacity = heap.len() * size_of::<u64>() - size_of::<Header>();
+
+        let ptr = unsafe { allocator.alloc(layout(capacity / 2, 8)) };
+        assert!(!ptr.is_null());
+        assert!(unsafe { allocator.alloc(layout(capacity, 8)) }.is_null());
+        assert!(unsafe { allocator.alloc(layout(1 << 40, 8)) }.is_null());
+    }
+
+    #[test]
+    fn test_fragmentation() {
+        // Allocate and free vectors of varying sizes in a loop, keeping a few
+        // alive at a time, the way a long-running instruction would.
+        let sizes = [16, 40, 100, 250, 600, 24, 1000];
+        let run = |allocator: &dyn GlobalAlloc| -> usize {
+            let mut live: [(*mut u8, Layout); 3] = [(null_mut(), layout(1, 1)); 3];
+            for i in 0..10_000 {
+                let slot = i % live.len();
+                let (ptr, old_layout) = live[slot];
+                if !ptr.is_null() {
+                    unsafe { allocator.dealloc(ptr, old_layout) };
+                }
+                let new_layout = layout(sizes[i % sizes.len()], 8);
+                let ptr = unsafe { allocator.alloc(new_layout) };
+                if ptr.is_null() {
+                    return i;
+                }
+                live[slot] = (ptr, new_layout);
+            }
+            10_000
+        };
+
+        let mut heap = [0u64; 1024];
+        let allocator = unsafe { FreeListAllocator::new(arena(&mut heap)) };
+        assert_eq!(run(&allocator), 10_000);
+        let stats = unsafe { allocator.stats() };
+        assert!(stats.peak_reserved < stats.capacity);
+        assert!(stats.peak_allocated <= 3 * 1024);
+
+        // The same workload exhausts a bump allocator of the same size.
+        let mut heap = [0u64; 1024];
+        let allocator = unsafe { BumpAllocator::new(arena(&mut heap)) };
+        assert!(run(&allocator) < 10_000);
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut heap = [0u64; 128];
+        let allocator = unsafe { FreeListAllocator::new(arena(&mut heap)) };
+        let header_len = size_of::<Header>();
+        assert_eq!(
+            unsafe { allocator.stats() },
+            HeapStats {
+                capacity: 1024,
+                reserved: header_len,
+                peak_reserved: header_len,
+                allocated: 0,
+                peak_allocated: 0,
+            }
+        );
+
+        let first = unsafe { allocator.alloc(layout(100, 8)) };
+        let second = unsafe { allocator.alloc(layout(8, 8)) };
+        unsafe { allocator.dealloc(first, layout(100, 8)) };
+        unsafe { allocator.dealloc(second, layout(8, 8)) };
+        assert_eq!(
+            unsafe { allocator.stats() },
+            HeapStats {
+                capacity: 1024,
+                reserved: header_len + 128,
+                peak_reserved: header_len + 128 + 16,
+                allocated: 0,
+                peak_allocated: 128 + 16,
+            }
+        );
+
+        // Another instance over the same memory shares the state.
+        let other = unsafe {
+            FreeListAllocator::with_fixed_address_range(heap.as_mut_ptr() as usize, 1024)
+        };
+        assert_eq!(unsafe { other.stats() }.peak_allocated, 144);
+    }
+}
@@ -0,0 +1,266 @@
+//! Lazy program entrypoint.
+//!
+//! The [`lazy_entrypoint!`] macro hands the program an [`InstructionContext`]
+//! instead of the deserialized input. Accounts are parsed one at a time, when
+//! the program asks for them, so an instruction that only reads the first
+//! few of many accounts does not pay for the rest.
+//!
+//! [`lazy_entrypoint!`]: crate::lazy_entrypoint
+
+use {
+    crate::{deserialize_account_view, deserialize_instruction_data, NON_DUP_MARKER},
+    core::mem::size_of,
+    solana_account_view::AccountView,
+    solana_program_error::ProgramError,
+    solana_pubkey::Pubkey,
+};
+
+/// Declare the program entrypoint and set up global handlers, parsing the
+/// input accounts on demand.
+///
+/// The argument is the name of a function with this type signature:
+///
+/// ```ignore
+/// fn process_instruction(
+///     context: InstructionContext, // Cursor over the serialized input
+/// ) -> ProgramResult;
+/// ```
+///
+/// Each call to [`InstructionContext::next_account`] parses one more account,
+/// and the instruction data and program id become available once every
+/// account has been read.
+///
+/// For more information about how the program entrypoint behaves and what it
+/// does, please see the documentation for [`entrypoint!`].
+///
+/// # Examples
+///
+/// ```no_run
+/// #[cfg(not(feature = "no-entrypoint"))]
+/// pub mod entrypoint {
+///
+///     use solana_program_entrypoint::lazy_entrypoint;
+///     use solana_program_entrypoint::InstructionContext;
+///     use solana_program_entrypoint::ProgramResult;
+///     use solana_msg::msg;
+///
+///     lazy_entrypoint!(process_instruction);
+///
+///     pub fn process_instruction(mut context: InstructionContext) -> ProgramResult {
+///         let payer = context.next_account()?.assume_account();
+///         msg!("Payer has {} lamports", payer.lamports());
+///
+///         Ok(())
+///     }
+///
+/// }
+/// ```
+///
+/// [`entrypoint!`]: crate::entrypoint
+#[macro_export]
+macro_rules! lazy_entrypoint {
+    ($process_instruction:ident) => {
+        /// # Safety
+        #[no_mangle]
+        pub unsafe extern "C" fn entrypoint(input: *mut u8) -> u64 {
+            match $process_instruction(unsafe { $crate::InstructionContext::new_unchecked(input) })
+            {
+                Ok(()) => $crate::SUCCESS,
+                Err(error) => error.into(),
+            }
+        }
+        $crate::custom_heap_default!();
+        $crate::custom_panic_default!();
+    };
+}
+
+/// An account read from the input buffer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MaybeAccount {
+    /// The first occurrence of an account.
+    Account(AccountView),
+
+    /// A duplicate of the account at the given position in the input.
+    Duplicated(u8),
+}
+
+impl MaybeAccount {
+    /// Returns the account view, panicking if the account is a duplicate.
+    #[inline(always)]
+    pub fn assume_account(self) -> AccountView {
+        match self {
+            MaybeAccount::Account(account) => account,
+            MaybeAccount::Duplicated(index) => {
+                panic!("account is a duplicate of the account at index {index}")
+            }
+        }
+    }
+}
+
+/// A cursor over the serialized input of an instruction.
+///
+/// Accounts are read in order with [`next_account`](Self::next_account). A
+/// duplicate account is returned as the index of the account it duplicates,
+/// which the program resolves against the accounts it has already read.
+#[derive(Debug)]
+pub struct InstructionContext {
+    /// Start of the input buffer.
+    input: *mut u8,
+
+    /// Offset of the next unread account, or of the instruction data once
+    /// all accounts have been read.
+    offset: usize,
+
+    /// Number of accounts that have not been read.
+    remaining: u64,
+}
+
+impl InstructionContext {
+    /// Creates a new [`InstructionContext`] over the input buffer.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `input` points to an input buffer
+    /// serialized by the runtime, which must stay valid for the lifetime of
+    /// the context and every account view read from it.
+    #[inline(always)]
+    pub unsafe fn new_unchecked(input: *mut u8) -> Self {
+        #[allow(clippy::cast_ptr_alignment)]
+        let remaining = *(input as *const u64);
+        Self {
+            input,
+            offset: size_of::<u64>(),
+            remaining,
+        }
+    }
+
+    /// Returns the number of accounts that have not been read.
+    #[inline(always)]
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    /// Reads the next account, failing if all accounts have been read.
+    #[inline(always)]
+    pub fn next_account(&mut self) -> Result<MaybeAccount, ProgramError> {
+        if self.remaining == 0 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        // SAFETY: There is at least one account left to read.
+        Ok(unsafe { self.next_account_unchecked() })
+    }
+
+    /// Reads the next account without checking that one is left.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that [`remaining`](Self::remaining) is not
+    /// zero. Otherwise the instruction data is read as an account.
+    #[allow(clippy::arithmetic_side_effects)]
+    #[inline(always)]
+    pub unsafe fn next_account_unchecked(&mut self) -> MaybeAccount {
+        self.remaining -= 1;
+
+        let dup_info = *self.input.add(self.offset);
+        if dup_info == NON_DUP_MARKER {
+            let (account_view, offset) = deserialize_account_view(self.input, self.offset);
+            self.offset = offset;
+            MaybeAccount::Account(account_view)
+        } else {
+            self.offset += size_of::<u64>(); // marker and padding
+            MaybeAccount::Duplicated(dup_info)
+        }
+    }
+
+    /// Returns the instruction data, failing if any account has not been
+    /// read.
+    #[inline(always)]
+    pub fn instruction_data(&self) -> Result<&[u8], ProgramError> {
+        if self.remaining > 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        // SAFETY: All accounts have been read.
+        Ok(unsafe { self.instruction_data_unchecked() })
+    }
+
+    /// Returns the instruction data without checking that all accounts have
+    /// been read.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that [`remaining`](Self::remaining) is zero.
+    /// Otherwise the next account is read as instruction data.
+    #[inline(always)]
+    pub unsafe fn instruction_data_unchecked(&self) -> &[u8] {
+        deserialize_instruction_data(self.input, self.offset).0
+    }
+
+    /// Returns the id of the executing program, failing if any account has
+    /// not been read.
+    #[inline(always)]
+    pub fn program_id(&self) -> Result<&Pubkey, ProgramError> {
+        if self.remaining > 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        // SAFETY: All accounts have been read.
+        Ok(unsafe { self.program_id_unchecked() })
+    }
+
+    /// Returns the id of the executing program without checking that all
+    /// accounts have been read.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that [`remaining`](Self::remaining) is zero.
+    /// Otherwise part of the next account is read as the program id.
+    #[inline(always)]
+    pub unsafe fn program_id_unchecked(&self) -> &Pubkey {
+        let (_, offset) = deserialize_instruction_data(self.input, self.offset);
+        &*(self.input.add(offset) as *const Pubkey)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::test::{serialize_input, KEY, OTHER_KEY, PROGRAM_ID},
+    };
+
+    #[test]
+    fn test_instruction_context() {
+        let mut buffer = serialize_input();
+        let mut context =
+            unsafe { InstructionContext::new_unchecked(buffer.as_mut_ptr() as *mut u8) };
+        assert_eq!(context.remaining(), 3);
+        assert_eq!(
+            context.instruction_data(),
+            Err(ProgramError::InvalidInstructionData)
+        );
+        assert_eq!(
+            context.program_id(),
+            Err(ProgramError::InvalidInstructionData)
+        );
+
+        let first = context.next_account().unwrap().assume_account();
+        assert_eq!(first.address(), &KEY);
+        assert_eq!(&*first.try_borrow().unwrap(), [5, 6, 7]);
+        let second = context.next_account().unwrap().assume_account();
+        assert_eq!(second.address(), &OTHER_KEY);
+        assert_eq!(context.next_account().unwrap(), MaybeAccount::Duplicated(0));
+        assert_eq!(context.remaining(), 0);
+        assert_eq!(
+            context.next_account(),
+            Err(ProgramError::NotEnoughAccountKeys)
+        );
+
+        assert_eq!(context.instruction_data().unwrap(), [8, 9]);
+        assert_eq!(context.program_id().unwrap(), &PROGRAM_ID);
+    }
+
+    #[test]
+    #[should_panic(expected = "account is a duplicate of the account at index 0")]
+    fn test_assume_account_duplicate() {
+        MaybeAccount::Duplicated(0).assume_account();
+    }
+}
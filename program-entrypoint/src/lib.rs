@@ -2,6 +2,19 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
 extern crate alloc;
+
+mod free_list;
+#[cfg(feature = "account-view")]
+mod lazy;
+
+pub use free_list::{FreeListAllocator, HeapStats};
+#[cfg(feature = "account-view")]
+pub use lazy::{InstructionContext, MaybeAccount};
+// need to re-export `AccountView` for entrypoint_account_view macro
+#[cfg(feature = "account-view")]
+pub use solana_account_view::AccountView as __AccountView;
+#[cfg(feature = "account-view")]
+use solana_account_view::{AccountView, RuntimeAccount};
 use {
     alloc::vec::Vec,
     solana_account_info::AccountInfo,
@@ -196,6 +209,89 @@ macro_rules! entrypoint_no_alloc {
     };
 }
 
+/// Declare the program entrypoint and set up global handlers, passing the
+/// input accounts as [`AccountView`]s.
+///
+/// This is similar to the [`entrypoint_no_alloc!`] macro, except that the
+/// accounts are not wrapped in `AccountInfo`s. Each [`AccountView`] is a
+/// pointer into the input buffer, and borrows of the account data are tracked
+/// by the borrow state stored in the buffer itself, so no `Rc<RefCell<_>>`
+/// needs to be created for any account. Duplicate accounts point to the same
+/// memory as the account they duplicate.
+///
+/// The first argument is the name of a function with this type signature:
+///
+/// ```ignore
+/// fn process_instruction(
+///     program_id: &Pubkey,           // Public key of the account the program was loaded into
+///     accounts: &mut [AccountView],  // All accounts required to process the instruction
+///     instruction_data: &[u8],       // Serialized instruction-specific data
+/// ) -> ProgramResult;
+/// ```
+///
+/// The optional second argument is the maximum number of input accounts,
+/// which defaults to 64. The accounts are written into an array of that
+/// length on the stack, and the program panics if more accounts are provided.
+///
+/// For more information about how the program entrypoint behaves and what it
+/// does, please see the documentation for [`entrypoint!`].
+///
+/// # Examples
+///
+/// ```no_run
+/// #[cfg(not(feature = "no-entrypoint"))]
+/// pub mod entrypoint {
+///
+///     use solana_account_view::AccountView;
+///     use solana_program_entrypoint::entrypoint_account_view;
+///     use solana_program_entrypoint::ProgramResult;
+///     use solana_msg::msg;
+///     use solana_pubkey::Pubkey;
+///
+///     entrypoint_account_view!(process_instruction, 16);
+///
+///     pub fn process_instruction(
+///         program_id: &Pubkey,
+///         accounts: &mut [AccountView],
+///         instruction_data: &[u8],
+///     ) -> ProgramResult {
+///         msg!("Hello world");
+///
+///         Ok(())
+///     }
+///
+/// }
+/// ```
+#[cfg(feature = "account-view")]
+#[macro_export]
+macro_rules! entrypoint_account_view {
+    ($process_instruction:ident) => {
+        $crate::entrypoint_account_view!($process_instruction, 64);
+    };
+    ($process_instruction:ident, $max_accounts:expr) => {
+        /// # Safety
+        #[no_mangle]
+        pub unsafe extern "C" fn entrypoint(input: *mut u8) -> u64 {
+            use core::mem::MaybeUninit;
+            let mut accounts =
+                [const { MaybeUninit::<$crate::__AccountView>::uninit() }; $max_accounts];
+            let (program_id, num_accounts, instruction_data) =
+                unsafe { $crate::deserialize_account_views(input, &mut accounts) };
+            // Use `slice_assume_init_mut` once it's stabilized
+            let accounts = unsafe {
+                &mut *(&mut accounts[..num_accounts] as *mut [MaybeUninit<$crate::__AccountView>]
+                    as *mut [$crate::__AccountView])
+            };
+            match $process_instruction(program_id, accounts, instruction_data) {
+                Ok(()) => $crate::SUCCESS,
+                Err(error) => error.into(),
+            }
+        }
+        $crate::custom_heap_default!();
+        $crate::custom_panic_default!();
+    };
+}
+
 /// Define the default global allocator.
 ///
 /// The default global allocator is enabled only if the calling crate has not
@@ -229,6 +325,59 @@ macro_rules! custom_heap_default {
     };
 }
 
+/// Define a [`FreeListAllocator`] as the global allocator.
+///
+/// Unlike the default [`BumpAllocator`], the free-list allocator reuses freed
+/// memory, so a program that allocates and drops values in a loop does not
+/// run out of heap. It is only defined for [BPF] targets.
+///
+/// [BPF]: https://solana.com/docs/programs/faq#berkeley-packet-filter-bpf
+///
+/// The optional argument is the length of the heap, which defaults to
+/// [`HEAP_LENGTH`]. A program that runs in transactions requesting a larger
+/// heap frame, with `ComputeBudgetInstruction::request_heap_frame` or the
+/// heap size of a v1 transaction config, can pass the requested length to
+/// make use of it.
+///
+/// # Cargo features
+///
+/// The [`entrypoint`] macro defines the default global allocator unless the
+/// calling crate enables its `custom-heap` feature, which it must do to use
+/// this macro.
+///
+/// # Examples
+///
+/// ```ignore
+/// solana_program_entrypoint::entrypoint!(process_instruction);
+/// solana_program_entrypoint::free_list_heap!(64 * 1024);
+/// ```
+///
+/// The allocator keeps its state on the heap, so the program can log its
+/// usage with a second instance over the same address range:
+///
+/// ```ignore
+/// let heap = unsafe {
+///     FreeListAllocator::with_fixed_address_range(HEAP_START_ADDRESS as usize, 64 * 1024)
+/// };
+/// unsafe { heap.stats() }.log();
+/// ```
+#[macro_export]
+macro_rules! free_list_heap {
+    () => {
+        $crate::free_list_heap!($crate::HEAP_LENGTH);
+    };
+    ($heap_length:expr) => {
+        #[cfg(target_os = "solana")]
+        #[global_allocator]
+        static A: $crate::FreeListAllocator = unsafe {
+            $crate::FreeListAllocator::with_fixed_address_range(
+                $crate::HEAP_START_ADDRESS as usize,
+                $heap_length,
+            )
+        };
+    };
+}
+
 /// Define the default global panic handler.
 ///
 /// This must be used if the [`entrypoint`] macro is not used, and no other
@@ -576,6 +725,88 @@ pub unsafe fn deserialize_into<'a>(
     (program_id, num_accounts, instruction_data)
 }
 
+#[cfg(feature = "account-view")]
+#[allow(clippy::arithmetic_side_effects)]
+#[inline(always)]
+unsafe fn deserialize_account_view(input: *mut u8, mut offset: usize) -> (AccountView, usize) {
+    // The duplicate marker of the account is the first field of the
+    // `RuntimeAccount`, where it is reused as the borrow state.
+    #[allow(clippy::cast_ptr_alignment)]
+    let account = input.add(offset) as *mut RuntimeAccount;
+    offset += size_of::<RuntimeAccount>();
+
+    // rent epoch is not deserialized, so skip it
+    offset += (*account).data_len as usize + MAX_PERMITTED_DATA_INCREASE + size_of::<u64>();
+    offset += (offset as *const u8).align_offset(BPF_ALIGN_OF_U128); // padding
+
+    (AccountView::new_unchecked(account), offset)
+}
+
+/// Deserialize the input arguments into [`AccountView`]s
+///
+/// Differs from `deserialize_into` by pointing each account view at its
+/// serialized `RuntimeAccount` instead of building an `AccountInfo`. The
+/// duplicate marker of a non-duplicate account is reused as the borrow state
+/// of its data, and a duplicate account shares the view of the account it
+/// duplicates.
+///
+/// Panics if the input slice is not large enough.
+///
+/// The integer arithmetic in this method is safe when called on a buffer that was
+/// serialized by runtime. Use with buffers serialized otherwise is unsupported and
+/// done at one's own risk.
+///
+/// # Safety
+#[cfg(feature = "account-view")]
+#[allow(clippy::arithmetic_side_effects)]
+pub unsafe fn deserialize_account_views<'a>(
+    input: *mut u8,
+    accounts: &mut [MaybeUninit<AccountView>],
+) -> (&'a Pubkey, usize, &'a [u8]) {
+    let mut offset: usize = 0;
+
+    // Number of accounts present
+
+    #[allow(clippy::cast_ptr_alignment)]
+    let num_accounts = *(input.add(offset) as *const u64) as usize;
+    offset += size_of::<u64>();
+
+    if num_accounts > accounts.len() {
+        panic!(
+            "{} accounts provided, but only {} are supported",
+            num_accounts,
+            accounts.len()
+        );
+    }
+
+    // Account Views
+
+    for i in 0..num_accounts {
+        let dup_info = *input.add(offset);
+        if dup_info == NON_DUP_MARKER {
+            let (account_view, new_offset) = deserialize_account_view(input, offset);
+            offset = new_offset;
+            accounts[i].write(account_view);
+        } else {
+            offset += size_of::<u64>(); // marker and padding
+
+            // Duplicate account, share the original view
+            accounts[i].write(accounts[dup_info as usize].assume_init_ref().clone());
+        }
+    }
+
+    // Instruction data
+
+    let (instruction_data, new_offset) = deserialize_instruction_data(input, offset);
+    offset = new_offset;
+
+    // Program Id
+
+    let program_id: &Pubkey = &*(input.add(offset) as *const Pubkey);
+
+    (program_id, num_accounts, instruction_data)
+}
+
 #[cfg(test)]
 mod test {
     use {super::*, std::alloc::GlobalAlloc};
@@ -653,4 +884,88 @@ mod test {
         );
         let _allocator = unsafe { BumpAllocator::new(unaligned) };
     }
+
+    #[cfg(feature = "account-view")]
+    pub(crate) const KEY: Pubkey = Pubkey::new_from_array([1; 32]);
+    #[cfg(feature = "account-view")]
+    pub(crate) const OTHER_KEY: Pubkey = Pubkey::new_from_array([2; 32]);
+    #[cfg(feature = "account-view")]
+    pub(crate) const OWNER: Pubkey = Pubkey::new_from_array([3; 32]);
+    #[cfg(feature = "account-view")]
+    pub(crate) const PROGRAM_ID: Pubkey = Pubkey::new_from_array([4; 32]);
+
+    /// Serializes two accounts, a duplicate of the first one and the
+    /// instruction data `[8, 9]` the same way the runtime does.
+    #[cfg(feature = "account-view")]
+    #[allow(clippy::arithmetic_side_effects)]
+    pub(crate) fn serialize_input() -> Vec<u64> {
+        let mut input = Vec::new();
+        input.extend_from_slice(&3u64.to_le_bytes());
+        for (key, is_signer, data) in [(&KEY, 1, &[5u8, 6, 7][..]), (&OTHER_KEY, 0, &[])] {
+            input.extend_from_slice(&[NON_DUP_MARKER, is_signer, 1, 0, 0, 0, 0, 0]);
+            input.extend_from_slice(key.as_ref());
+            input.extend_from_slice(OWNER.as_ref());
+            input.extend_from_slice(&42u64.to_le_bytes());
+            input.extend_from_slice(&(data.len() as u64).to_le_bytes());
+            input.extend_from_slice(data);
+            input.resize(input.len() + MAX_PERMITTED_DATA_INCREASE, 0);
+            input.resize(input.len().next_multiple_of(BPF_ALIGN_OF_U128), 0);
+            input.extend_from_slice(&u64::MAX.to_le_bytes());
+        }
+        input.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0]);
+        input.extend_from_slice(&2u64.to_le_bytes());
+        input.extend_from_slice(&[8, 9]);
+        input.extend_from_slice(PROGRAM_ID.as_ref());
+
+        // Copy the input into an aligned buffer.
+        let mut buffer = vec![0u64; input.len().div_ceil(size_of::<u64>())];
+        unsafe {
+            (buffer.as_mut_ptr() as *mut u8).copy_from_nonoverlapping(input.as_ptr(), input.len())
+        };
+        buffer
+    }
+
+    #[cfg(feature = "account-view")]
+    #[test]
+    fn test_deserialize_account_views() {
+        let mut buffer = serialize_input();
+        let buffer_ptr = buffer.as_mut_ptr() as *mut u8;
+
+        let mut accounts = [const { MaybeUninit::<AccountView>::uninit() }; 3];
+        let (deserialized_program_id, num_accounts, instruction_data) =
+            unsafe { deserialize_account_views(buffer_ptr, &mut accounts) };
+        assert_eq!(deserialized_program_id, &PROGRAM_ID);
+        assert_eq!(num_accounts, 3);
+        assert_eq!(instruction_data, [8, 9]);
+
+        let [mut first, second, mut duplicate] =
+            accounts.map(|account| unsafe { account.assume_init() });
+        assert_eq!(first.address(), &KEY);
+        assert_eq!(first.owner(), &OWNER);
+        assert!(first.is_signer());
+        assert!(first.is_writable());
+        assert!(!first.executable());
+        assert_eq!(first.lamports(), 42);
+        assert_eq!(&*first.try_borrow().unwrap(), [5, 6, 7]);
+        assert_eq!(second.address(), &OTHER_KEY);
+        assert!(!second.is_signer());
+        assert!(second.is_data_empty());
+        assert_eq!(duplicate, first);
+
+        // Borrows are shared with the duplicate.
+        let data = first.try_borrow_mut().unwrap();
+        assert!(duplicate.try_borrow().is_err());
+        drop(data);
+        duplicate.set_lamports(7);
+        assert_eq!(first.lamports(), 7);
+    }
+
+    #[cfg(feature = "account-view")]
+    #[test]
+    #[should_panic(expected = "2 accounts provided, but only 1 are supported")]
+    fn test_deserialize_account_views_too_many_accounts() {
+        let mut input = [2u64, 0];
+        let mut accounts = [const { MaybeUninit::<AccountView>::uninit() }; 1];
+        unsafe { deserialize_account_views(input.as_mut_ptr() as *mut u8, &mut accounts) };
+    }
 }
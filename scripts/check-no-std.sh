@@ -56,6 +56,7 @@ no_std_alloc_crates=(
   -p solana-message
   -p solana-serialize-utils
   -p solana-signer
+  -p solana-signer-store
   -p solana-short-vec
   -p solana-stake-history
   -p solana-transaction
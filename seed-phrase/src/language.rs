@@ -0,0 +1,222 @@
+//! BIP-39 word lists.
+//!
+//! English is always available. The other languages are enabled with the
+//! feature of the same name. Words are stored in Unicode NFKD form, as
+//! required by BIP-39.
+
+use {std::sync::OnceLock, unicode_normalization::UnicodeNormalization};
+
+/// The number of words in a BIP-39 word list.
+pub const WORD_LIST_LEN: usize = 2048;
+
+/// The language of a BIP-39 word list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Language {
+    English,
+    #[cfg(feature = "chinese-simplified")]
+    ChineseSimplified,
+    #[cfg(feature = "chinese-traditional")]
+    ChineseTraditional,
+    #[cfg(feature = "french")]
+    French,
+    #[cfg(feature = "italian")]
+    Italian,
+    #[cfg(feature = "japanese")]
+    Japanese,
+    #[cfg(feature = "korean")]
+    Korean,
+    #[cfg(feature = "spanish")]
+    Spanish,
+}
+
+struct WordList {
+    words: Vec<&'static str>,
+    /// The words with their indexes, sorted by word for lookups.
+    sorted: Vec<(&'static str, u16)>,
+}
+
+impl WordList {
+    fn new(source: &'static str) -> Self {
+        let words: Vec<_> = source.split_whitespace().collect();
+        assert_eq!(words.len(), WORD_LIST_LEN, "invalid word list length");
+        let mut sorted: Vec<_> = words
+            .iter()
+            .enumerate()
+            .map(|(index, word)| (*word, index as u16))
+            .collect();
+        sorted.sort_unstable();
+        Self { words, sorted }
+    }
+}
+
+impl Language {
+    /// All enabled languages.
+    pub const ALL: &'static [Language] = &[
+        Language::English,
+        #[cfg(feature = "chinese-simplified")]
+        Language::ChineseSimplified,
+        #[cfg(feature = "chinese-traditional")]
+        Language::ChineseTraditional,
+        #[cfg(feature = "french")]
+        Language::French,
+        #[cfg(feature = "italian")]
+        Language::Italian,
+        #[cfg(feature = "japanese")]
+        Language::Japanese,
+        #[cfg(feature = "korean")]
+        Language::Korean,
+        #[cfg(feature = "spanish")]
+        Language::Spanish,
+    ];
+
+    fn list(self) -> &'static WordList {
+        macro_rules! word_list {
+            ($file:literal) => {{
+                static LIST: OnceLock<WordList> = OnceLock::new();
+                LIST.get_or_init(|| WordList::new(include_str!(concat!("wordlists/", $file))))
+            }};
+        }
+
+        match self {
+            Language::English => word_list!("english.txt"),
+            #[cfg(feature = "chinese-simplified")]
+            Language::ChineseSimplified => word_list!("chinese_simplified.txt"),
+            #[cfg(feature = "chinese-traditional")]
+            Language::ChineseTraditional => word_list!("chinese_traditional.txt"),
+            #[cfg(feature = "french")]
+            Language::French => word_list!("french.txt"),
+            #[cfg(feature = "italian")]
+            Language::Italian => word_list!("italian.txt"),
+            #[cfg(feature = "japanese")]
+            Language::Japanese => word_list!("japanese.txt"),
+            #[cfg(feature = "korean")]
+            Language::Korean => word_list!("korean.txt"),
+            #[cfg(feature = "spanish")]
+            Language::Spanish => word_list!("spanish.txt"),
+        }
+    }
+
+    /// Returns the words of the list, in index order.
+    pub fn word_list(self) -> &'static [&'static str] {
+        &self.list().words
+    }
+
+    /// Returns the word at `index`, if it is in range.
+    pub fn word(self, index: u16) -> Option<&'static str> {
+        self.word_list().get(usize::from(index)).copied()
+    }
+
+    /// Returns the index of `word` in the list.
+    ///
+    /// The word is normalized to NFKD first, so precomposed input such as
+    /// `"élève"` matches the decomposed word list.
+    pub fn index_of(self, word: &str) -> Option<u16> {
+        let word: String = word.nfkd().collect();
+        let sorted = &self.list().sorted;
+        sorted
+            .binary_search_by(|(candidate, _)| (*candidate).cmp(word.as_str()))
+            .ok()
+            .map(|position| sorted[position].1)
+    }
+
+    /// Returns the words that start with `prefix`, in sorted order.
+    ///
+    /// This can be used to autocomplete words as they are typed. BIP-39 word
+    /// lists are designed so that, in most languages, the first four letters
+    /// identify a word. The prefix is normalized to NFKD like the words.
+    pub fn words_with_prefix(self, prefix: &str) -> Vec<&'static str> {
+        let prefix: String = prefix.nfkd().collect();
+        let sorted = &self.list().sorted;
+        let start = sorted.partition_point(|(word, _)| *word < prefix.as_str());
+        sorted[start..]
+            .iter()
+            .take_while(|(word, _)| word.starts_with(prefix.as_str()))
+            .map(|(word, _)| *word)
+            .collect()
+    }
+
+    /// Returns the separator placed between the words of a phrase.
+    pub fn separator(self) -> &'static str {
+        match self {
+            #[cfg(feature = "japanese")]
+            Language::Japanese => "\u{3000}",
+            _ => " ",
+        }
+    }
+
+    /// Returns the first enabled language whose word list contains every
+    /// word of `phrase`.
+    ///
+    /// Some words are in more than one list, so the language returned is not
+    /// necessarily the one the phrase was created in. Use
+    /// [`Mnemonic::from_phrase`](crate::Mnemonic::from_phrase) with each
+    /// language to find the one whose checksum matches.
+    pub fn detect(phrase: &str) -> Option<Language> {
+        Self::ALL.iter().copied().find(|language| {
+            let mut words = phrase.split_whitespace().peekable();
+            words.peek().is_some() && words.all(|word| language.index_of(word).is_some())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_lists() {
+        for language in Language::ALL {
+            let words = language.word_list();
+            assert_eq!(words.len(), WORD_LIST_LEN);
+            for (index, word) in words.iter().enumerate() {
+                assert_eq!(language.index_of(word), Some(index as u16));
+                assert_eq!(language.word(index as u16), Some(*word));
+            }
+            assert_eq!(language.word(WORD_LIST_LEN as u16), None);
+        }
+    }
+
+    #[test]
+    fn test_english_lookup() {
+        let english = Language::English;
+        assert_eq!(english.index_of("abandon"), Some(0));
+        assert_eq!(english.index_of("zoo"), Some(2047));
+        assert_eq!(english.index_of("solana"), None);
+        assert_eq!(english.index_of("Abandon"), None);
+    }
+
+    #[test]
+    fn test_words_with_prefix() {
+        let english = Language::English;
+        assert_eq!(english.words_with_prefix("zo"), vec!["zone", "zoo"]);
+        assert_eq!(english.words_with_prefix("abando"), vec!["abandon"]);
+        assert_eq!(english.words_with_prefix("abandonx"), Vec::<&str>::new());
+        assert_eq!(english.words_with_prefix("xyz"), Vec::<&str>::new());
+        assert_eq!(english.words_with_prefix("").len(), WORD_LIST_LEN);
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Language::detect("abandon zoo"), Some(Language::English));
+        assert_eq!(Language::detect("abandon solana"), None);
+        assert_eq!(Language::detect(""), None);
+    }
+
+    #[cfg(all(feature = "french", feature = "spanish"))]
+    #[test]
+    fn test_precomposed_lookup() {
+        // The word lists are decomposed, while typed input is usually
+        // precomposed.
+        let french = Language::French;
+        let eleve = french.index_of("e\u{301}le\u{300}ve").unwrap();
+        assert_eq!(french.index_of("\u{e9}l\u{e8}ve"), Some(eleve));
+        assert_eq!(
+            french.words_with_prefix("\u{e9}l\u{e8}"),
+            vec!["e\u{301}le\u{300}ve"]
+        );
+
+        let spanish = Language::Spanish;
+        let abaco = spanish.index_of("a\u{301}baco").unwrap();
+        assert_eq!(spanish.index_of("\u{e1}baco"), Some(abaco));
+    }
+}
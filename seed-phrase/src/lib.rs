@@ -2,6 +2,14 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 use hmac::Hmac;
 
+pub mod language;
+pub mod mnemonic;
+
+pub use {
+    language::Language,
+    mnemonic::{Mnemonic, MnemonicError},
+};
+
 pub fn generate_seed_from_seed_phrase_and_passphrase(
     seed_phrase: &str,
     passphrase: &str,
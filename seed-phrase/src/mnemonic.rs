@@ -0,0 +1,388 @@
+//! BIP-39 mnemonic phrases.
+//!
+//! A mnemonic encodes 128 to 256 bits of entropy, followed by a checksum of
+//! one bit per 32 bits of entropy, as a phrase of 12 to 24 words taken from a
+//! [`Language`] word list. Each word encodes 11 bits.
+
+use {
+    crate::{generate_seed_from_seed_phrase_and_passphrase, language::Language},
+    sha2::{Digest, Sha256},
+    std::fmt,
+    unicode_normalization::UnicodeNormalization,
+};
+
+/// The valid entropy lengths in bytes.
+pub const ENTROPY_LENGTHS: [usize; 5] = [16, 20, 24, 28, 32];
+
+/// The valid phrase lengths in words.
+pub const WORD_COUNTS: [usize; 5] = [12, 15, 18, 21, 24];
+
+/// Errors produced when creating or validating a mnemonic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MnemonicError {
+    /// The entropy is not 16, 20, 24, 28 or 32 bytes long.
+    InvalidEntropyLength(usize),
+    /// The phrase is not 12, 15, 18, 21 or 24 words long.
+    InvalidWordCount(usize),
+    /// The word is not in the word list.
+    UnknownWord(String),
+    /// The checksum of the phrase does not match its entropy.
+    InvalidChecksum,
+}
+
+impl std::error::Error for MnemonicError {}
+
+impl fmt::Display for MnemonicError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidEntropyLength(len) => write!(f, "invalid entropy length: {len} bytes"),
+            Self::InvalidWordCount(count) => write!(f, "invalid word count: {count}"),
+            Self::UnknownWord(word) => write!(f, "unknown word: {word}"),
+            Self::InvalidChecksum => f.write_str("invalid checksum"),
+        }
+    }
+}
+
+/// A BIP-39 mnemonic phrase with a valid checksum.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Mnemonic {
+    language: Language,
+    phrase: String,
+    entropy: Vec<u8>,
+}
+
+impl Mnemonic {
+    /// Creates the mnemonic for `entropy`, which must be 16 bytes for a 12
+    /// word phrase up to 32 bytes for a 24 word phrase.
+    pub fn from_entropy(entropy: &[u8], language: Language) -> Result<Self, MnemonicError> {
+        if !ENTROPY_LENGTHS.contains(&entropy.len()) {
+            return Err(MnemonicError::InvalidEntropyLength(entropy.len()));
+        }
+        let mut data = entropy.to_vec();
+        data.push(checksum(entropy));
+        let phrase = split_into_indexes(&data)
+            .into_iter()
+            .map(|index| language.word_list()[usize::from(index)])
+            .collect::<Vec<_>>()
+            .join(language.separator());
+        Ok(Self {
+            language,
+            phrase,
+            entropy: entropy.to_vec(),
+        })
+    }
+
+    /// Parses `phrase`, checking that every word is in the `language` word
+    /// list and that the checksum matches.
+    ///
+    /// Words may be separated by any whitespace, and are normalized to NFKD
+    /// before they are looked up, so precomposed input is accepted.
+    pub fn from_phrase(phrase: &str, language: Language) -> Result<Self, MnemonicError> {
+        let words: Vec<_> = phrase.split_whitespace().collect();
+        if !WORD_COUNTS.contains(&words.len()) {
+            return Err(MnemonicError::InvalidWordCount(words.len()));
+        }
+        let indexes = words
+            .iter()
+            .map(|word| {
+                language
+                    .index_of(word)
+                    .ok_or_else(|| MnemonicError::UnknownWord(word.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let data = join_indexes(&indexes);
+        // Every 3 words hold 32 bits of entropy and 1 bit of checksum, which
+        // is at the top of the last byte.
+        let checksum_bits = (words.len() / 3) as u32;
+        let checksum_mask = !u8::MAX.checked_shr(checksum_bits).unwrap_or(0);
+        let (checksum_byte, entropy) = data.split_last().expect("at least 12 words");
+        if checksum_byte & checksum_mask != checksum(entropy) & checksum_mask {
+            return Err(MnemonicError::InvalidChecksum);
+        }
+
+        let phrase = indexes
+            .iter()
+            .map(|index| language.word_list()[usize::from(*index)])
+            .collect::<Vec<_>>()
+            .join(language.separator());
+        Ok(Self {
+            language,
+            phrase,
+            entropy: entropy.to_vec(),
+        })
+    }
+
+    /// Checks that `phrase` is a valid mnemonic in `language`.
+    pub fn validate(phrase: &str, language: Language) -> Result<(), MnemonicError> {
+        Self::from_phrase(phrase, language).map(|_| ())
+    }
+
+    /// Returns the language of the phrase.
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    /// Returns the phrase, with words separated by the language separator.
+    pub fn phrase(&self) -> &str {
+        &self.phrase
+    }
+
+    /// Returns the entropy encoded by the phrase.
+    pub fn entropy(&self) -> &[u8] {
+        &self.entropy
+    }
+
+    /// Returns the number of words in the phrase.
+    pub fn word_count(&self) -> usize {
+        // Every 4 bytes of entropy take 3 words.
+        (self.entropy.len() / 4).saturating_mul(3)
+    }
+
+    /// Derives the 64 byte BIP-39 seed with the given passphrase.
+    ///
+    /// As BIP-39 requires, the phrase and the `mnemonic{passphrase}` salt are
+    /// normalized to NFKD, which also turns the ideographic space separating
+    /// Japanese words into a regular space.
+    pub fn to_seed(&self, passphrase: &str) -> Vec<u8> {
+        let phrase: String = self.phrase.nfkd().collect();
+        // "mnemonic" is unchanged by NFKD, so normalizing the passphrase
+        // normalizes the whole salt.
+        let passphrase: String = passphrase.nfkd().collect();
+        generate_seed_from_seed_phrase_and_passphrase(&phrase, &passphrase)
+    }
+}
+
+impl fmt::Debug for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Mnemonic")
+            .field("language", &self.language)
+            .field("word_count", &self.word_count())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Returns the first byte of the SHA-256 hash of `entropy`. Only the leading
+/// `entropy.len() / 4` bits are used as the checksum.
+fn checksum(entropy: &[u8]) -> u8 {
+    Sha256::digest(entropy)[0]
+}
+
+/// Splits `data` into 11-bit word indexes, dropping any trailing bits.
+#[allow(clippy::arithmetic_side_effects)]
+fn split_into_indexes(data: &[u8]) -> Vec<u16> {
+    let mut indexes = Vec::with_capacity(data.len() * 8 / 11);
+    let mut buffer = 0u32;
+    let mut bits = 0u32;
+    for byte in data {
+        buffer = (buffer << 8) | u32::from(*byte);
+        bits += 8;
+        if bits >= 11 {
+            bits -= 11;
+            indexes.push(((buffer >> bits) & 0x7ff) as u16);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    indexes
+}
+
+/// Joins 11-bit word indexes into bytes, padding the last byte with zeros.
+#[allow(clippy::arithmetic_side_effects)]
+fn join_indexes(indexes: &[u16]) -> Vec<u8> {
+    let mut data = Vec::with_capacity((indexes.len() * 11).div_ceil(8));
+    let mut buffer = 0u32;
+    let mut bits = 0u32;
+    for index in indexes {
+        buffer = (buffer << 11) | u32::from(*index);
+        bits += 11;
+        while bits >= 8 {
+            bits -= 8;
+            data.push((buffer >> bits) as u8);
+        }
+        buffer &= (1 << bits) - 1;
+    }
+    if bits > 0 {
+        data.push((buffer << (8 - bits)) as u8);
+    }
+    data
+}
+
+#[cfg(test)]
+#[allow(clippy::arithmetic_side_effects)]
+mod tests {
+    use super::*;
+
+    fn hex(data: &str) -> Vec<u8> {
+        (0..data.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&data[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    // Test vectors from https://github.com/trezor/python-mnemonic/blob/master/vectors.json
+    const VECTORS: &[(&str, &str, &str)] = &[
+        (
+            "00000000000000000000000000000000",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+             abandon about",
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+        ),
+        (
+            "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+            "legal winner thank year wave sausage worth useful legal winner thank yellow",
+            "2e8905819b8723fe2c1d161860e5ee1830318dbf49a83bd451cfb8440c28bd6fa457fe1296106559a3c80937a1c1069be3a3a5bd381ee6260e8d9739fce1f607",
+        ),
+        (
+            "80808080808080808080808080808080",
+            "letter advice cage absurd amount doctor acoustic avoid letter advice cage above",
+            "d71de856f81a8acc65e6fc851a38d4d7ec216fd0796d0a6827a3ad6ed5511a30fa280f12eb2e47ed2ac03b5c462a0358d18d69fe4f985ec81778c1b370b652a8",
+        ),
+        (
+            "ffffffffffffffffffffffffffffffff",
+            "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong",
+            "ac27495480225222079d7be181583751e86f571027b0497b5b5d11218e0a8a13332572917f0f8e5a589620c6f15b11c61dee327651a14c34e18231052e48c069",
+        ),
+        (
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+             abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+             abandon abandon abandon art",
+            "bda85446c68413707090a52022edd26a1c9462295029f2e60cd7c4f2bbd3097170af7a4d73245cafa9c3cca8d561a7c3de6f5d4a10be8ed2a5e608d68f92fcc8",
+        ),
+        (
+            "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo \
+             zoo zoo vote",
+            "dd48c104698c30cfe2b6142103248622fb7bb0ff692eebb00089b32d22484e1613912f0a5b694407be899ffd31ed3992c456cdf60f5d4564b8ba3f05a69890ad",
+        ),
+        (
+            "9e885d952ad362caeb4efe34a8e91bd2",
+            "ozone drill grab fiber curtain grace pudding thank cruise elder eight picnic",
+            "274ddc525802f7c828d8ef7ddbcdc5304e87ac3535913611fbbfa986d0c9e5476c91689f9c8a54fd55bd38606aa6a8595ad213d4c9c9f9aca3fb217069a41028",
+        ),
+    ];
+
+    #[test]
+    fn test_vectors() {
+        for (entropy, phrase, seed) in VECTORS {
+            let entropy = hex(entropy);
+            let mnemonic = Mnemonic::from_entropy(&entropy, Language::English).unwrap();
+            assert_eq!(mnemonic.phrase(), *phrase);
+            assert_eq!(mnemonic.word_count(), entropy.len() / 4 * 3);
+            assert_eq!(mnemonic.to_seed("TREZOR"), hex(seed));
+
+            let parsed = Mnemonic::from_phrase(phrase, Language::English).unwrap();
+            assert_eq!(parsed, mnemonic);
+            assert_eq!(parsed.entropy(), entropy);
+        }
+    }
+
+    // Test vector from https://github.com/bip32JP/bip32JP.github.io/blob/master/test_JP_BIP39.json
+    #[cfg(feature = "japanese")]
+    #[test]
+    fn test_japanese_vector() {
+        let phrase = "あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　\
+                      あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　\
+                      あいこくしん　あおぞら";
+        let passphrase = "㍍ガバヴァぱばぐゞちぢ十人十色";
+        let seed = hex(
+            "a262d6fb6122ecf45be09c50492b31f92e9beb7d9a845987a02cefda57a15f9c467a17872029a9e92299b5cbdf306e3a0ee620245cbd508959b6cb7ca637bd55",
+        );
+
+        let mnemonic = Mnemonic::from_phrase(phrase, Language::Japanese).unwrap();
+        assert_eq!(mnemonic.entropy(), [0; 16]);
+        assert_eq!(mnemonic.to_seed(passphrase), seed);
+        assert_eq!(
+            Mnemonic::from_entropy(&[0; 16], Language::Japanese)
+                .unwrap()
+                .to_seed(passphrase),
+            seed
+        );
+    }
+
+    #[cfg(feature = "french")]
+    #[test]
+    fn test_precomposed_phrase() {
+        let mnemonic = Mnemonic::from_entropy(&[0x80; 16], Language::French).unwrap();
+        let precomposed: String = mnemonic.phrase().nfc().collect();
+        assert_ne!(precomposed, mnemonic.phrase());
+
+        let parsed = Mnemonic::from_phrase(&precomposed, Language::French).unwrap();
+        assert_eq!(parsed, mnemonic);
+        assert_eq!(parsed.to_seed(""), mnemonic.to_seed(""));
+    }
+
+    #[test]
+    fn test_roundtrip_all_lengths() {
+        for len in ENTROPY_LENGTHS {
+            let entropy: Vec<u8> = (0..len as u8).map(|i| i.wrapping_mul(37)).collect();
+            for language in Language::ALL {
+                let mnemonic = Mnemonic::from_entropy(&entropy, *language).unwrap();
+                assert_eq!(mnemonic.word_count(), len / 4 * 3);
+                let parsed = Mnemonic::from_phrase(mnemonic.phrase(), *language).unwrap();
+                assert_eq!(parsed.entropy(), entropy);
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_entropy() {
+        for len in [0, 15, 17, 33] {
+            assert_eq!(
+                Mnemonic::from_entropy(&vec![0; len], Language::English),
+                Err(MnemonicError::InvalidEntropyLength(len))
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_phrase() {
+        assert_eq!(
+            Mnemonic::validate("abandon abandon about", Language::English),
+            Err(MnemonicError::InvalidWordCount(3))
+        );
+        assert_eq!(
+            Mnemonic::validate(
+                "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+                 abandon solana",
+                Language::English
+            ),
+            Err(MnemonicError::UnknownWord("solana".to_string()))
+        );
+        // A typo that swaps in another valid word.
+        assert_eq!(
+            Mnemonic::validate(
+                "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+                 abandon abandon",
+                Language::English
+            ),
+            Err(MnemonicError::InvalidChecksum)
+        );
+        assert_eq!(
+            Mnemonic::validate(
+                "legal winner thank year wave sausage worth useful legal winner yellow thank",
+                Language::English
+            ),
+            Err(MnemonicError::InvalidChecksum)
+        );
+    }
+
+    #[test]
+    fn test_whitespace() {
+        let mnemonic = Mnemonic::from_phrase(
+            "  legal winner\tthank year wave sausage worth useful legal winner thank\nyellow ",
+            Language::English,
+        )
+        .unwrap();
+        assert_eq!(
+            mnemonic.phrase(),
+            "legal winner thank year wave sausage worth useful legal winner thank yellow"
+        );
+    }
+
+    #[test]
+    fn test_debug_hides_phrase() {
+        let mnemonic = Mnemonic::from_entropy(&[0; 16], Language::English).unwrap();
+        assert!(!format!("{mnemonic:?}").contains("abandon"));
+    }
+}
@@ -2,7 +2,7 @@ use {
     bitvec::prelude::*,
     criterion::{criterion_group, criterion_main, BenchmarkId, Criterion},
     rand::{rngs::ThreadRng, Rng},
-    solana_signer_store::{decode, encode_base2, encode_base3, Decoded},
+    solana_signer_store::{decode, encode_base2, encode_base3, Decoded, EncodedSigners},
     std::iter,
 };
 
@@ -72,6 +72,10 @@ fn bench_base2(c: &mut Criterion) {
                 assert!(matches!(decoded, Decoded::Base2(_)));
             });
         });
+
+        group.bench_with_input(BenchmarkId::new("iter", size), size, |b, _| {
+            b.iter(|| EncodedSigners::new(&encoded, *size).unwrap().iter().count());
+        });
     }
     group.finish();
 }
@@ -96,6 +100,10 @@ fn bench_base3(c: &mut Criterion) {
             });
         });
 
+        group.bench_with_input(BenchmarkId::new("iter_uniform", size), size, |b, _| {
+            b.iter(|| EncodedSigners::new(&encoded, *size).unwrap().iter().count());
+        });
+
         // Benchmark for sparse data
         let (base_sparse, fallback_sparse) = create_test_data_base3_sparse(*size);
         let encoded_sparse = encode_base3(&base_sparse, &fallback_sparse).unwrap();
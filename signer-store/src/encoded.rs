@@ -0,0 +1,357 @@
+//! Allocation-free queries over an encoded signer bitmap.
+//!
+//! [`decode`](crate::decode) materializes the encoded vectors as `BitVec`s.
+//! When only the set indices are needed, [`EncodedSigners`] validates the
+//! encoding once and then answers [`iter`](EncodedSigners::iter),
+//! [`popcount`](EncodedSigners::popcount) and
+//! [`contains`](EncodedSigners::contains) directly from the byte slice.
+
+use {
+    crate::{DecodeError, Version, BASE3_SYMBOLS_PER_BYTE, HEADER_LEN},
+    num_traits::FromPrimitive,
+};
+
+// Number of distinct values of a full Base3 chunk byte (3^5).
+const BASE3_CHUNK_VALUES: usize = 243;
+
+// Number of `1` and `2` symbols in each Base3 chunk byte.
+const BASE3_ONES: [u8; BASE3_CHUNK_VALUES] = base3_symbol_counts(1);
+const BASE3_TWOS: [u8; BASE3_CHUNK_VALUES] = base3_symbol_counts(2);
+
+#[allow(clippy::arithmetic_side_effects)]
+const fn base3_symbol_counts(symbol: u8) -> [u8; BASE3_CHUNK_VALUES] {
+    let mut counts = [0; BASE3_CHUNK_VALUES];
+    let mut value = 0;
+    while value < BASE3_CHUNK_VALUES {
+        let mut remaining = value;
+        while remaining > 0 {
+            if remaining % 3 == symbol as usize {
+                counts[value] += 1;
+            }
+            remaining /= 3;
+        }
+        value += 1;
+    }
+    counts
+}
+
+/// The input vector a set index belongs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Vector {
+    /// The only vector of a Base2 encoding, or the first of a Base3 encoding.
+    Base,
+    /// The second vector of a Base3 encoding.
+    Fallback,
+}
+
+/// A validated view over an encoded signer bitmap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EncodedSigners<'a> {
+    base3: bool,
+    len: usize,
+    data: &'a [u8],
+}
+
+impl<'a> EncodedSigners<'a> {
+    /// Validates `bytes` with the same rules as [`decode`](crate::decode),
+    /// without allocating.
+    pub fn new(bytes: &'a [u8], max_len: usize) -> Result<Self, DecodeError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(DecodeError::InputTooShort);
+        }
+        let version = Version::from_u8(bytes[0]).ok_or(DecodeError::UnsupportedEncoding)?;
+        let len = usize::from(u16::from_le_bytes([bytes[1], bytes[2]]));
+        if len > max_len {
+            return Err(DecodeError::CorruptDataPayload);
+        }
+        let data = &bytes[HEADER_LEN..];
+
+        let base3 = version == Version::Base3;
+        let symbols_per_byte = if base3 { BASE3_SYMBOLS_PER_BYTE } else { 8 };
+        if data.len() != len.div_ceil(symbols_per_byte) {
+            return Err(DecodeError::CorruptDataPayload);
+        }
+        // Require the unused bits or symbols of the last byte to be zero, and
+        // every Base3 chunk to hold at most five symbols.
+        if base3
+            && data
+                .iter()
+                .any(|byte| usize::from(*byte) >= BASE3_CHUNK_VALUES)
+        {
+            return Err(DecodeError::CorruptDataPayload);
+        }
+        let remainder = if base3 {
+            len % BASE3_SYMBOLS_PER_BYTE
+        } else {
+            len % 8
+        };
+        if let (Some(&last_byte), true) = (data.last(), remainder != 0) {
+            let used_values = if base3 {
+                3usize.pow(remainder as u32)
+            } else {
+                1 << remainder
+            };
+            if usize::from(last_byte) >= used_values {
+                return Err(DecodeError::CorruptDataPayload);
+            }
+        }
+
+        Ok(Self { base3, len, data })
+    }
+
+    /// Returns the encoding version.
+    pub fn version(&self) -> Version {
+        if self.base3 {
+            Version::Base3
+        } else {
+            Version::Base2
+        }
+    }
+
+    /// Returns the number of encoded indices, set or not.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the encoding holds no indices.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of set indices across both vectors.
+    pub fn popcount(&self) -> usize {
+        self.popcount_base()
+            .saturating_add(self.popcount_fallback())
+    }
+
+    /// Returns the number of indices set in the base vector.
+    pub fn popcount_base(&self) -> usize {
+        if self.base3 {
+            self.data
+                .iter()
+                .map(|byte| usize::from(BASE3_ONES[usize::from(*byte)]))
+                .sum()
+        } else {
+            self.data
+                .iter()
+                .map(|byte| byte.count_ones() as usize)
+                .sum()
+        }
+    }
+
+    /// Returns the number of indices set in the fallback vector.
+    pub fn popcount_fallback(&self) -> usize {
+        if self.base3 {
+            self.data
+                .iter()
+                .map(|byte| usize::from(BASE3_TWOS[usize::from(*byte)]))
+                .sum()
+        } else {
+            0
+        }
+    }
+
+    /// Returns the vector that `index` is set in, or `None` if it is unset or
+    /// out of range.
+    pub fn contains(&self, index: usize) -> Option<Vector> {
+        if index >= self.len {
+            return None;
+        }
+        if self.base3 {
+            let byte = self.data[index / BASE3_SYMBOLS_PER_BYTE];
+            let shift = 3u8.pow((index % BASE3_SYMBOLS_PER_BYTE) as u32);
+            match byte.checked_div(shift).map(|symbols| symbols % 3) {
+                Some(1) => Some(Vector::Base),
+                Some(2) => Some(Vector::Fallback),
+                _ => None,
+            }
+        } else {
+            let byte = self.data[index / 8];
+            (byte >> (index % 8) & 1 == 1).then_some(Vector::Base)
+        }
+    }
+
+    /// Returns an iterator over the set indices in ascending order, together
+    /// with the vector each is set in.
+    pub fn iter(&self) -> Iter<'a> {
+        Iter {
+            base3: self.base3,
+            bytes: self.data.iter(),
+            offset: 0,
+            next_offset: 0,
+            current: 0,
+        }
+    }
+
+    /// Returns an iterator over the indices set in `vector`, in ascending
+    /// order.
+    pub fn indices(&self, vector: Vector) -> impl Iterator<Item = usize> + 'a {
+        self.iter()
+            .filter(move |(_, set)| *set == vector)
+            .map(|(index, _)| index)
+    }
+}
+
+impl<'a> IntoIterator for &EncodedSigners<'a> {
+    type Item = (usize, Vector);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the set indices of an [`EncodedSigners`].
+#[derive(Clone, Debug)]
+pub struct Iter<'a> {
+    base3: bool,
+    bytes: core::slice::Iter<'a, u8>,
+    // Index of the first symbol of the current byte.
+    offset: usize,
+    next_offset: usize,
+    // Undecoded symbols of the current byte. Base2 clears bits as they are
+    // yielded, Base3 shifts out one symbol at a time.
+    current: u8,
+}
+
+impl Iter<'_> {
+    // Loads the next byte of the payload.
+    fn advance(&mut self) -> Option<()> {
+        self.current = *self.bytes.next()?;
+        self.offset = self.next_offset;
+        self.next_offset = self.next_offset.saturating_add(if self.base3 {
+            BASE3_SYMBOLS_PER_BYTE
+        } else {
+            8
+        });
+        Some(())
+    }
+}
+
+impl Iterator for Iter<'_> {
+    type Item = (usize, Vector);
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.current != 0 {
+                if self.base3 {
+                    let symbol = self.current % 3;
+                    let index = self.offset;
+                    self.current /= 3;
+                    self.offset += 1;
+                    match symbol {
+                        1 => return Some((index, Vector::Base)),
+                        2 => return Some((index, Vector::Fallback)),
+                        _ => continue,
+                    }
+                }
+                let bit = self.current.trailing_zeros() as usize;
+                // Clear the lowest set bit.
+                self.current &= self.current - 1;
+                return Some((self.offset + bit, Vector::Base));
+            }
+            self.advance()?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{decode, encode_base2, encode_base3, Decoded},
+        alloc::vec::Vec,
+        bitvec::prelude::*,
+        rand::Rng,
+    };
+
+    fn random_vectors(len: usize, base3: bool) -> (BitVec<u8, Lsb0>, BitVec<u8, Lsb0>) {
+        let mut rng = rand::rng();
+        let mut base = BitVec::with_capacity(len);
+        let mut fallback = BitVec::with_capacity(len);
+        for _ in 0..len {
+            let symbol = rng.random_range(0..if base3 { 3 } else { 2 });
+            base.push(symbol == 1);
+            fallback.push(symbol == 2);
+        }
+        (base, fallback)
+    }
+
+    fn assert_matches_decode(bytes: &[u8], len: usize) {
+        let encoded = EncodedSigners::new(bytes, len).unwrap();
+        let (base, fallback) = match decode(bytes, len).unwrap() {
+            Decoded::Base2(base) => {
+                let fallback = BitVec::repeat(false, base.len());
+                (base, fallback)
+            }
+            Decoded::Base3(base, fallback) => (base, fallback),
+        };
+
+        assert_eq!(encoded.len(), len);
+        assert_eq!(
+            encoded.indices(Vector::Base).collect::<Vec<_>>(),
+            base.iter_ones().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            encoded.indices(Vector::Fallback).collect::<Vec<_>>(),
+            fallback.iter_ones().collect::<Vec<_>>()
+        );
+        assert_eq!(encoded.popcount_base(), base.count_ones());
+        assert_eq!(encoded.popcount_fallback(), fallback.count_ones());
+        assert_eq!(encoded.popcount(), encoded.iter().count());
+        for index in 0..len.saturating_add(8) {
+            let expected = if base.get(index).is_some_and(|bit| *bit) {
+                Some(Vector::Base)
+            } else if fallback.get(index).is_some_and(|bit| *bit) {
+                Some(Vector::Fallback)
+            } else {
+                None
+            };
+            assert_eq!(encoded.contains(index), expected, "index {index}");
+        }
+    }
+
+    #[test]
+    fn test_matches_decode() {
+        for len in [0, 1, 4, 5, 7, 8, 9, 23, 64, 100, 1000] {
+            let (base, _) = random_vectors(len, false);
+            assert_matches_decode(&encode_base2(&base).unwrap(), len);
+
+            let (base, fallback) = random_vectors(len, true);
+            assert_matches_decode(&encode_base3(&base, &fallback).unwrap(), len);
+        }
+
+        let all_set: BitVec<u8, Lsb0> = core::iter::repeat_n(true, 20).collect();
+        let encoded = encode_base2(&all_set).unwrap();
+        let encoded = EncodedSigners::new(&encoded, 20).unwrap();
+        assert_eq!(encoded.version(), Version::Base2);
+        assert!(encoded
+            .iter()
+            .eq((0..20).map(|index| (index, Vector::Base))));
+    }
+
+    #[test]
+    fn test_rejects_what_decode_rejects() {
+        let mut rejected = 0;
+        for version in [Version::Base2 as u8, Version::Base3 as u8, 2] {
+            for len in 0u8..=10 {
+                for data_len in 0..=2 {
+                    for byte in 0u8..=255 {
+                        let mut bytes = Vec::from([version, len, 0]);
+                        bytes.extend(core::iter::repeat_n(byte, data_len));
+                        let expected = decode(&bytes, 9).map(|_| ());
+                        let actual = EncodedSigners::new(&bytes, 9).map(|_| ());
+                        assert_eq!(actual, expected, "{bytes:?}");
+                        rejected += usize::from(actual.is_err());
+                    }
+                }
+            }
+        }
+        assert!(rejected > 0);
+        assert_eq!(
+            EncodedSigners::new(&[0, 0], 0),
+            Err(DecodeError::InputTooShort)
+        );
+    }
+}
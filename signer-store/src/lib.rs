@@ -35,9 +35,21 @@
 //!     original number of bits (i.e., the length of the input vectors; not the
 //!     length of the final vector).
 //! 3.  **Data Payload**: A sequence of bytes containing the packed base-3 digits.
+//!
+//! # Decoding
+//!
+//! [`decode`] returns the encoded vectors as `BitVec`s. [`EncodedSigners`]
+//! instead iterates and queries the set indices in place, without allocating.
+#![no_std]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+extern crate alloc;
+
+mod encoded;
+
+pub use crate::encoded::{EncodedSigners, Iter, Vector};
 use {
+    alloc::{vec, vec::Vec},
     bitvec::prelude::*,
     num_derive::{FromPrimitive, ToPrimitive},
     num_traits::FromPrimitive,
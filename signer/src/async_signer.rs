@@ -0,0 +1,295 @@
+//! Asynchronous signers.
+//!
+//! [`AsyncSigner`] is the asynchronous counterpart of [`Signer`], for keys
+//! held by services such as key management systems where signing is a
+//! network request. [`AsyncAdapter`] lets any [`Signer`] be used where an
+//! [`AsyncSigner`] is expected, and [`BlockingAdapter`] does the opposite by
+//! blocking the current thread on each request.
+//!
+//! [`AsyncSigners`] signs with a collection of signers, running the requests
+//! for one message concurrently.
+
+use {
+    crate::{Signer, SignerError},
+    alloc::{boxed::Box, sync::Arc, vec::Vec},
+    core::{future::Future, pin::Pin},
+    futures::future::try_join_all,
+    solana_pubkey::Pubkey,
+    solana_signature::Signature,
+};
+
+/// The future returned by [`AsyncSigner`] methods.
+pub type SignerFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, SignerError>> + Send + 'a>>;
+
+/// The `AsyncSigner` trait declares the operations of digital signature
+/// providers that sign asynchronously.
+pub trait AsyncSigner: Send + Sync {
+    /// Fallibly gets the implementor's public key
+    fn try_pubkey(&self) -> SignerFuture<'_, Pubkey>;
+    /// Fallibly produces an Ed25519 signature over the provided `message` bytes.
+    fn try_sign_message<'a>(&'a self, message: &'a [u8]) -> SignerFuture<'a, Signature>;
+    /// Whether the implementation requires user interaction to sign
+    fn is_interactive(&self) -> bool;
+}
+
+macro_rules! impl_async_signer_for_ptr {
+    ($($ptr:ty),*) => {
+        $(
+            impl<T: AsyncSigner + ?Sized> AsyncSigner for $ptr {
+                fn try_pubkey(&self) -> SignerFuture<'_, Pubkey> {
+                    (**self).try_pubkey()
+                }
+
+                fn try_sign_message<'a>(
+                    &'a self,
+                    message: &'a [u8],
+                ) -> SignerFuture<'a, Signature> {
+                    (**self).try_sign_message(message)
+                }
+
+                fn is_interactive(&self) -> bool {
+                    (**self).is_interactive()
+                }
+            }
+        )*
+    };
+}
+
+// This implements `AsyncSigner` for the ptr types `Box/Arc/&`
+impl_async_signer_for_ptr!(&T, Box<T>, Arc<T>);
+
+/// Uses a [`Signer`] as an [`AsyncSigner`].
+///
+/// The returned futures complete immediately.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsyncAdapter<S>(pub S);
+
+impl<S: Signer + Send + Sync> AsyncSigner for AsyncAdapter<S> {
+    fn try_pubkey(&self) -> SignerFuture<'_, Pubkey> {
+        Box::pin(async move { self.0.try_pubkey() })
+    }
+
+    fn try_sign_message<'a>(&'a self, message: &'a [u8]) -> SignerFuture<'a, Signature> {
+        Box::pin(async move { self.0.try_sign_message(message) })
+    }
+
+    fn is_interactive(&self) -> bool {
+        self.0.is_interactive()
+    }
+}
+
+/// Uses an [`AsyncSigner`] as a [`Signer`], blocking the current thread
+/// until each request completes.
+///
+/// This must not be used from within an asynchronous runtime that does not
+/// allow blocking.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockingAdapter<S>(pub S);
+
+impl<S: AsyncSigner> Signer for BlockingAdapter<S> {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        futures::executor::block_on(self.0.try_pubkey())
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        futures::executor::block_on(self.0.try_sign_message(message))
+    }
+
+    fn is_interactive(&self) -> bool {
+        self.0.is_interactive()
+    }
+}
+
+/// Convenience trait for working with mixed collections of `AsyncSigner`s
+pub trait AsyncSigners: Sync {
+    fn try_pubkeys(&self) -> SignerFuture<'_, Vec<Pubkey>>;
+    /// Signs `message` with every signer, running the requests concurrently.
+    fn try_sign_message<'a>(&'a self, message: &'a [u8]) -> SignerFuture<'a, Vec<Signature>>;
+    fn is_interactive(&self) -> bool;
+}
+
+/// Any `T` where `T` impls `IntoIterator` yielding `AsyncSigner`s implements
+/// `AsyncSigners`.
+///
+/// This includes `[&dyn AsyncSigner]`, `[Box<dyn AsyncSigner>]`,
+/// `Vec<AsyncAdapter<Keypair>>`, etc.
+impl<T: ?Sized + Sync, S: AsyncSigner + ?Sized> AsyncSigners for T
+where
+    for<'a> &'a T: IntoIterator<Item = &'a S>,
+{
+    fn try_pubkeys(&self) -> SignerFuture<'_, Vec<Pubkey>> {
+        Box::pin(try_join_all(
+            self.into_iter().map(|signer| signer.try_pubkey()),
+        ))
+    }
+
+    fn try_sign_message<'a>(&'a self, message: &'a [u8]) -> SignerFuture<'a, Vec<Signature>> {
+        Box::pin(try_join_all(
+            self.into_iter()
+                .map(|signer| signer.try_sign_message(message)),
+        ))
+    }
+
+    fn is_interactive(&self) -> bool {
+        self.into_iter().any(|signer| signer.is_interactive())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{null_signer::NullSigner, signers::Signers},
+        alloc::vec,
+        core::{
+            sync::atomic::{AtomicUsize, Ordering},
+            task::{Context, Poll},
+        },
+        futures::executor::block_on,
+    };
+
+    /// Completes after every signer of the test has started signing, so the
+    /// signatures only complete if the requests run concurrently.
+    struct BarrierSigner<'a> {
+        pubkey: Pubkey,
+        started: &'a AtomicUsize,
+        signers: usize,
+    }
+
+    struct Barrier<'a> {
+        started: &'a AtomicUsize,
+        signers: usize,
+        polled: bool,
+    }
+
+    impl Future for Barrier<'_> {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if !self.polled {
+                self.polled = true;
+                self.started.fetch_add(1, Ordering::SeqCst);
+            }
+            if self.started.load(Ordering::SeqCst) == self.signers {
+                Poll::Ready(())
+            } else {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    impl AsyncSigner for BarrierSigner<'_> {
+        fn try_pubkey(&self) -> SignerFuture<'_, Pubkey> {
+            Box::pin(async move { Ok(self.pubkey) })
+        }
+
+        fn try_sign_message<'a>(&'a self, message: &'a [u8]) -> SignerFuture<'a, Signature> {
+            Box::pin(async move {
+                Barrier {
+                    started: self.started,
+                    signers: self.signers,
+                    polled: false,
+                }
+                .await;
+                let mut signature = [0u8; 64];
+                signature[..32].copy_from_slice(self.pubkey.as_ref());
+                signature[32] = message.len() as u8;
+                Ok(Signature::from(signature))
+            })
+        }
+
+        fn is_interactive(&self) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn test_concurrent_signing() {
+        let started = AtomicUsize::new(0);
+        let signers = (1..=3u8)
+            .map(|i| BarrierSigner {
+                pubkey: Pubkey::from([i; 32]),
+                started: &started,
+                signers: 3,
+            })
+            .collect::<Vec<_>>();
+
+        let signatures = block_on(AsyncSigners::try_sign_message(&signers, b"message")).unwrap();
+        assert_eq!(signatures.len(), 3);
+        for (signer, signature) in signers.iter().zip(&signatures) {
+            assert_eq!(&signature.as_ref()[..32], signer.pubkey.as_ref());
+        }
+        assert_eq!(
+            block_on(AsyncSigners::try_pubkeys(&signers)).unwrap(),
+            vec![
+                Pubkey::from([1; 32]),
+                Pubkey::from([2; 32]),
+                Pubkey::from([3; 32])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_adapters() {
+        let null_signer = NullSigner::new(&Pubkey::from([1; 32]));
+        let started = AtomicUsize::new(0);
+        let barrier_signer = BarrierSigner {
+            pubkey: Pubkey::from([2; 32]),
+            started: &started,
+            signers: 1,
+        };
+
+        let signers: [&dyn AsyncSigner; 2] = [&AsyncAdapter(null_signer.clone()), &barrier_signer];
+        assert!(!AsyncSigners::is_interactive(&signers));
+        let signatures = block_on(AsyncSigners::try_sign_message(&signers, b"message")).unwrap();
+        assert_eq!(signatures[0], Signature::default());
+
+        // A fresh barrier, as the previous one has already been released.
+        let started = AtomicUsize::new(0);
+        let blocking = BlockingAdapter(BarrierSigner {
+            pubkey: Pubkey::from([2; 32]),
+            started: &started,
+            signers: 1,
+        });
+        assert_eq!(Signer::pubkey(&blocking), Pubkey::from([2; 32]));
+        let sync_signers: [&dyn Signer; 2] = [&null_signer, &blocking];
+        assert_eq!(
+            Signers::try_sign_message(&sync_signers, b"message").unwrap(),
+            signatures
+        );
+    }
+
+    #[test]
+    fn test_error() {
+        struct FailingSigner;
+
+        impl AsyncSigner for FailingSigner {
+            fn try_pubkey(&self) -> SignerFuture<'_, Pubkey> {
+                Box::pin(async { Err(SignerError::NoDeviceFound) })
+            }
+
+            fn try_sign_message<'a>(&'a self, _message: &'a [u8]) -> SignerFuture<'a, Signature> {
+                Box::pin(async { Err(SignerError::NoDeviceFound) })
+            }
+
+            fn is_interactive(&self) -> bool {
+                true
+            }
+        }
+
+        let signers: Vec<Box<dyn AsyncSigner>> = vec![
+            Box::new(AsyncAdapter(NullSigner::new(&Pubkey::default()))),
+            Box::new(FailingSigner),
+        ];
+        assert!(AsyncSigners::is_interactive(&signers));
+        assert_eq!(
+            block_on(AsyncSigners::try_sign_message(&signers, b"message")),
+            Err(SignerError::NoDeviceFound)
+        );
+        assert_eq!(
+            Signer::try_pubkey(&BlockingAdapter(FailingSigner)),
+            Err(SignerError::NoDeviceFound)
+        );
+    }
+}
@@ -23,9 +23,16 @@ use {
     solana_transaction_error::TransactionError,
 };
 
+#[cfg(feature = "async")]
+pub mod async_signer;
 pub mod null_signer;
+#[cfg(feature = "remote")]
+pub mod remote;
 pub mod signers;
 
+#[cfg(feature = "async")]
+pub use async_signer::AsyncSigner;
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum PresignerError {
     VerificationFailure,
@@ -0,0 +1,631 @@
+//! Signers backed by remote devices such as hardware wallets.
+//!
+//! A [`RemoteSigner`] holds no key material. It sends the message to a device
+//! over a [`RemoteTransport`], the device shows it to the user for
+//! confirmation, and the signature is returned once the user approves. The
+//! key is selected on the device with a [`DerivationPath`].
+//!
+//! Requests and responses are split into fixed-size packets, each starting
+//! with a channel id, a tag byte and a sequence number. The first packet of a
+//! payload also carries the payload length. A request payload is an
+//! instruction byte followed by its data, and a response payload is the data
+//! followed by a big-endian status word.
+//!
+//! Devices are found through a [`RemoteDeviceManager`], which lists the
+//! connected devices and opens a transport to one of them.
+
+use {
+    crate::{Signer, SignerError},
+    alloc::{boxed::Box, format, string::String, vec::Vec},
+    core::{fmt, time::Duration},
+    solana_derivation_path::DerivationPath,
+    solana_pubkey::Pubkey,
+    solana_signature::Signature,
+    std::time::Instant,
+};
+
+/// Channel id used for all exchanges.
+pub const REMOTE_CHANNEL: u16 = 0x0101;
+/// Tag byte identifying a command packet.
+pub const REMOTE_TAG: u8 = 0x05;
+
+/// Instruction to get the public key for a derivation path.
+pub const INS_GET_PUBKEY: u8 = 0x05;
+/// Instruction to sign a message with the key for a derivation path.
+pub const INS_SIGN_MESSAGE: u8 = 0x06;
+
+/// Status word for a successful request.
+pub const STATUS_OK: u16 = 0x9000;
+/// Status word for a request rejected by the user.
+pub const STATUS_USER_REJECTED: u16 = 0x6985;
+
+/// Default time to wait for the user to confirm a request on the device.
+pub const DEFAULT_CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(120);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const HEADER_LEN: usize = 5;
+const FIRST_HEADER_LEN: usize = HEADER_LEN + 2;
+const STATUS_LEN: usize = 2;
+
+/// Packet-level connection to a remote device.
+pub trait RemoteTransport {
+    /// Returns the size of a single packet.
+    fn packet_size(&self) -> usize;
+    /// Writes a packet to the device.
+    fn write_packet(&self, packet: &[u8]) -> Result<(), SignerError>;
+    /// Reads the next packet from the device, waiting at most `timeout`.
+    /// Returns `None` if no packet arrived in time.
+    fn read_packet(&self, timeout: Duration) -> Result<Option<Vec<u8>>, SignerError>;
+}
+
+/// Identifies a connected remote device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteDeviceInfo {
+    pub manufacturer: String,
+    pub model: String,
+    pub serial: String,
+}
+
+/// Enumerates and opens remote devices.
+pub trait RemoteDeviceManager {
+    /// Lists the connected devices.
+    fn list_devices(&self) -> Result<Vec<RemoteDeviceInfo>, SignerError>;
+    /// Opens a transport to a device.
+    fn open_device(&self, info: &RemoteDeviceInfo)
+        -> Result<Box<dyn RemoteTransport>, SignerError>;
+}
+
+/// Splits `payload` into packets of `packet_size` bytes on `channel`.
+///
+/// The last packet is padded with zeros.
+pub fn encode_frames(
+    channel: u16,
+    payload: &[u8],
+    packet_size: usize,
+) -> Result<Vec<Vec<u8>>, SignerError> {
+    if packet_size <= FIRST_HEADER_LEN {
+        return Err(SignerError::InvalidInput(format!(
+            "packet size {packet_size} is too small"
+        )));
+    }
+    let payload_len = u16::try_from(payload.len())
+        .map_err(|_| SignerError::InvalidInput(String::from("payload is too large")))?;
+
+    let mut packets = Vec::new();
+    let mut remaining = payload;
+    let mut sequence = 0u16;
+    loop {
+        let mut packet = Vec::with_capacity(packet_size);
+        packet.extend_from_slice(&channel.to_be_bytes());
+        packet.push(REMOTE_TAG);
+        packet.extend_from_slice(&sequence.to_be_bytes());
+        if sequence == 0 {
+            packet.extend_from_slice(&payload_len.to_be_bytes());
+        }
+        let chunk_len = remaining
+            .len()
+            .min(packet_size.saturating_sub(packet.len()));
+        let (chunk, rest) = remaining.split_at(chunk_len);
+        packet.extend_from_slice(chunk);
+        packet.resize(packet_size, 0);
+        packets.push(packet);
+        remaining = rest;
+        if remaining.is_empty() {
+            return Ok(packets);
+        }
+        sequence = sequence
+            .checked_add(1)
+            .ok_or_else(|| SignerError::InvalidInput(String::from("payload is too large")))?;
+    }
+}
+
+/// Reassembles a payload from the packets produced by [`encode_frames`].
+#[derive(Debug, Clone)]
+pub struct FrameDecoder {
+    channel: u16,
+    sequence: u16,
+    payload_len: usize,
+    payload: Vec<u8>,
+}
+
+impl FrameDecoder {
+    pub fn new(channel: u16) -> Self {
+        Self {
+            channel,
+            sequence: 0,
+            payload_len: 0,
+            payload: Vec::new(),
+        }
+    }
+
+    /// Adds a packet, returning the payload once all of its packets have been
+    /// received.
+    pub fn push(&mut self, packet: &[u8]) -> Result<Option<Vec<u8>>, SignerError> {
+        let header_len = if self.sequence == 0 {
+            FIRST_HEADER_LEN
+        } else {
+            HEADER_LEN
+        };
+        if packet.len() <= header_len {
+            return Err(protocol_error("packet is too short"));
+        }
+        if packet[0..2] != self.channel.to_be_bytes() || packet[2] != REMOTE_TAG {
+            return Err(protocol_error("unexpected packet channel or tag"));
+        }
+        if packet[3..5] != self.sequence.to_be_bytes() {
+            return Err(protocol_error("unexpected packet sequence number"));
+        }
+        if self.sequence == 0 {
+            self.payload_len = usize::from(u16::from_be_bytes([packet[5], packet[6]]));
+            self.payload = Vec::with_capacity(self.payload_len);
+        }
+        let missing = self.payload_len.saturating_sub(self.payload.len());
+        let data = &packet[header_len..];
+        self.payload
+            .extend_from_slice(&data[..missing.min(data.len())]);
+
+        if self.payload.len() == self.payload_len {
+            self.sequence = 0;
+            Ok(Some(core::mem::take(&mut self.payload)))
+        } else {
+            self.sequence = self
+                .sequence
+                .checked_add(1)
+                .ok_or_else(|| protocol_error("too many packets"))?;
+            Ok(None)
+        }
+    }
+}
+
+/// Serializes a derivation path as its length followed by each big-endian
+/// child index.
+///
+/// The length is a single byte, so paths deeper than 255 levels are rejected.
+pub fn serialize_derivation_path(derivation_path: &DerivationPath) -> Result<Vec<u8>, SignerError> {
+    let path = derivation_path.path();
+    let depth = u8::try_from(path.len()).map_err(|_| {
+        SignerError::InvalidInput(format!(
+            "derivation path is {} levels deep, at most 255 are supported",
+            path.len()
+        ))
+    })?;
+    let mut data = Vec::with_capacity(path.len().saturating_mul(4).saturating_add(1));
+    data.push(depth);
+    for index in path {
+        data.extend_from_slice(&index.to_bits().to_be_bytes());
+    }
+    Ok(data)
+}
+
+fn protocol_error(message: &str) -> SignerError {
+    SignerError::Protocol(String::from(message))
+}
+
+/// A [`Signer`] whose key is held on a remote device.
+pub struct RemoteSigner {
+    transport: Box<dyn RemoteTransport>,
+    derivation_path: DerivationPath,
+    pubkey: Pubkey,
+    confirmation_timeout: Duration,
+}
+
+impl RemoteSigner {
+    /// Connects to the key at `derivation_path` on the device behind
+    /// `transport`.
+    pub fn new(
+        transport: Box<dyn RemoteTransport>,
+        derivation_path: DerivationPath,
+    ) -> Result<Self, SignerError> {
+        let mut signer = Self {
+            transport,
+            derivation_path,
+            pubkey: Pubkey::default(),
+            confirmation_timeout: DEFAULT_CONFIRMATION_TIMEOUT,
+        };
+        let data = signer.exchange(
+            INS_GET_PUBKEY,
+            &serialize_derivation_path(&signer.derivation_path)?,
+        )?;
+        let pubkey = <[u8; 32]>::try_from(data.as_slice())
+            .map_err(|_| protocol_error("invalid public key length"))?;
+        signer.pubkey = Pubkey::from(pubkey);
+        Ok(signer)
+    }
+
+    /// Connects to the key at `derivation_path` on a device found by
+    /// `manager`, either the one with the given `serial` or the first device
+    /// found.
+    pub fn open(
+        manager: &dyn RemoteDeviceManager,
+        serial: Option<&str>,
+        derivation_path: DerivationPath,
+    ) -> Result<Self, SignerError> {
+        let info = manager
+            .list_devices()?
+            .into_iter()
+            .find(|info| serial.is_none_or(|serial| info.serial == serial))
+            .ok_or(SignerError::NoDeviceFound)?;
+        Self::new(manager.open_device(&info)?, derivation_path)
+    }
+
+    /// Sets how long to wait for the user to confirm a request.
+    #[must_use]
+    pub fn with_confirmation_timeout(mut self, confirmation_timeout: Duration) -> Self {
+        self.confirmation_timeout = confirmation_timeout;
+        self
+    }
+
+    /// Returns the derivation path of the key.
+    pub fn derivation_path(&self) -> &DerivationPath {
+        &self.derivation_path
+    }
+
+    fn exchange(&self, instruction: u8, data: &[u8]) -> Result<Vec<u8>, SignerError> {
+        let mut request = Vec::with_capacity(data.len().saturating_add(1));
+        request.push(instruction);
+        request.extend_from_slice(data);
+        for packet in encode_frames(REMOTE_CHANNEL, &request, self.transport.packet_size())? {
+            self.transport.write_packet(&packet)?;
+        }
+
+        let start = Instant::now();
+        let mut decoder = FrameDecoder::new(REMOTE_CHANNEL);
+        let mut response = loop {
+            let remaining = self.confirmation_timeout.saturating_sub(start.elapsed());
+            if remaining.is_zero() {
+                return Err(SignerError::Connection(String::from(
+                    "timed out waiting for the device",
+                )));
+            }
+            if let Some(packet) = self.transport.read_packet(remaining.min(POLL_INTERVAL))? {
+                if let Some(response) = decoder.push(&packet)? {
+                    break response;
+                }
+            }
+        };
+
+        let status_offset = response
+            .len()
+            .checked_sub(STATUS_LEN)
+            .ok_or_else(|| protocol_error("response is missing the status word"))?;
+        let status = response.split_off(status_offset);
+        let status = u16::from_be_bytes([status[0], status[1]]);
+        match status {
+            STATUS_OK => Ok(response),
+            STATUS_USER_REJECTED => Err(SignerError::UserCancel(String::from(
+                "request rejected on the device",
+            ))),
+            status => Err(SignerError::Protocol(format!(
+                "device returned status {status:#06x}"
+            ))),
+        }
+    }
+}
+
+impl fmt::Debug for RemoteSigner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RemoteSigner")
+            .field("derivation_path", &self.derivation_path)
+            .field("pubkey", &self.pubkey)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.pubkey)
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        let mut data = serialize_derivation_path(&self.derivation_path)?;
+        data.extend_from_slice(message);
+        let signature = self.exchange(INS_SIGN_MESSAGE, &data)?;
+        let signature = <[u8; 64]>::try_from(signature.as_slice())
+            .map_err(|_| protocol_error("invalid signature length"))?;
+        Ok(Signature::from(signature))
+    }
+
+    fn is_interactive(&self) -> bool {
+        true
+    }
+}
+
+impl<T> PartialEq<T> for RemoteSigner
+where
+    T: Signer,
+{
+    fn eq(&self, other: &T) -> bool {
+        self.pubkey == other.pubkey()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::arithmetic_side_effects)]
+mod tests {
+    use {
+        super::*,
+        crate::{null_signer::NullSigner, signers::Signers},
+        alloc::{collections::VecDeque, string::ToString, vec},
+        core::cell::{Cell, RefCell},
+    };
+
+    /// A device that answers requests in-process, deriving keys and
+    /// signatures deterministically from the request.
+    struct LoopbackDevice {
+        packet_size: usize,
+        decoder: RefCell<FrameDecoder>,
+        responses: RefCell<VecDeque<Vec<u8>>>,
+        confirmation_polls: usize,
+        pending_polls: Cell<usize>,
+        reject: bool,
+        requests: RefCell<Vec<Vec<u8>>>,
+    }
+
+    impl LoopbackDevice {
+        fn new(packet_size: usize) -> Self {
+            Self {
+                packet_size,
+                decoder: RefCell::new(FrameDecoder::new(REMOTE_CHANNEL)),
+                responses: RefCell::new(VecDeque::new()),
+                confirmation_polls: 0,
+                pending_polls: Cell::new(0),
+                reject: false,
+                requests: RefCell::new(Vec::new()),
+            }
+        }
+
+        fn pubkey(path: &[u8]) -> [u8; 32] {
+            let mut pubkey = [0u8; 32];
+            for (i, byte) in path.iter().enumerate() {
+                pubkey[i % 32] ^= *byte;
+            }
+            pubkey[31] = 0xaa;
+            pubkey
+        }
+
+        fn handle(&self, request: &[u8]) -> Vec<u8> {
+            let path_len = 1 + 4 * usize::from(request[1]);
+            let path = &request[1..1 + path_len];
+            let mut response = match request[0] {
+                INS_GET_PUBKEY => Self::pubkey(path).to_vec(),
+                INS_SIGN_MESSAGE => {
+                    self.pending_polls.set(self.confirmation_polls);
+                    if self.reject {
+                        return STATUS_USER_REJECTED.to_be_bytes().to_vec();
+                    }
+                    let message = &request[1 + path_len..];
+                    let mut signature = [0u8; 64];
+                    signature[..32].copy_from_slice(&Self::pubkey(path));
+                    for (i, byte) in message.iter().enumerate() {
+                        signature[32 + i % 32] ^= *byte;
+                    }
+                    signature.to_vec()
+                }
+                _ => return 0x6d00u16.to_be_bytes().to_vec(),
+            };
+            response.extend_from_slice(&STATUS_OK.to_be_bytes());
+            response
+        }
+    }
+
+    impl RemoteTransport for LoopbackDevice {
+        fn packet_size(&self) -> usize {
+            self.packet_size
+        }
+
+        fn write_packet(&self, packet: &[u8]) -> Result<(), SignerError> {
+            assert_eq!(packet.len(), self.packet_size);
+            if let Some(request) = self.decoder.borrow_mut().push(packet)? {
+                let response = self.handle(&request);
+                self.requests.borrow_mut().push(request);
+                let frames = encode_frames(REMOTE_CHANNEL, &response, self.packet_size)?;
+                self.responses.borrow_mut().extend(frames);
+            }
+            Ok(())
+        }
+
+        fn read_packet(&self, timeout: Duration) -> Result<Option<Vec<u8>>, SignerError> {
+            // Simulate the user taking a while to confirm on the device.
+            if self.pending_polls.get() > 0 {
+                self.pending_polls.set(self.pending_polls.get() - 1);
+                return Ok(None);
+            }
+            let packet = self.responses.borrow_mut().pop_front();
+            if packet.is_none() {
+                std::thread::sleep(timeout);
+            }
+            Ok(packet)
+        }
+    }
+
+    impl RemoteTransport for &'static LoopbackDevice {
+        fn packet_size(&self) -> usize {
+            (*self).packet_size()
+        }
+
+        fn write_packet(&self, packet: &[u8]) -> Result<(), SignerError> {
+            (*self).write_packet(packet)
+        }
+
+        fn read_packet(&self, timeout: Duration) -> Result<Option<Vec<u8>>, SignerError> {
+            (*self).read_packet(timeout)
+        }
+    }
+
+    struct LoopbackManager {
+        devices: Vec<RemoteDeviceInfo>,
+    }
+
+    impl RemoteDeviceManager for LoopbackManager {
+        fn list_devices(&self) -> Result<Vec<RemoteDeviceInfo>, SignerError> {
+            Ok(self.devices.clone())
+        }
+
+        fn open_device(
+            &self,
+            info: &RemoteDeviceInfo,
+        ) -> Result<Box<dyn RemoteTransport>, SignerError> {
+            assert!(self.devices.contains(info));
+            Ok(Box::new(LoopbackDevice::new(64)))
+        }
+    }
+
+    fn device_info(serial: &str) -> RemoteDeviceInfo {
+        RemoteDeviceInfo {
+            manufacturer: "Loopback".to_string(),
+            model: "Mock".to_string(),
+            serial: serial.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_frames_roundtrip() {
+        for payload_len in [0, 1, 57, 58, 59, 300, 1232] {
+            let payload = (0..payload_len).map(|i| i as u8).collect::<Vec<_>>();
+            let packets = encode_frames(REMOTE_CHANNEL, &payload, 64).unwrap();
+            assert!(packets.iter().all(|packet| packet.len() == 64));
+            let mut decoder = FrameDecoder::new(REMOTE_CHANNEL);
+            let (last, rest) = packets.split_last().unwrap();
+            for packet in rest {
+                assert_eq!(decoder.push(packet).unwrap(), None);
+            }
+            assert_eq!(decoder.push(last).unwrap(), Some(payload));
+        }
+    }
+
+    #[test]
+    fn test_frames_invalid() {
+        assert!(matches!(
+            encode_frames(REMOTE_CHANNEL, &[1], FIRST_HEADER_LEN),
+            Err(SignerError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            encode_frames(REMOTE_CHANNEL, &vec![0; 70_000], 64),
+            Err(SignerError::InvalidInput(_))
+        ));
+
+        let packets = encode_frames(REMOTE_CHANNEL, &[0; 100], 64).unwrap();
+        let mut decoder = FrameDecoder::new(0x0202);
+        assert!(matches!(
+            decoder.push(&packets[0]),
+            Err(SignerError::Protocol(_))
+        ));
+        let mut decoder = FrameDecoder::new(REMOTE_CHANNEL);
+        assert!(matches!(
+            decoder.push(&packets[1]),
+            Err(SignerError::Protocol(_))
+        ));
+    }
+
+    #[test]
+    fn test_derivation_path_selects_key() {
+        let device: &'static LoopbackDevice = Box::leak(Box::new(LoopbackDevice::new(64)));
+        let first =
+            RemoteSigner::new(Box::new(device), DerivationPath::new_bip44(Some(0), None)).unwrap();
+        let second =
+            RemoteSigner::new(Box::new(device), DerivationPath::new_bip44(Some(1), None)).unwrap();
+        assert_ne!(first.pubkey(), second.pubkey());
+        assert_eq!(
+            first.derivation_path(),
+            &DerivationPath::new_bip44(Some(0), None)
+        );
+
+        let requests = device.requests.borrow();
+        assert_eq!(requests[0][0], INS_GET_PUBKEY);
+        assert_eq!(
+            requests[0][1..],
+            serialize_derivation_path(&DerivationPath::new_bip44(Some(0), None)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_serialize_derivation_path_depth() {
+        let path = |depth: usize| {
+            DerivationPath::from_absolute_path_str(&format!("m{}", "/0".repeat(depth))).unwrap()
+        };
+        let data = serialize_derivation_path(&path(255)).unwrap();
+        assert_eq!(data.len(), 1 + 255 * 4);
+        assert_eq!(data[0], 255);
+
+        assert!(matches!(
+            serialize_derivation_path(&path(256)),
+            Err(SignerError::InvalidInput(_))
+        ));
+        // Connecting fails instead of sending a corrupt request.
+        let device = LoopbackDevice::new(64);
+        assert!(matches!(
+            RemoteSigner::new(Box::new(device), path(256)),
+            Err(SignerError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_sign_after_confirmation() {
+        let mut device = LoopbackDevice::new(64);
+        device.confirmation_polls = 5;
+        let signer = RemoteSigner::new(Box::new(device), DerivationPath::default()).unwrap();
+        assert!(signer.is_interactive());
+
+        // Long enough to span several packets.
+        let message = vec![7u8; 500];
+        let signature = signer.try_sign_message(&message).unwrap();
+        assert_eq!(&signature.as_ref()[..32], signer.pubkey().as_ref());
+    }
+
+    #[test]
+    fn test_user_rejection_and_timeout() {
+        let mut device = LoopbackDevice::new(64);
+        device.reject = true;
+        let signer = RemoteSigner::new(Box::new(device), DerivationPath::default()).unwrap();
+        assert!(matches!(
+            signer.try_sign_message(b"hello"),
+            Err(SignerError::UserCancel(_))
+        ));
+        assert_eq!(signer.sign_message(b"hello"), Signature::default());
+
+        let mut device = LoopbackDevice::new(64);
+        device.confirmation_polls = usize::MAX;
+        let signer = RemoteSigner::new(Box::new(device), DerivationPath::default())
+            .unwrap()
+            .with_confirmation_timeout(Duration::from_millis(10));
+        assert!(matches!(
+            signer.try_sign_message(b"hello"),
+            Err(SignerError::Connection(_))
+        ));
+    }
+
+    #[test]
+    fn test_open_device() {
+        let manager = LoopbackManager {
+            devices: vec![device_info("first"), device_info("second")],
+        };
+        assert!(RemoteSigner::open(&manager, None, DerivationPath::default()).is_ok());
+        assert!(RemoteSigner::open(&manager, Some("second"), DerivationPath::default()).is_ok());
+        assert_eq!(
+            RemoteSigner::open(&manager, Some("third"), DerivationPath::default()).unwrap_err(),
+            SignerError::NoDeviceFound
+        );
+        let manager = LoopbackManager { devices: vec![] };
+        assert_eq!(
+            RemoteSigner::open(&manager, None, DerivationPath::default()).unwrap_err(),
+            SignerError::NoDeviceFound
+        );
+    }
+
+    #[test]
+    fn test_signers() {
+        let remote =
+            RemoteSigner::new(Box::new(LoopbackDevice::new(64)), DerivationPath::default())
+                .unwrap();
+        let null_signer = NullSigner::new(&Pubkey::from([1; 32]));
+        let signers: [&dyn Signer; 2] = [&remote, &null_signer];
+        assert!(Signers::is_interactive(&signers));
+        assert_eq!(
+            Signers::try_pubkeys(&signers).unwrap(),
+            vec![remote.pubkey(), null_signer.pubkey()]
+        );
+        let signatures = Signers::try_sign_message(&signers, b"message").unwrap();
+        assert_eq!(signatures[0], remote.try_sign_message(b"message").unwrap());
+        assert_eq!(signatures[1], Signature::default());
+    }
+}
@@ -0,0 +1,694 @@
+//! Decoding of instructions of well-known programs.
+//!
+//! An [`InstructionDecoderRegistry`] maps program ids to
+//! [`InstructionDecoder`]s, which turn raw instruction data into the typed
+//! instruction values of each program's interface crate. The default registry
+//! knows the System, Compute Budget, Vote, BPF Upgradeable Loader, Address
+//! Lookup Table and Feature Gate programs; other programs can be added by
+//! implementing [`InstructionDecoder`] and calling
+//! [`InstructionDecoderRegistry::register`].
+//!
+//! ```
+//! # use {
+//! #     solana_address::Address,
+//! #     solana_system_interface::instruction::{self as system_instruction, SystemInstruction},
+//! #     solana_transaction::decoder::InstructionDecoderRegistry,
+//! # };
+//! let from = Address::new_unique();
+//! let to = Address::new_unique();
+//! let instruction = system_instruction::transfer(&from, &to, 42);
+//!
+//! let registry = InstructionDecoderRegistry::default();
+//! let decoded = registry
+//!     .decode(&instruction.program_id, &instruction.data)
+//!     .unwrap();
+//!
+//! assert_eq!(
+//!     decoded.downcast_ref::<SystemInstruction>(),
+//!     Some(&SystemInstruction::Transfer { lamports: 42 })
+//! );
+//! assert_eq!(decoded.account_names(), ["Funding account", "Recipient account"]);
+//! ```
+
+use {
+    alloc::{boxed::Box, string::String},
+    core::{any::Any, fmt},
+    solana_address::Address,
+    solana_address_lookup_table_interface::instruction::ProgramInstruction,
+    solana_compute_budget_interface::ComputeBudgetInstruction,
+    solana_feature_gate_interface::instruction::FeatureGateInstruction,
+    solana_loader_v3_interface::instruction::UpgradeableLoaderInstruction,
+    solana_message::compiled_instruction::CompiledInstruction,
+    solana_sdk_ids::{
+        address_lookup_table, bpf_loader_upgradeable, compute_budget, feature, system_program, vote,
+    },
+    solana_system_interface::instruction::SystemInstruction,
+    solana_vote_interface::instruction::VoteInstruction,
+    std::collections::HashMap,
+};
+
+/// Decodes the instructions of a program.
+pub trait InstructionDecoder: Send + Sync {
+    /// Returns the name of the program.
+    fn program_name(&self) -> &str;
+
+    /// Decodes instruction data, returning `None` if the data is not a valid
+    /// instruction of the program.
+    fn decode(&self, data: &[u8]) -> Option<DecodedInstruction>;
+}
+
+/// A typed instruction value.
+trait InstructionValue: Any + fmt::Debug + Send + Sync {}
+
+impl<T: Any + fmt::Debug + Send + Sync> InstructionValue for T {}
+
+/// An instruction decoded into the instruction type of its program.
+#[derive(Debug)]
+pub struct DecodedInstruction {
+    name: &'static str,
+    instruction: Box<dyn InstructionValue>,
+    account_names: &'static [&'static str],
+}
+
+impl DecodedInstruction {
+    /// Creates a decoded instruction from the name of the instruction, its
+    /// typed value and the names of the accounts it expects, in order.
+    ///
+    /// Optional accounts are named as well; accounts past the end of
+    /// `account_names` are left unnamed.
+    pub fn new<T: Any + fmt::Debug + Send + Sync>(
+        name: &'static str,
+        instruction: T,
+        account_names: &'static [&'static str],
+    ) -> Self {
+        Self {
+            name,
+            instruction: Box::new(instruction),
+            account_names,
+        }
+    }
+
+    /// Returns the typed instruction value if it is of type `T`.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        (self.instruction.as_ref() as &dyn Any).downcast_ref()
+    }
+
+    /// Returns the names of the accounts expected by the instruction.
+    pub fn account_names(&self) -> &'static [&'static str] {
+        self.account_names
+    }
+
+    /// Returns the name of the account at `position` in the instruction's
+    /// account list.
+    pub fn account_name(&self, position: usize) -> Option<&'static str> {
+        self.account_names.get(position).copied()
+    }
+
+    /// Returns the name of the instruction.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the debug representation of the typed instruction value.
+    pub fn details(&self) -> String {
+        alloc::format!("{:?}", self.instruction)
+    }
+}
+
+/// A registry of [`InstructionDecoder`]s keyed by program id.
+pub struct InstructionDecoderRegistry {
+    decoders: HashMap<Address, Box<dyn InstructionDecoder>>,
+}
+
+impl Default for InstructionDecoderRegistry {
+    /// Creates a registry with decoders for the well-known programs.
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(system_program::id(), SystemDecoder);
+        registry.register(compute_budget::id(), ComputeBudgetDecoder);
+        registry.register(vote::id(), VoteDecoder);
+        registry.register(bpf_loader_upgradeable::id(), LoaderV3Decoder);
+        registry.register(address_lookup_table::id(), AddressLookupTableDecoder);
+        registry.register(feature::id(), FeatureGateDecoder);
+        registry
+    }
+}
+
+impl fmt::Debug for InstructionDecoderRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(
+                self.decoders
+                    .iter()
+                    .map(|(program_id, decoder)| (program_id, decoder.program_name())),
+            )
+            .finish()
+    }
+}
+
+impl InstructionDecoderRegistry {
+    /// Creates a registry without any decoders.
+    pub fn empty() -> Self {
+        Self {
+            decoders: HashMap::new(),
+        }
+    }
+
+    /// Registers the decoder for a program, returning the decoder it
+    /// replaces, if any.
+    pub fn register(
+        &mut self,
+        program_id: Address,
+        decoder: impl InstructionDecoder + 'static,
+    ) -> Option<Box<dyn InstructionDecoder>> {
+        self.decoders.insert(program_id, Box::new(decoder))
+    }
+
+    /// Returns the decoder registered for a program.
+    pub fn get(&self, program_id: &Address) -> Option<&dyn InstructionDecoder> {
+        self.decoders.get(program_id).map(Box::as_ref)
+    }
+
+    /// Returns the name of a registered program.
+    pub fn program_name(&self, program_id: &Address) -> Option<&str> {
+        self.get(program_id).map(InstructionDecoder::program_name)
+    }
+
+    /// Decodes the data of an instruction of a registered program.
+    pub fn decode(&self, program_id: &Address, data: &[u8]) -> Option<DecodedInstruction> {
+        self.get(program_id)?.decode(data)
+    }
+
+    /// Decodes a compiled instruction, resolving its program id from the
+    /// account keys of its message.
+    pub fn decode_compiled_instruction(
+        &self,
+        account_keys: &[Address],
+        instruction: &CompiledInstruction,
+    ) -> Option<DecodedInstruction> {
+        let program_id = account_keys.get(usize::from(instruction.program_id_index))?;
+        self.decode(program_id, &instruction.data)
+    }
+}
+
+fn limited_deserialize<T: serde::de::DeserializeOwned>(data: &[u8]) -> Option<T> {
+    solana_bincode::limited_deserialize(data, data.len() as u64).ok()
+}
+
+struct SystemDecoder;
+
+impl InstructionDecoder for SystemDecoder {
+    fn program_name(&self) -> &str {
+        "System"
+    }
+
+    fn decode(&self, data: &[u8]) -> Option<DecodedInstruction> {
+        let instruction: SystemInstruction = limited_deserialize(data)?;
+        let (name, account_names): (_, &[&str]) = match instruction {
+            SystemInstruction::CreateAccount { .. } => {
+                ("CreateAccount", &["Funding account", "New account"])
+            }
+            SystemInstruction::Assign { .. } => ("Assign", &["Assigned account"]),
+            SystemInstruction::Transfer { .. } => {
+                ("Transfer", &["Funding account", "Recipient account"])
+            }
+            SystemInstruction::CreateAccountWithSeed { .. } => (
+                "CreateAccountWithSeed",
+                &["Funding account", "Created account", "Base account"],
+            ),
+            SystemInstruction::AdvanceNonceAccount => (
+                "AdvanceNonceAccount",
+                &[
+                    "Nonce account",
+                    "RecentBlockhashes sysvar",
+                    "Nonce authority",
+                ],
+            ),
+            SystemInstruction::WithdrawNonceAccount(_) => (
+                "WithdrawNonceAccount",
+                &[
+                    "Nonce account",
+                    "Recipient account",
+                    "RecentBlockhashes sysvar",
+                    "Rent sysvar",
+                    "Nonce authority",
+                ],
+            ),
+            SystemInstruction::InitializeNonceAccount(_) => (
+                "InitializeNonceAccount",
+                &["Nonce account", "RecentBlockhashes sysvar", "Rent sysvar"],
+            ),
+            SystemInstruction::AuthorizeNonceAccount(_) => (
+                "AuthorizeNonceAccount",
+                &["Nonce account", "Nonce authority"],
+            ),
+            SystemInstruction::Allocate { .. } => ("Allocate", &["New account"]),
+            SystemInstruction::AllocateWithSeed { .. } => {
+                ("AllocateWithSeed", &["Allocated account", "Base account"])
+            }
+            SystemInstruction::AssignWithSeed { .. } => {
+                ("AssignWithSeed", &["Assigned account", "Base account"])
+            }
+            SystemInstruction::TransferWithSeed { .. } => (
+                "TransferWithSeed",
+                &[
+                    "Funding account",
+                    "Base for funding account",
+                    "Recipient account",
+                ],
+            ),
+            SystemInstruction::UpgradeNonceAccount => ("UpgradeNonceAccount", &["Nonce account"]),
+            SystemInstruction::CreateAccountAllowPrefund { .. } => (
+                "CreateAccountAllowPrefund",
+                &["New account", "Funding account"],
+            ),
+        };
+        Some(DecodedInstruction::new(name, instruction, account_names))
+    }
+}
+
+struct ComputeBudgetDecoder;
+
+impl InstructionDecoder for ComputeBudgetDecoder {
+    fn program_name(&self) -> &str {
+        "Compute Budget"
+    }
+
+    fn decode(&self, data: &[u8]) -> Option<DecodedInstruction> {
+        let instruction: ComputeBudgetInstruction = borsh::from_slice(data).ok()?;
+        let name = match instruction {
+            ComputeBudgetInstruction::Unused => "Unused",
+            ComputeBudgetInstruction::RequestHeapFrame(_) => "RequestHeapFrame",
+            ComputeBudgetInstruction::SetComputeUnitLimit(_) => "SetComputeUnitLimit",
+            ComputeBudgetInstruction::SetComputeUnitPrice(_) => "SetComputeUnitPrice",
+            ComputeBudgetInstruction::SetLoadedAccountsDataSizeLimit(_) => {
+                "SetLoadedAccountsDataSizeLimit"
+            }
+        };
+        Some(DecodedInstruction::new(name, instruction, &[]))
+    }
+}
+
+struct VoteDecoder;
+
+impl InstructionDecoder for VoteDecoder {
+    fn program_name(&self) -> &str {
+        "Vote"
+    }
+
+    fn decode(&self, data: &[u8]) -> Option<DecodedInstruction> {
+        const VOTE: &[&str] = &[
+            "Vote account",
+            "Slot hashes sysvar",
+            "Clock sysvar",
+            "Vote authority",
+        ];
+        const VOTE_STATE_UPDATE: &[&str] = &["Vote account", "Vote authority"];
+        const UPDATE_COMMISSION: &[&str] = &["Vote account", "Withdraw authority"];
+
+        let instruction: VoteInstruction = limited_deserialize(data)?;
+        let (name, account_names): (_, &[&str]) = match instruction {
+            VoteInstruction::InitializeAccount(_) => (
+                "InitializeAccount",
+                &[
+                    "Vote account",
+                    "Rent sysvar",
+                    "Clock sysvar",
+                    "Validator identity",
+                ],
+            ),
+            VoteInstruction::Authorize(..) => (
+                "Authorize",
+                &["Vote account", "Clock sysvar", "Vote or withdraw authority"],
+            ),
+            VoteInstruction::Vote(_) => ("Vote", VOTE),
+            VoteInstruction::VoteSwitch(..) => ("VoteSwitch", VOTE),
+            VoteInstruction::Withdraw(_) => (
+                "Withdraw",
+                &["Vote account", "Recipient account", "Withdraw authority"],
+            ),
+            VoteInstruction::UpdateValidatorIdentity => (
+                "UpdateValidatorIdentity",
+                &[
+                    "Vote account",
+                    "New validator identity",
+                    "Withdraw authority",
+                ],
+            ),
+            VoteInstruction::UpdateCommission(_) => ("UpdateCommission", UPDATE_COMMISSION),
+            VoteInstruction::UpdateCommissionBps { .. } => {
+                ("UpdateCommissionBps", UPDATE_COMMISSION)
+            }
+            VoteInstruction::AuthorizeChecked(_) => (
+                "AuthorizeChecked",
+                &[
+                    "Vote account",
+                    "Clock sysvar",
+                    "Vote or withdraw authority",
+                    "New vote or withdraw authority",
+                ],
+            ),
+            VoteInstruction::UpdateVoteState(_) => ("UpdateVoteState", VOTE_STATE_UPDATE),
+            VoteInstruction::UpdateVoteStateSwitch(..) => {
+                ("UpdateVoteStateSwitch", VOTE_STATE_UPDATE)
+            }
+            VoteInstruction::CompactUpdateVoteState(_) => {
+                ("CompactUpdateVoteState", VOTE_STATE_UPDATE)
+            }
+            VoteInstruction::CompactUpdateVoteStateSwitch(..) => {
+                ("CompactUpdateVoteStateSwitch", VOTE_STATE_UPDATE)
+            }
+            VoteInstruction::TowerSync(_) => ("TowerSync", VOTE_STATE_UPDATE),
+            VoteInstruction::TowerSyncSwitch(..) => ("TowerSyncSwitch", VOTE_STATE_UPDATE),
+            VoteInstruction::AuthorizeWithSeed(_) => (
+                "AuthorizeWithSeed",
+                &["Vote account", "Clock sysvar", "Base key of authority"],
+            ),
+            VoteInstruction::AuthorizeCheckedWithSeed(_) => (
+                "AuthorizeCheckedWithSeed",
+                &[
+                    "Vote account",
+                    "Clock sysvar",
+                    "Base key of authority",
+                    "New vote or withdraw authority",
+                ],
+            ),
+            VoteInstruction::InitializeAccountV2(_) => (
+                "InitializeAccountV2",
+                &[
+                    "Vote account",
+                    "Validator identity",
+                    "Inflation rewards collector",
+                    "Block revenue collector",
+                ],
+            ),
+            VoteInstruction::UpdateCommissionCollector(_) => (
+                "UpdateCommissionCollector",
+                &[
+                    "Vote account",
+                    "New collector account",
+                    "Withdraw authority",
+                ],
+            ),
+            VoteInstruction::DepositDelegatorRewards { .. } => (
+                "DepositDelegatorRewards",
+                &["Vote account", "Source account"],
+            ),
+        };
+        Some(DecodedInstruction::new(name, instruction, account_names))
+    }
+}
+
+struct LoaderV3Decoder;
+
+impl InstructionDecoder for LoaderV3Decoder {
+    fn program_name(&self) -> &str {
+        "BPF Upgradeable Loader"
+    }
+
+    fn decode(&self, data: &[u8]) -> Option<DecodedInstruction> {
+        const BUFFER: &[&str] = &["Buffer account", "Buffer authority"];
+        const SET_AUTHORITY: &[&str] = &["Account", "Current authority", "New authority"];
+
+        let instruction: UpgradeableLoaderInstruction = wincode::deserialize(data).ok()?;
+        let (name, account_names): (_, &[&str]) = match instruction {
+            UpgradeableLoaderInstruction::InitializeBuffer => ("InitializeBuffer", BUFFER),
+            UpgradeableLoaderInstruction::Write { .. } => ("Write", BUFFER),
+            UpgradeableLoaderInstruction::DeployWithMaxDataLen { .. } => (
+                "DeployWithMaxDataLen",
+                &[
+                    "Payer account",
+                    "ProgramData account",
+                    "Program account",
+                    "Buffer account",
+                    "Rent sysvar",
+                    "Clock sysvar",
+                    "System program",
+                    "Program authority",
+                ],
+            ),
+            UpgradeableLoaderInstruction::Upgrade { .. } => (
+                "Upgrade",
+                &[
+                    "ProgramData account",
+                    "Program account",
+                    "Buffer account",
+                    "Spill account",
+                    "Rent sysvar",
+                    "Clock sysvar",
+                    "Program authority",
+                ],
+            ),
+            UpgradeableLoaderInstruction::SetAuthority => ("SetAuthority", SET_AUTHORITY),
+            UpgradeableLoaderInstruction::SetAuthorityChecked => {
+                ("SetAuthorityChecked", SET_AUTHORITY)
+            }
+            UpgradeableLoaderInstruction::Close { .. } => (
+                "Close",
+                &[
+                    "Account",
+                    "Recipient account",
+                    "Authority",
+                    "Program account",
+                ],
+            ),
+            UpgradeableLoaderInstruction::ExtendProgram { .. } => (
+                "ExtendProgram",
+                &[
+                    "ProgramData account",
+                    "Program account",
+                    "System program",
+                    "Payer account",
+                ],
+            ),
+        };
+        Some(DecodedInstruction::new(name, instruction, account_names))
+    }
+}
+
+struct AddressLookupTableDecoder;
+
+impl InstructionDecoder for AddressLookupTableDecoder {
+    fn program_name(&self) -> &str {
+        "Address Lookup Table"
+    }
+
+    fn decode(&self, data: &[u8]) -> Option<DecodedInstruction> {
+        const CREATE_OR_EXTEND: &[&str] = &[
+            "Lookup table account",
+            "Authority",
+            "Payer account",
+            "System program",
+        ];
+        const AUTHORITY_ONLY: &[&str] = &["Lookup table account", "Authority"];
+
+        let instruction: ProgramInstruction = limited_deserialize(data)?;
+        let (name, account_names): (_, &[&str]) = match instruction {
+            ProgramInstruction::CreateLookupTable { .. } => ("CreateLookupTable", CREATE_OR_EXTEND),
+            ProgramInstruction::ExtendLookupTable { .. } => ("ExtendLookupTable", CREATE_OR_EXTEND),
+            ProgramInstruction::FreezeLookupTable => ("FreezeLookupTable", AUTHORITY_ONLY),
+            ProgramInstruction::DeactivateLookupTable => ("DeactivateLookupTable", AUTHORITY_ONLY),
+            ProgramInstruction::CloseLookupTable => (
+                "CloseLookupTable",
+                &["Lookup table account", "Authority", "Recipient account"],
+            ),
+        };
+        Some(DecodedInstruction::new(name, instruction, account_names))
+    }
+}
+
+struct FeatureGateDecoder;
+
+impl InstructionDecoder for FeatureGateDecoder {
+    fn program_name(&self) -> &str {
+        "Feature Gate"
+    }
+
+    fn decode(&self, data: &[u8]) -> Option<DecodedInstruction> {
+        let instruction = FeatureGateInstruction::unpack(data).ok()?;
+        let (name, account_names): (_, &[&str]) = match instruction {
+            FeatureGateInstruction::RevokePendingActivation => (
+                "RevokePendingActivation",
+                &["Feature account", "Incinerator", "System program"],
+            ),
+        };
+        Some(DecodedInstruction::new(name, instruction, account_names))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, alloc::vec, solana_instruction::Instruction};
+
+    fn decode(instruction: &Instruction) -> DecodedInstruction {
+        InstructionDecoderRegistry::default()
+            .decode(&instruction.program_id, &instruction.data)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_decode_well_known_programs() {
+        let address = Address::new_unique();
+
+        let decoded = decode(&solana_system_interface::instruction::transfer(
+            &address, &address, 42,
+        ));
+        assert_eq!(
+            decoded.downcast_ref::<SystemInstruction>(),
+            Some(&SystemInstruction::Transfer { lamports: 42 })
+        );
+        assert_eq!(decoded.name(), "Transfer");
+        assert_eq!(decoded.details(), "Transfer { lamports: 42 }");
+
+        let decoded = decode(&ComputeBudgetInstruction::set_compute_unit_limit(1));
+        assert_eq!(
+            decoded.downcast_ref::<ComputeBudgetInstruction>(),
+            Some(&ComputeBudgetInstruction::SetComputeUnitLimit(1))
+        );
+        assert_eq!(decoded.name(), "SetComputeUnitLimit");
+        assert!(decoded.account_names().is_empty());
+
+        let decoded = decode(&solana_vote_interface::instruction::withdraw(
+            &address, &address, 1, &address,
+        ));
+        assert_eq!(
+            decoded.downcast_ref::<VoteInstruction>(),
+            Some(&VoteInstruction::Withdraw(1))
+        );
+        assert_eq!(
+            decoded.account_names(),
+            ["Vote account", "Recipient account", "Withdraw authority"]
+        );
+
+        let decoded = decode(
+            &solana_address_lookup_table_interface::instruction::freeze_lookup_table(
+                address, address,
+            ),
+        );
+        assert_eq!(
+            decoded.downcast_ref::<ProgramInstruction>(),
+            Some(&ProgramInstruction::FreezeLookupTable)
+        );
+
+        let decoded = decode(&Instruction::new_with_bytes(
+            feature::id(),
+            &FeatureGateInstruction::RevokePendingActivation.pack(),
+            vec![],
+        ));
+        assert_eq!(
+            decoded.downcast_ref::<FeatureGateInstruction>(),
+            Some(&FeatureGateInstruction::RevokePendingActivation)
+        );
+        assert_eq!(decoded.account_name(1), Some("Incinerator"));
+        assert_eq!(decoded.account_name(3), None);
+
+        // the wrong type is not returned
+        assert_eq!(decoded.downcast_ref::<SystemInstruction>(), None);
+    }
+
+    #[test]
+    fn test_decode_loader_v3_optional_trailing_bool() {
+        let registry = InstructionDecoderRegistry::default();
+        let program_id = bpf_loader_upgradeable::id();
+
+        // `Close` without the trailing `tombstone` byte
+        let decoded = registry.decode(&program_id, &[5, 0, 0, 0]).unwrap();
+        assert_eq!(
+            decoded.downcast_ref::<UpgradeableLoaderInstruction>(),
+            Some(&UpgradeableLoaderInstruction::Close { tombstone: false })
+        );
+
+        let decoded = registry.decode(&program_id, &[5, 0, 0, 0, 1]).unwrap();
+        assert_eq!(
+            decoded.downcast_ref::<UpgradeableLoaderInstruction>(),
+            Some(&UpgradeableLoaderInstruction::Close { tombstone: true })
+        );
+    }
+
+    #[test]
+    fn test_decode_invalid_data() {
+        let registry = InstructionDecoderRegistry::default();
+
+        assert!(registry.decode(&system_program::id(), &[255]).is_none());
+        assert!(registry.decode(&compute_budget::id(), &[]).is_none());
+        assert!(registry.decode(&feature::id(), &[1]).is_none());
+        assert!(registry
+            .decode(&Address::new_unique(), &[0, 0, 0, 0])
+            .is_none());
+    }
+
+    #[test]
+    fn test_decode_compiled_instruction() {
+        let registry = InstructionDecoderRegistry::default();
+        let account_keys = [Address::new_unique(), system_program::id()];
+        let data = bincode::serialize(&SystemInstruction::Assign {
+            owner: Address::new_unique(),
+        })
+        .unwrap();
+        let instruction = CompiledInstruction::new_from_raw_parts(1, data, vec![0]);
+
+        let decoded = registry
+            .decode_compiled_instruction(&account_keys, &instruction)
+            .unwrap();
+        assert_eq!(decoded.name(), "Assign");
+        assert_eq!(decoded.account_names(), ["Assigned account"]);
+
+        let instruction = CompiledInstruction::new_from_raw_parts(2, vec![], vec![]);
+        assert!(registry
+            .decode_compiled_instruction(&account_keys, &instruction)
+            .is_none());
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum CounterInstruction {
+        Increment(u8),
+    }
+
+    struct CounterDecoder;
+
+    impl InstructionDecoder for CounterDecoder {
+        fn program_name(&self) -> &str {
+            "Counter"
+        }
+
+        fn decode(&self, data: &[u8]) -> Option<DecodedInstruction> {
+            match data {
+                [0, amount] => Some(DecodedInstruction::new(
+                    "Increment",
+                    CounterInstruction::Increment(*amount),
+                    &["Counter account"],
+                )),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn test_register_custom_decoder() {
+        let program_id = Address::new_unique();
+        let mut registry = InstructionDecoderRegistry::default();
+
+        assert!(registry.register(program_id, CounterDecoder).is_none());
+        assert_eq!(registry.program_name(&program_id), Some("Counter"));
+
+        let decoded = registry.decode(&program_id, &[0, 7]).unwrap();
+        assert_eq!(
+            decoded.downcast_ref::<CounterInstruction>(),
+            Some(&CounterInstruction::Increment(7))
+        );
+        assert_eq!(decoded.account_names(), ["Counter account"]);
+        assert_eq!(decoded.name(), "Increment");
+
+        // registering a decoder for a well-known program replaces it
+        let replaced = registry.register(system_program::id(), CounterDecoder);
+        assert_eq!(
+            replaced.map(|decoder| String::from(decoder.program_name())),
+            Some(String::from("System"))
+        );
+        assert!(registry.decode(&system_program::id(), &[0, 1]).is_some());
+
+        assert!(InstructionDecoderRegistry::empty()
+            .get(&system_program::id())
+            .is_none());
+    }
+}
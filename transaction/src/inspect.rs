@@ -0,0 +1,669 @@
+//! Human-readable inspection of versioned transactions.
+//!
+//! [`TransactionInspection`] decodes a [`VersionedTransaction`] of any
+//! version into a flat description of its signatures, header, accounts,
+//! compute budget configuration and instructions. Instructions are decoded
+//! with an [`InstructionDecoderRegistry`], which knows the well-known programs
+//! by default.
+//!
+//! The inspection can be printed in a readable layout through its
+//! [`Display`] implementation or serialized to JSON with
+//! [`TransactionInspection::to_json`].
+//!
+//! ```
+//! # use {
+//! #     solana_hash::Hash,
+//! #     solana_keypair::Keypair,
+//! #     solana_signer::Signer,
+//! #     solana_system_interface::instruction as system_instruction,
+//! #     solana_transaction::{inspect::TransactionInspection, Transaction},
+//! # };
+//! let payer = Keypair::new();
+//! let instruction = system_instruction::transfer(&payer.pubkey(), &payer.pubkey(), 42);
+//! let transaction = Transaction::new_signed_with_payer(
+//!     &[instruction],
+//!     Some(&payer.pubkey()),
+//!     &[&payer],
+//!     Hash::default(),
+//! );
+//!
+//! let inspection = TransactionInspection::new(&transaction.into());
+//! println!("{inspection}");
+//! ```
+//!
+//! [`Display`]: core::fmt::Display
+
+use {
+    crate::{
+        decoder::InstructionDecoderRegistry,
+        versioned::{TransactionVersion, VersionedTransaction},
+    },
+    alloc::{
+        format,
+        string::{String, ToString},
+        vec::Vec,
+    },
+    core::fmt,
+    serde_derive::Serialize,
+    solana_address::Address,
+    solana_message::{v1::TransactionConfig, AddressLookupTableAccount, VersionedMessage},
+};
+
+/// A readable description of a [`VersionedTransaction`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionInspection {
+    /// Version of the transaction message.
+    pub version: TransactionVersion,
+    /// Base58-encoded transaction signatures.
+    pub signatures: Vec<String>,
+    /// Message header counts.
+    pub header: HeaderInspection,
+    /// Base58-encoded recent blockhash or durable nonce.
+    pub recent_blockhash: String,
+    /// Accounts referenced by the message, static keys first followed by
+    /// the addresses loaded from lookup tables.
+    pub accounts: Vec<AccountInspection>,
+    /// Compute budget configuration of V1 messages.
+    pub config: Option<TransactionConfig>,
+    /// Address table lookups of V0 messages.
+    pub address_table_lookups: Vec<AddressTableLookupInspection>,
+    /// Instructions in execution order.
+    pub instructions: Vec<InstructionInspection>,
+}
+
+/// Message header counts.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeaderInspection {
+    pub num_required_signatures: u8,
+    pub num_readonly_signed_accounts: u8,
+    pub num_readonly_unsigned_accounts: u8,
+}
+
+/// An account referenced by the message.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountInspection {
+    /// Base58-encoded address, or `None` for an address loaded from a lookup
+    /// table that was not provided.
+    pub address: Option<String>,
+    pub signer: bool,
+    pub writable: bool,
+    /// Where the address of the account comes from.
+    pub source: AccountSource,
+}
+
+/// Where the address of an account comes from.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum AccountSource {
+    /// The address is one of the static account keys of the message.
+    Static,
+    /// The address is loaded from an address lookup table.
+    #[serde(rename_all = "camelCase")]
+    LookupTable {
+        /// Base58-encoded address of the lookup table.
+        table: String,
+        /// Index of the address in the lookup table.
+        index: u8,
+    },
+}
+
+/// An address table lookup of a V0 message.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressTableLookupInspection {
+    /// Base58-encoded address of the lookup table.
+    pub account_key: String,
+    pub writable_indexes: Vec<u8>,
+    pub readonly_indexes: Vec<u8>,
+}
+
+/// An instruction of the message.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstructionInspection {
+    /// Base58-encoded program id, or `None` if the program id index is out
+    /// of bounds.
+    pub program_id: Option<String>,
+    pub program_id_index: u8,
+    /// Indexes into the accounts of the message.
+    pub accounts: Vec<u8>,
+    /// Hex-encoded instruction data.
+    pub data: String,
+    /// The decoded instruction, if its program has a registered decoder and
+    /// its data is valid.
+    pub decoded: Option<DecodedInstructionInspection>,
+}
+
+/// An instruction decoded by the decoder registered for its program.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedInstructionInspection {
+    /// Name of the program.
+    pub program: String,
+    /// Name of the instruction variant.
+    pub name: String,
+    /// Debug representation of the decoded instruction.
+    pub details: String,
+    /// Names of the instruction accounts, in order. Accounts the decoder
+    /// does not name are omitted.
+    pub account_names: Vec<String>,
+}
+
+impl TransactionInspection {
+    /// Inspects a transaction, leaving addresses loaded from lookup tables
+    /// unresolved.
+    pub fn new(transaction: &VersionedTransaction) -> Self {
+        Self::new_with_lookup_tables(transaction, &[])
+    }
+
+    /// Inspects a transaction, resolving the addresses loaded from the given
+    /// lookup tables.
+    pub fn new_with_lookup_tables(
+        transaction: &VersionedTransaction,
+        address_lookup_table_accounts: &[AddressLookupTableAccount],
+    ) -> Self {
+        Self::new_with_decoders(
+            transaction,
+            address_lookup_table_accounts,
+            &InstructionDecoderRegistry::default(),
+        )
+    }
+
+    /// Inspects a transaction, resolving the addresses loaded from the given
+    /// lookup tables and decoding instructions with the given registry.
+    pub fn new_with_decoders(
+        transaction: &VersionedTransaction,
+        address_lookup_table_accounts: &[AddressLookupTableAccount],
+        decoders: &InstructionDecoderRegistry,
+    ) -> Self {
+        let message = &transaction.message;
+        let header = message.header();
+
+        let mut account_addresses: Vec<(Option<&Address>, AccountSource)> = message
+            .static_account_keys()
+            .iter()
+            .map(|key| (Some(key), AccountSource::Static))
+            .collect();
+
+        let lookups = message.address_table_lookups().unwrap_or_default();
+        // loaded writable addresses of every lookup come before the readonly ones
+        for writable in [true, false] {
+            for lookup in lookups {
+                let table = address_lookup_table_accounts
+                    .iter()
+                    .find(|table| table.key == lookup.account_key);
+                let indexes = if writable {
+                    &lookup.writable_indexes
+                } else {
+                    &lookup.readonly_indexes
+                };
+                account_addresses.extend(indexes.iter().map(|index| {
+                    (
+                        table.and_then(|table| table.addresses.get(usize::from(*index))),
+                        AccountSource::LookupTable {
+                            table: lookup.account_key.to_string(),
+                            index: *index,
+                        },
+                    )
+                }));
+            }
+        }
+
+        let accounts = account_addresses
+            .into_iter()
+            .enumerate()
+            .map(|(index, (address, source))| AccountInspection {
+                address: address.map(ToString::to_string),
+                signer: message.is_signer(index),
+                writable: message.is_maybe_writable(index, None),
+                source,
+            })
+            .collect();
+
+        let instructions = message
+            .instructions()
+            .iter()
+            .map(|instruction| {
+                let program_id = message
+                    .static_account_keys()
+                    .get(usize::from(instruction.program_id_index));
+                InstructionInspection {
+                    program_id: program_id.map(ToString::to_string),
+                    program_id_index: instruction.program_id_index,
+                    accounts: instruction.accounts.clone(),
+                    data: instruction
+                        .data
+                        .iter()
+                        .map(|byte| format!("{byte:02x}"))
+                        .collect(),
+                    decoded: program_id.and_then(|program_id| {
+                        let program = decoders.program_name(program_id)?;
+                        let decoded = decoders.decode(program_id, &instruction.data)?;
+                        Some(DecodedInstructionInspection {
+                            program: program.to_string(),
+                            name: decoded.name().to_string(),
+                            details: decoded.details(),
+                            account_names: decoded
+                                .account_names()
+                                .iter()
+                                .take(instruction.accounts.len())
+                                .map(ToString::to_string)
+                                .collect(),
+                        })
+                    }),
+                }
+            })
+            .collect();
+
+        Self {
+            version: transaction.version(),
+            signatures: transaction
+                .signatures
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            header: HeaderInspection {
+                num_required_signatures: header.num_required_signatures,
+                num_readonly_signed_accounts: header.num_readonly_signed_accounts,
+                num_readonly_unsigned_accounts: header.num_readonly_unsigned_accounts,
+            },
+            recent_blockhash: message.recent_blockhash().to_string(),
+            accounts,
+            config: match message {
+                VersionedMessage::V1(message) => Some(message.config),
+                _ => None,
+            },
+            address_table_lookups: lookups
+                .iter()
+                .map(|lookup| AddressTableLookupInspection {
+                    account_key: lookup.account_key.to_string(),
+                    writable_indexes: lookup.writable_indexes.clone(),
+                    readonly_indexes: lookup.readonly_indexes.clone(),
+                })
+                .collect(),
+            instructions,
+        }
+    }
+
+    /// Deserializes a transaction from its wire format and inspects it.
+    pub fn from_bytes(bytes: &[u8]) -> wincode::ReadResult<Self> {
+        wincode::deserialize::<VersionedTransaction>(bytes)
+            .map(|transaction| Self::new(&transaction))
+    }
+
+    /// Serializes the inspection to a JSON string.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    /// Serializes the inspection to a pretty-printed JSON string.
+    pub fn to_json_pretty(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+impl fmt::Display for TransactionInspection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.version {
+            TransactionVersion::Legacy(_) => writeln!(f, "Version: legacy")?,
+            TransactionVersion::Number(version) => writeln!(f, "Version: {version}")?,
+        }
+
+        writeln!(f, "Signatures ({}):", self.signatures.len())?;
+        for (index, signature) in self.signatures.iter().enumerate() {
+            writeln!(f, "  {index}: {signature}")?;
+        }
+
+        writeln!(f, "Header:")?;
+        writeln!(
+            f,
+            "  Required signatures: {}",
+            self.header.num_required_signatures
+        )?;
+        writeln!(
+            f,
+            "  Readonly signed accounts: {}",
+            self.header.num_readonly_signed_accounts
+        )?;
+        writeln!(
+            f,
+            "  Readonly unsigned accounts: {}",
+            self.header.num_readonly_unsigned_accounts
+        )?;
+        writeln!(f, "Recent blockhash: {}", self.recent_blockhash)?;
+
+        writeln!(f, "Accounts ({}):", self.accounts.len())?;
+        for (index, account) in self.accounts.iter().enumerate() {
+            let address = account.address.as_deref().unwrap_or("<unresolved>");
+            let signer = if account.signer { "signer" } else { "-" };
+            let writable = if account.writable {
+                "writable"
+            } else {
+                "readonly"
+            };
+            write!(f, "  {index}: {address} [{signer}, {writable}]")?;
+            match &account.source {
+                AccountSource::Static => writeln!(f)?,
+                AccountSource::LookupTable { table, index } => {
+                    writeln!(f, " (lookup table {table}, index {index})")?
+                }
+            }
+        }
+
+        if let Some(config) = &self.config {
+            writeln!(f, "Config:")?;
+            if let Some(priority_fee) = config.priority_fee {
+                writeln!(f, "  Priority fee: {priority_fee} lamports")?;
+            }
+            if let Some(compute_unit_limit) = config.compute_unit_limit {
+                writeln!(f, "  Compute unit limit: {compute_unit_limit}")?;
+            }
+            if let Some(limit) = config.loaded_accounts_data_size_limit {
+                writeln!(f, "  Loaded accounts data size limit: {limit} bytes")?;
+            }
+            if let Some(heap_size) = config.heap_size {
+                writeln!(f, "  Heap size: {heap_size} bytes")?;
+            }
+        }
+
+        if !self.address_table_lookups.is_empty() {
+            writeln!(
+                f,
+                "Address table lookups ({}):",
+                self.address_table_lookups.len()
+            )?;
+            for (index, lookup) in self.address_table_lookups.iter().enumerate() {
+                writeln!(
+                    f,
+                    "  {index}: {} writable {:?} readonly {:?}",
+                    lookup.account_key, lookup.writable_indexes, lookup.readonly_indexes
+                )?;
+            }
+        }
+
+        writeln!(f, "Instructions ({}):", self.instructions.len())?;
+        for (index, instruction) in self.instructions.iter().enumerate() {
+            let program_id = instruction.program_id.as_deref().unwrap_or("<invalid>");
+            match &instruction.decoded {
+                Some(decoded) => writeln!(f, "  {index}: {program_id} ({})", decoded.program)?,
+                None => writeln!(f, "  {index}: {program_id}")?,
+            }
+            let account_names = instruction
+                .decoded
+                .as_ref()
+                .map(|decoded| decoded.account_names.as_slice())
+                .unwrap_or_default();
+            if account_names.is_empty() {
+                writeln!(f, "     Accounts: {:?}", instruction.accounts)?;
+            } else {
+                writeln!(f, "     Accounts:")?;
+                for (position, account_index) in instruction.accounts.iter().enumerate() {
+                    match account_names.get(position) {
+                        Some(name) => writeln!(f, "       {name}: {account_index}")?,
+                        None => writeln!(f, "       {account_index}")?,
+                    }
+                }
+            }
+            writeln!(f, "     Data: {}", instruction.data)?;
+            if let Some(decoded) = &instruction.decoded {
+                writeln!(f, "     Decoded: {}", decoded.details)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            decoder::{DecodedInstruction, InstructionDecoder},
+            Transaction,
+        },
+        alloc::vec,
+        solana_compute_budget_interface::ComputeBudgetInstruction,
+        solana_hash::Hash,
+        solana_instruction::{AccountMeta, Instruction},
+        solana_keypair::Keypair,
+        solana_message::{v0, v1, Message},
+        solana_signer::Signer,
+        solana_system_interface::instruction as system_instruction,
+    };
+
+    #[test]
+    fn test_inspect_legacy_transaction() {
+        let payer = Keypair::new();
+        let to = Address::new_unique();
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_limit(1_000),
+            system_instruction::transfer(&payer.pubkey(), &to, 42),
+        ];
+        let transaction = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&payer.pubkey()),
+            &[&payer],
+            Hash::default(),
+        );
+
+        let inspection = TransactionInspection::new(&transaction.clone().into());
+
+        assert_eq!(inspection.version, TransactionVersion::LEGACY);
+        assert_eq!(
+            inspection.signatures,
+            vec![transaction.signatures[0].to_string()]
+        );
+        assert_eq!(
+            inspection.header,
+            HeaderInspection {
+                num_required_signatures: 1,
+                num_readonly_signed_accounts: 0,
+                num_readonly_unsigned_accounts: 2,
+            }
+        );
+        assert_eq!(
+            inspection.accounts[0],
+            AccountInspection {
+                address: Some(payer.pubkey().to_string()),
+                signer: true,
+                writable: true,
+                source: AccountSource::Static,
+            }
+        );
+        assert_eq!(
+            inspection.accounts[1],
+            AccountInspection {
+                address: Some(to.to_string()),
+                signer: false,
+                writable: true,
+                source: AccountSource::Static,
+            }
+        );
+        assert!(inspection.config.is_none());
+        assert!(inspection.address_table_lookups.is_empty());
+
+        assert_eq!(
+            inspection.instructions[0].decoded,
+            Some(DecodedInstructionInspection {
+                program: "Compute Budget".to_string(),
+                name: "SetComputeUnitLimit".to_string(),
+                details: "SetComputeUnitLimit(1000)".to_string(),
+                account_names: vec![],
+            })
+        );
+        assert_eq!(inspection.instructions[1].data, "020000002a00000000000000");
+        assert_eq!(
+            inspection.instructions[1].decoded,
+            Some(DecodedInstructionInspection {
+                program: "System".to_string(),
+                name: "Transfer".to_string(),
+                details: "Transfer { lamports: 42 }".to_string(),
+                account_names: vec![
+                    "Funding account".to_string(),
+                    "Recipient account".to_string()
+                ],
+            })
+        );
+
+        let display = inspection.to_string();
+        assert!(display.starts_with("Version: legacy\n"));
+        assert!(display.contains(&format!("0: {} [signer, writable]", payer.pubkey())));
+        assert!(display.contains("Funding account: 0\n"));
+        assert!(display.contains("Decoded: Transfer { lamports: 42 }"));
+    }
+
+    #[test]
+    fn test_inspect_v0_transaction_with_lookup_tables() {
+        let payer = Keypair::new();
+        let to = Address::new_unique();
+        let lookup_table = AddressLookupTableAccount {
+            key: Address::new_unique(),
+            addresses: vec![Address::new_unique(), to],
+        };
+        let message = v0::Message::try_compile(
+            &payer.pubkey(),
+            &[system_instruction::transfer(&payer.pubkey(), &to, 1)],
+            core::slice::from_ref(&lookup_table),
+            Hash::default(),
+        )
+        .unwrap();
+        let transaction =
+            VersionedTransaction::try_new(VersionedMessage::V0(message), &[&payer]).unwrap();
+
+        let unresolved = TransactionInspection::new(&transaction);
+        let loaded_account = unresolved.accounts.last().unwrap();
+        assert_eq!(loaded_account.address, None);
+        assert!(loaded_account.writable);
+        assert_eq!(
+            loaded_account.source,
+            AccountSource::LookupTable {
+                table: lookup_table.key.to_string(),
+                index: 1,
+            }
+        );
+        assert_eq!(
+            unresolved.address_table_lookups,
+            vec![AddressTableLookupInspection {
+                account_key: lookup_table.key.to_string(),
+                writable_indexes: vec![1],
+                readonly_indexes: vec![],
+            }]
+        );
+        assert!(unresolved.to_string().contains("<unresolved>"));
+
+        let resolved = TransactionInspection::new_with_lookup_tables(
+            &transaction,
+            core::slice::from_ref(&lookup_table),
+        );
+        assert_eq!(
+            resolved.accounts.last().unwrap().address,
+            Some(to.to_string())
+        );
+    }
+
+    #[test]
+    fn test_inspect_v1_transaction() {
+        let payer = Keypair::new();
+        let config = v1::TransactionConfig::empty()
+            .with_priority_fee(1_000)
+            .with_compute_unit_limit(200_000);
+        let message = v1::Message::try_compile_with_config(
+            &payer.pubkey(),
+            &[system_instruction::transfer(
+                &payer.pubkey(),
+                &Address::new_unique(),
+                1,
+            )],
+            Hash::default(),
+            config,
+        )
+        .unwrap();
+        let transaction =
+            VersionedTransaction::try_new(VersionedMessage::V1(message), &[&payer]).unwrap();
+
+        let inspection =
+            TransactionInspection::from_bytes(&wincode::serialize(&transaction).unwrap()).unwrap();
+
+        assert_eq!(inspection.version, TransactionVersion::Number(1));
+        assert_eq!(inspection.config, Some(config));
+        let display = inspection.to_string();
+        assert!(display.contains("Priority fee: 1000 lamports"));
+        assert!(display.contains("Compute unit limit: 200000"));
+    }
+
+    #[test]
+    fn test_inspect_with_decoders() {
+        #[derive(Debug)]
+        struct Memo(#[allow(dead_code)] String);
+
+        struct MemoDecoder;
+
+        impl InstructionDecoder for MemoDecoder {
+            fn program_name(&self) -> &str {
+                "Memo"
+            }
+
+            fn decode(&self, data: &[u8]) -> Option<DecodedInstruction> {
+                let memo = String::from_utf8(data.to_vec()).ok()?;
+                Some(DecodedInstruction::new("Memo", Memo(memo), &["Signer"]))
+            }
+        }
+
+        let payer = Address::new_unique();
+        let memo_program = Address::new_unique();
+        let instruction = Instruction::new_with_bytes(
+            memo_program,
+            b"hello",
+            vec![AccountMeta::new_readonly(payer, true)],
+        );
+        let transaction: VersionedTransaction =
+            Transaction::new_unsigned(Message::new(&[instruction], Some(&payer))).into();
+
+        assert_eq!(
+            TransactionInspection::new(&transaction).instructions[0].decoded,
+            None
+        );
+
+        let mut decoders = InstructionDecoderRegistry::empty();
+        decoders.register(memo_program, MemoDecoder);
+        let inspection = TransactionInspection::new_with_decoders(&transaction, &[], &decoders);
+
+        assert_eq!(
+            inspection.instructions[0].decoded,
+            Some(DecodedInstructionInspection {
+                program: "Memo".to_string(),
+                name: "Memo".to_string(),
+                details: "Memo(\"hello\")".to_string(),
+                account_names: vec!["Signer".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        let payer = Address::new_unique();
+        let message = Message::new(
+            &[system_instruction::transfer(&payer, &payer, 7)],
+            Some(&payer),
+        );
+        let transaction = Transaction::new_unsigned(message);
+
+        let json: serde_json::Value = serde_json::from_str(
+            &TransactionInspection::new(&transaction.into())
+                .to_json()
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(json["version"], "legacy");
+        assert_eq!(json["header"]["numRequiredSignatures"], 1);
+        assert_eq!(json["accounts"][0]["address"], payer.to_string());
+        assert_eq!(json["accounts"][0]["source"]["type"], "static");
+        assert_eq!(json["config"], serde_json::Value::Null);
+        assert_eq!(json["instructions"][0]["decoded"]["name"], "Transfer");
+    }
+}
@@ -117,6 +117,8 @@ extern crate std;
 
 #[cfg(feature = "frozen-abi")]
 use solana_frozen_abi_macro::{frozen_abi, AbiExample, StableAbi};
+#[cfg(feature = "async")]
+pub use solana_signer::async_signer::AsyncSigners;
 #[cfg(feature = "wincode")]
 pub use solana_signer::{signers::Signers, SignerError};
 use {
@@ -145,6 +147,10 @@ pub use {
     wincode::{containers, SchemaRead, SchemaWrite},
 };
 
+#[cfg(feature = "decoder")]
+pub mod decoder;
+#[cfg(feature = "inspect")]
+pub mod inspect;
 #[cfg(feature = "std")]
 pub mod sanitized;
 pub mod simple_vote_transaction_checker;
@@ -1016,6 +1022,65 @@ impl Transaction {
         Ok(())
     }
 
+    /// Sign the transaction with asynchronous signers, returning any errors.
+    ///
+    /// This is the asynchronous version of [`Transaction::try_sign`]. The
+    /// signatures are requested concurrently.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Transaction::try_sign`].
+    #[cfg(feature = "async")]
+    pub async fn try_sign_async<T: AsyncSigners + ?Sized>(
+        &mut self,
+        signers: &T,
+        recent_blockhash: Hash,
+    ) -> Result<(), SignerError> {
+        self.try_partial_sign_async(signers, recent_blockhash)
+            .await?;
+
+        if !self.is_signed() {
+            Err(SignerError::NotEnoughSigners)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Sign the transaction with a subset of required keys held by
+    /// asynchronous signers, returning any errors.
+    ///
+    /// This is the asynchronous version of [`Transaction::try_partial_sign`].
+    /// The signatures are requested concurrently.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Transaction::try_partial_sign`].
+    #[cfg(feature = "async")]
+    pub async fn try_partial_sign_async<T: AsyncSigners + ?Sized>(
+        &mut self,
+        signers: &T,
+        recent_blockhash: Hash,
+    ) -> Result<(), SignerError> {
+        let positions: Vec<usize> = self
+            .get_signing_keypair_positions(&signers.try_pubkeys().await?)?
+            .into_iter()
+            .collect::<Option<_>>()
+            .ok_or(SignerError::KeypairPubkeyMismatch)?;
+
+        if recent_blockhash != self.message.recent_blockhash {
+            self.message.recent_blockhash = recent_blockhash;
+            self.signatures
+                .iter_mut()
+                .for_each(|signature| *signature = Signature::default());
+        }
+
+        let signatures = signers.try_sign_message(&self.message_data()).await?;
+        for (position, signature) in positions.into_iter().zip(signatures) {
+            self.signatures[position] = signature;
+        }
+        Ok(())
+    }
+
     /// Returns a signature that is not valid for signing this transaction.
     pub fn get_invalid_signature() -> Signature {
         Signature::default()
@@ -1157,14 +1222,23 @@ mod tests {
 
     use {
         super::*,
-        alloc::{boxed::Box, vec},
+        alloc::{boxed::Box, collections::VecDeque, vec},
         bincode::{deserialize, serialize, serialized_size},
-        core::mem::size_of,
+        core::{cell::RefCell, mem::size_of, time::Duration},
+        futures::executor::block_on,
+        solana_derivation_path::DerivationPath,
         solana_instruction::AccountMeta,
         solana_keypair::Keypair,
         solana_presigner::Presigner,
         solana_sha256_hasher::hash,
-        solana_signer::Signer,
+        solana_signer::{
+            async_signer::{AsyncAdapter, AsyncSigner},
+            remote::{
+                encode_frames, FrameDecoder, RemoteSigner, RemoteTransport, INS_GET_PUBKEY,
+                INS_SIGN_MESSAGE, REMOTE_CHANNEL, STATUS_OK,
+            },
+            Signer,
+        },
         solana_system_interface::instruction as system_instruction,
     };
 
@@ -1467,6 +1541,50 @@ mod tests {
         assert!(tx.is_signed());
     }
 
+    #[test]
+    fn test_partial_sign_async() {
+        let keypair0 = Keypair::new();
+        let keypair1 = Keypair::new();
+        let keypair2 = Keypair::new();
+        let ix = Instruction::new_with_bincode(
+            Address::default(),
+            &0,
+            vec![
+                AccountMeta::new(keypair0.pubkey(), true),
+                AccountMeta::new(keypair1.pubkey(), true),
+                AccountMeta::new(keypair2.pubkey(), true),
+            ],
+        );
+        let message = Message::new(&[ix], Some(&keypair0.pubkey()));
+        let mut tx = Transaction::new_unsigned(message);
+
+        let signers = [AsyncAdapter(&keypair0), AsyncAdapter(&keypair2)];
+        block_on(tx.try_partial_sign_async(&signers, Hash::default())).unwrap();
+        assert!(!tx.is_signed());
+        assert_eq!(
+            block_on(tx.try_sign_async(&signers, Hash::default())),
+            Err(SignerError::NotEnoughSigners)
+        );
+        block_on(tx.try_partial_sign_async(&[AsyncAdapter(&keypair1)], Hash::default())).unwrap();
+        assert!(tx.is_signed());
+
+        let hash = hash(&[1]);
+        let signers: Vec<Box<dyn AsyncSigner>> = vec![
+            Box::new(AsyncAdapter(&keypair2)),
+            Box::new(AsyncAdapter(&keypair0)),
+            Box::new(AsyncAdapter(&keypair1)),
+        ];
+        block_on(tx.try_sign_async(&signers, hash)).unwrap();
+        let mut expected = tx.clone();
+        expected.sign(&[&keypair0, &keypair1, &keypair2], hash);
+        assert_eq!(tx, expected);
+
+        assert_eq!(
+            block_on(tx.try_partial_sign_async(&[AsyncAdapter(Keypair::new())], hash)),
+            Err(SignerError::KeypairPubkeyMismatch)
+        );
+    }
+
     #[test]
     #[should_panic]
     fn test_transaction_missing_keypair() {
@@ -1582,6 +1700,77 @@ mod tests {
         );
     }
 
+    /// A remote device that signs with a keypair, answering requests
+    /// in-process.
+    struct KeypairDevice {
+        keypair: Keypair,
+        decoder: RefCell<FrameDecoder>,
+        responses: RefCell<VecDeque<Vec<u8>>>,
+    }
+
+    impl RemoteTransport for KeypairDevice {
+        fn packet_size(&self) -> usize {
+            64
+        }
+
+        fn write_packet(&self, packet: &[u8]) -> Result<(), SignerError> {
+            let Some(request) = self.decoder.borrow_mut().push(packet)? else {
+                return Ok(());
+            };
+            // The instruction byte is followed by the derivation path, a
+            // depth byte and 4 bytes per level, then the message.
+            let message_start = usize::from(request[1]).saturating_mul(4).saturating_add(2);
+            let mut response = match request[0] {
+                INS_GET_PUBKEY => self.keypair.pubkey().to_bytes().to_vec(),
+                INS_SIGN_MESSAGE => {
+                    <[u8; 64]>::from(self.keypair.sign_message(&request[message_start..])).to_vec()
+                }
+                instruction => panic!("unexpected instruction {instruction}"),
+            };
+            response.extend_from_slice(&STATUS_OK.to_be_bytes());
+            let frames = encode_frames(REMOTE_CHANNEL, &response, self.packet_size())?;
+            self.responses.borrow_mut().extend(frames);
+            Ok(())
+        }
+
+        fn read_packet(&self, _timeout: Duration) -> Result<Option<Vec<u8>>, SignerError> {
+            Ok(self.responses.borrow_mut().pop_front())
+        }
+    }
+
+    #[test]
+    fn test_try_sign_remote_signer() {
+        let device_keypair = Keypair::new();
+        let device = KeypairDevice {
+            keypair: device_keypair.insecure_clone(),
+            decoder: RefCell::new(FrameDecoder::new(REMOTE_CHANNEL)),
+            responses: RefCell::new(VecDeque::new()),
+        };
+        let remote = RemoteSigner::new(Box::new(device), DerivationPath::default()).unwrap();
+        assert_eq!(remote.pubkey(), device_keypair.pubkey());
+        let keypair = Keypair::new();
+
+        let ix = Instruction::new_with_bincode(
+            Address::default(),
+            &0,
+            vec![
+                AccountMeta::new(keypair.pubkey(), true),
+                AccountMeta::new(remote.pubkey(), true),
+            ],
+        );
+        let message = Message::new(&[ix], Some(&keypair.pubkey()));
+        let mut tx = Transaction::new_unsigned(message);
+
+        let signers: [&dyn Signer; 2] = [&keypair, &remote];
+        assert_eq!(tx.try_sign(&signers, Hash::default()), Ok(()));
+        assert!(tx.is_signed());
+        assert_eq!(
+            tx.signatures[1],
+            device_keypair.sign_message(&tx.message_data())
+        );
+        tx.verify().expect("valid signatures");
+    }
+
     fn nonced_transfer_tx() -> (Address, Address, Transaction) {
         let from_keypair = Keypair::new();
         let from_pubkey = from_keypair.pubkey();
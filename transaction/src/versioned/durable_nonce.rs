@@ -0,0 +1,620 @@
+//! Construction and validation of durable nonce transactions.
+//!
+//! A durable nonce transaction uses the durable nonce stored in a nonce
+//! account as its recent blockhash instead of a recent blockhash, and must
+//! advance that nonce in its first instruction. The nonce authority has to
+//! sign the advance instruction.
+//!
+//! [`DurableNonceTransactionBuilder`] assembles such a transaction from the
+//! nonce account state and a list of instructions, in any message format, and
+//! [`verify_durable_nonce_transaction`] checks an existing transaction against
+//! the nonce account using the same rules as the runtime.
+
+use {
+    super::VersionedTransaction,
+    crate::{SignerError, Signers},
+    alloc::vec::Vec,
+    solana_account::AccountSharedData,
+    solana_address::Address,
+    solana_hash::Hash,
+    solana_instruction::Instruction,
+    solana_message::{
+        inline_nonce::is_advance_nonce_instruction_data,
+        legacy,
+        v0::{self, LoadedAddresses},
+        v1::{self, TransactionConfig},
+        AddressLookupTableAccount, CompileError, SanitizedMessage, SanitizedVersionedMessage,
+        SimpleAddressLoader, VersionedMessage,
+    },
+    solana_nonce::{
+        state::{Data, State},
+        versions::Versions,
+    },
+    solana_nonce_account::verify_nonce_account,
+    solana_sdk_ids::system_program,
+    solana_system_interface::instruction::advance_nonce_account,
+    std::collections::HashSet,
+};
+
+/// Errors produced when building or verifying a durable nonce transaction.
+#[derive(Debug, PartialEq, Eq)]
+pub enum DurableNonceError {
+    /// The nonce account uses the legacy nonce domain, which cannot be used
+    /// for durable transactions.
+    LegacyNonce,
+    /// The nonce account is not initialized.
+    UninitializedNonce,
+    /// An instruction already advances a nonce account.
+    DuplicateAdvanceNonce,
+    /// The message does not advance the nonce account in its first
+    /// instruction.
+    MissingAdvanceNonce,
+    /// The recent blockhash of the message is not the durable nonce of an
+    /// initialized nonce account owned by the system program.
+    InvalidDurableNonce,
+    /// The nonce authority does not sign the advance nonce instruction.
+    MissingNonceAuthoritySignature(Address),
+    /// An address lookup table used by the message was not provided.
+    LookupTableNotFound(Address),
+    /// An address lookup table index used by the message is out of bounds.
+    InvalidLookupTableIndex(Address),
+    /// The message failed sanitization.
+    InvalidMessage,
+    /// The message could not be compiled.
+    Compile(CompileError),
+    /// The transaction could not be signed.
+    Signer(SignerError),
+}
+
+impl core::fmt::Display for DurableNonceError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::LegacyNonce => write!(f, "legacy nonce accounts cannot be used"),
+            Self::UninitializedNonce => write!(f, "nonce account is not initialized"),
+            Self::DuplicateAdvanceNonce => {
+                write!(f, "instructions already advance a nonce account")
+            }
+            Self::MissingAdvanceNonce => {
+                write!(f, "first instruction does not advance the nonce account")
+            }
+            Self::InvalidDurableNonce => {
+                write!(f, "recent blockhash does not match the durable nonce")
+            }
+            Self::MissingNonceAuthoritySignature(authority) => {
+                write!(f, "missing signature for nonce authority {authority}")
+            }
+            Self::LookupTableNotFound(table) => {
+                write!(f, "address lookup table {table} not found")
+            }
+            Self::InvalidLookupTableIndex(table) => {
+                write!(f, "invalid index into address lookup table {table}")
+            }
+            Self::InvalidMessage => write!(f, "invalid message"),
+            Self::Compile(err) => write!(f, "failed to compile message: {err}"),
+            Self::Signer(err) => write!(f, "failed to sign transaction: {err}"),
+        }
+    }
+}
+
+impl core::error::Error for DurableNonceError {}
+
+impl From<CompileError> for DurableNonceError {
+    fn from(err: CompileError) -> Self {
+        Self::Compile(err)
+    }
+}
+
+impl From<SignerError> for DurableNonceError {
+    fn from(err: SignerError) -> Self {
+        Self::Signer(err)
+    }
+}
+
+/// Returns the nonce data of a nonce account that can be used for durable
+/// transactions.
+fn nonce_data(nonce_state: &Versions) -> Result<&Data, DurableNonceError> {
+    match nonce_state {
+        Versions::Legacy(_) => Err(DurableNonceError::LegacyNonce),
+        Versions::Current(state) => match state.as_ref() {
+            State::Uninitialized => Err(DurableNonceError::UninitializedNonce),
+            State::Initialized(data) => Ok(data),
+        },
+    }
+}
+
+/// Builds a signed durable nonce transaction.
+///
+/// The advance nonce instruction is inserted as the first instruction and the
+/// durable nonce is used as the recent blockhash.
+///
+/// # Examples
+///
+/// ```
+/// # use solana_hash::Hash;
+/// # use solana_keypair::Keypair;
+/// # use solana_nonce::{
+/// #     state::{Data, DurableNonce, State},
+/// #     versions::Versions,
+/// # };
+/// # use solana_signer::Signer;
+/// # use solana_system_interface::instruction::transfer;
+/// # use solana_transaction::versioned::durable_nonce::DurableNonceTransactionBuilder;
+/// let payer = Keypair::new();
+/// let nonce_address = solana_address::Address::new_unique();
+/// let nonce_state = Versions::new(State::Initialized(Data::new(
+///     payer.pubkey(),
+///     DurableNonce::from_blockhash(&Hash::new_unique()),
+///     5_000,
+/// )));
+///
+/// let builder = DurableNonceTransactionBuilder::new(payer.pubkey(), nonce_address, &nonce_state)?
+///     .instruction(transfer(&payer.pubkey(), &nonce_address, 1));
+/// let transaction = builder.build_legacy(&[&payer])?;
+/// assert_eq!(
+///     transaction.message.recent_blockhash(),
+///     &builder.durable_nonce()
+/// );
+/// # Ok::<(), solana_transaction::versioned::durable_nonce::DurableNonceError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DurableNonceTransactionBuilder {
+    payer: Address,
+    nonce_address: Address,
+    nonce_state: Versions,
+    instructions: Vec<Instruction>,
+}
+
+impl DurableNonceTransactionBuilder {
+    /// Creates a builder for transactions paid by `payer` that use the nonce
+    /// account at `nonce_address` with state `nonce_state`.
+    pub fn new(
+        payer: Address,
+        nonce_address: Address,
+        nonce_state: &Versions,
+    ) -> Result<Self, DurableNonceError> {
+        nonce_data(nonce_state)?;
+        Ok(Self {
+            payer,
+            nonce_address,
+            nonce_state: nonce_state.clone(),
+            instructions: Vec::new(),
+        })
+    }
+
+    /// Appends an instruction.
+    #[must_use]
+    pub fn instruction(mut self, instruction: Instruction) -> Self {
+        self.instructions.push(instruction);
+        self
+    }
+
+    /// Appends several instructions.
+    #[must_use]
+    pub fn instructions(mut self, instructions: impl IntoIterator<Item = Instruction>) -> Self {
+        self.instructions.extend(instructions);
+        self
+    }
+
+    /// Returns the authority that must sign the advance nonce instruction.
+    pub fn nonce_authority(&self) -> &Address {
+        &self.data().authority
+    }
+
+    /// Returns the durable nonce used as the recent blockhash.
+    pub fn durable_nonce(&self) -> Hash {
+        self.data().blockhash()
+    }
+
+    /// Builds and signs a legacy transaction.
+    pub fn build_legacy<T: Signers + ?Sized>(
+        &self,
+        signers: &T,
+    ) -> Result<VersionedTransaction, DurableNonceError> {
+        let instructions = self.compile_instructions()?;
+        // Without lookup tables, a v0 message has the same keys and
+        // instructions as a legacy one, and fails instead of panicking when
+        // they overflow.
+        let message =
+            v0::Message::try_compile(&self.payer, &instructions, &[], self.durable_nonce())?;
+        let message = legacy::Message::new_with_compiled_instructions(
+            message.header.num_required_signatures,
+            message.header.num_readonly_signed_accounts,
+            message.header.num_readonly_unsigned_accounts,
+            message.account_keys,
+            message.recent_blockhash,
+            message.instructions,
+        );
+        self.sign(VersionedMessage::Legacy(message), &[], signers)
+    }
+
+    /// Builds and signs a v0 transaction, loading accounts from the address
+    /// lookup tables where possible.
+    pub fn build_v0<T: Signers + ?Sized>(
+        &self,
+        address_lookup_table_accounts: &[AddressLookupTableAccount],
+        signers: &T,
+    ) -> Result<VersionedTransaction, DurableNonceError> {
+        let instructions = self.compile_instructions()?;
+        let message = v0::Message::try_compile(
+            &self.payer,
+            &instructions,
+            address_lookup_table_accounts,
+            self.durable_nonce(),
+        )?;
+        self.sign(
+            VersionedMessage::V0(message),
+            address_lookup_table_accounts,
+            signers,
+        )
+    }
+
+    /// Builds and signs a v1 transaction with the given transaction config.
+    pub fn build_v1<T: Signers + ?Sized>(
+        &self,
+        config: TransactionConfig,
+        signers: &T,
+    ) -> Result<VersionedTransaction, DurableNonceError> {
+        let instructions = self.compile_instructions()?;
+        let message = v1::Message::try_compile_with_config(
+            &self.payer,
+            &instructions,
+            self.durable_nonce(),
+            config,
+        )?;
+        self.sign(VersionedMessage::V1(message), &[], signers)
+    }
+
+    fn data(&self) -> &Data {
+        // Checked in `new`.
+        nonce_data(&self.nonce_state).expect("initialized nonce account")
+    }
+
+    fn compile_instructions(&self) -> Result<Vec<Instruction>, DurableNonceError> {
+        if self.instructions.iter().any(|instruction| {
+            system_program::check_id(&instruction.program_id)
+                && is_advance_nonce_instruction_data(&instruction.data)
+        }) {
+            return Err(DurableNonceError::DuplicateAdvanceNonce);
+        }
+        let mut instructions = Vec::with_capacity(self.instructions.len().saturating_add(1));
+        instructions.push(advance_nonce_account(
+            &self.nonce_address,
+            self.nonce_authority(),
+        ));
+        instructions.extend_from_slice(&self.instructions);
+        Ok(instructions)
+    }
+
+    fn sign<T: Signers + ?Sized>(
+        &self,
+        message: VersionedMessage,
+        address_lookup_table_accounts: &[AddressLookupTableAccount],
+        signers: &T,
+    ) -> Result<VersionedTransaction, DurableNonceError> {
+        let authority = self.nonce_authority();
+        if !signers.try_pubkeys()?.contains(authority) {
+            return Err(DurableNonceError::MissingNonceAuthoritySignature(
+                *authority,
+            ));
+        }
+        let nonce_account =
+            AccountSharedData::new_data(0, &self.nonce_state, &system_program::id())
+                .expect("nonce state serializes");
+        verify_durable_nonce_message(
+            &message,
+            &self.nonce_address,
+            &nonce_account,
+            address_lookup_table_accounts,
+        )?;
+        Ok(VersionedTransaction::try_new(message, signers)?)
+    }
+}
+
+/// Verifies that `transaction` is a valid durable nonce transaction for
+/// `nonce_account`, the account at `nonce_address`.
+///
+/// The message must advance the nonce account in its first instruction, and
+/// the nonce account must hold a durable nonce matching the recent blockhash
+/// of the message, as checked by [`verify_nonce_account`]. The nonce
+/// authority must sign the advance instruction. Address lookup tables
+/// referenced by a v0 message are resolved from
+/// `address_lookup_table_accounts`.
+///
+/// Signatures are not verified.
+pub fn verify_durable_nonce_transaction(
+    transaction: &VersionedTransaction,
+    nonce_address: &Address,
+    nonce_account: &AccountSharedData,
+    address_lookup_table_accounts: &[AddressLookupTableAccount],
+) -> Result<(), DurableNonceError> {
+    verify_durable_nonce_message(
+        &transaction.message,
+        nonce_address,
+        nonce_account,
+        address_lookup_table_accounts,
+    )
+}
+
+fn verify_durable_nonce_message(
+    message: &VersionedMessage,
+    nonce_address: &Address,
+    nonce_account: &AccountSharedData,
+    address_lookup_table_accounts: &[AddressLookupTableAccount],
+) -> Result<(), DurableNonceError> {
+    let loaded_addresses = match message {
+        VersionedMessage::V0(message) => load_addresses(
+            &message.address_table_lookups,
+            address_lookup_table_accounts,
+        )?,
+        _ => LoadedAddresses::default(),
+    };
+    let message = SanitizedVersionedMessage::try_new(message.clone())
+        .map_err(|_| DurableNonceError::InvalidMessage)?;
+    let message = SanitizedMessage::try_new(
+        message,
+        SimpleAddressLoader::Enabled(loaded_addresses),
+        &HashSet::new(),
+    )
+    .map_err(|_| DurableNonceError::InvalidMessage)?;
+
+    if message.get_durable_nonce() != Some(nonce_address) {
+        return Err(DurableNonceError::MissingAdvanceNonce);
+    }
+    let data = verify_nonce_account(nonce_account, message.recent_blockhash())
+        .ok_or(DurableNonceError::InvalidDurableNonce)?;
+    if !message
+        .get_ix_signers(0)
+        .any(|signer| signer == &data.authority)
+    {
+        return Err(DurableNonceError::MissingNonceAuthoritySignature(
+            data.authority,
+        ));
+    }
+    Ok(())
+}
+
+fn load_addresses(
+    address_table_lookups: &[v0::MessageAddressTableLookup],
+    address_lookup_table_accounts: &[AddressLookupTableAccount],
+) -> Result<LoadedAddresses, DurableNonceError> {
+    let mut loaded_addresses = LoadedAddresses::default();
+    for lookup in address_table_lookups {
+        let table = address_lookup_table_accounts
+            .iter()
+            .find(|table| table.key == lookup.account_key)
+            .ok_or(DurableNonceError::LookupTableNotFound(lookup.account_key))?;
+        for (indexes, addresses) in [
+            (&lookup.writable_indexes, &mut loaded_addresses.writable),
+            (&lookup.readonly_indexes, &mut loaded_addresses.readonly),
+        ] {
+            for index in indexes {
+                let address = table
+                    .addresses
+                    .get(usize::from(*index))
+                    .ok_or(DurableNonceError::InvalidLookupTableIndex(table.key))?;
+                addresses.push(*address);
+            }
+        }
+    }
+    Ok(loaded_addresses)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*, solana_account::WritableAccount, solana_keypair::Keypair,
+        solana_nonce::state::DurableNonce, solana_signer::Signer,
+        solana_system_interface::instruction::transfer,
+    };
+
+    fn nonce_state(authority: &Address) -> Versions {
+        Versions::new(State::Initialized(Data::new(
+            *authority,
+            DurableNonce::from_blockhash(&Hash::new_unique()),
+            5_000,
+        )))
+    }
+
+    fn nonce_account(nonce_state: &Versions) -> AccountSharedData {
+        AccountSharedData::new_data(1_000_000, nonce_state, &system_program::id()).unwrap()
+    }
+
+    fn assert_nonce_transaction(
+        transaction: &VersionedTransaction,
+        nonce_address: &Address,
+        nonce_state: &Versions,
+        address_lookup_table_accounts: &[AddressLookupTableAccount],
+    ) {
+        assert_eq!(
+            verify_durable_nonce_transaction(
+                transaction,
+                nonce_address,
+                &nonce_account(nonce_state),
+                address_lookup_table_accounts,
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            transaction.message.recent_blockhash(),
+            &nonce_data(nonce_state).unwrap().blockhash()
+        );
+        let instruction = &transaction.message.instructions()[0];
+        let keys = transaction.message.static_account_keys();
+        assert_eq!(
+            keys[usize::from(instruction.program_id_index)],
+            system_program::id()
+        );
+        assert!(is_advance_nonce_instruction_data(&instruction.data));
+        assert!(transaction.verify_with_results().iter().all(|ok| *ok));
+    }
+
+    #[test]
+    fn test_build_all_versions() {
+        let payer = Keypair::new();
+        let authority = Keypair::new();
+        let nonce_address = Address::new_unique();
+        let recipient = Address::new_unique();
+        let nonce_state = nonce_state(&authority.pubkey());
+        let builder =
+            DurableNonceTransactionBuilder::new(payer.pubkey(), nonce_address, &nonce_state)
+                .unwrap()
+                .instruction(transfer(&payer.pubkey(), &recipient, 42));
+
+        let transaction = builder.build_legacy(&[&payer, &authority]).unwrap();
+        assert!(matches!(transaction.message, VersionedMessage::Legacy(_)));
+        assert_nonce_transaction(&transaction, &nonce_address, &nonce_state, &[]);
+
+        let table = AddressLookupTableAccount {
+            key: Address::new_unique(),
+            addresses: alloc::vec![recipient],
+        };
+        let transaction = builder
+            .build_v0(core::slice::from_ref(&table), &[&payer, &authority])
+            .unwrap();
+        let VersionedMessage::V0(message) = &transaction.message else {
+            panic!("expected a v0 message");
+        };
+        assert_eq!(message.address_table_lookups.len(), 1);
+        assert_nonce_transaction(
+            &transaction,
+            &nonce_address,
+            &nonce_state,
+            core::slice::from_ref(&table),
+        );
+        assert_eq!(
+            verify_durable_nonce_transaction(
+                &transaction,
+                &nonce_address,
+                &nonce_account(&nonce_state),
+                &[]
+            ),
+            Err(DurableNonceError::LookupTableNotFound(table.key))
+        );
+
+        let transaction = builder
+            .build_v1(TransactionConfig::default(), &[&payer, &authority])
+            .unwrap();
+        assert!(matches!(transaction.message, VersionedMessage::V1(_)));
+        assert_nonce_transaction(&transaction, &nonce_address, &nonce_state, &[]);
+    }
+
+    #[test]
+    fn test_payer_is_authority() {
+        let payer = Keypair::new();
+        let nonce_address = Address::new_unique();
+        let nonce_state = nonce_state(&payer.pubkey());
+        let transaction =
+            DurableNonceTransactionBuilder::new(payer.pubkey(), nonce_address, &nonce_state)
+                .unwrap()
+                .build_legacy(&[&payer])
+                .unwrap();
+        assert_eq!(transaction.signatures.len(), 1);
+        assert_nonce_transaction(&transaction, &nonce_address, &nonce_state, &[]);
+    }
+
+    #[test]
+    fn test_invalid_nonce_state() {
+        let payer = Address::new_unique();
+        let nonce_address = Address::new_unique();
+        assert_eq!(
+            DurableNonceTransactionBuilder::new(
+                payer,
+                nonce_address,
+                &Versions::new(State::Uninitialized)
+            ),
+            Err(DurableNonceError::UninitializedNonce)
+        );
+        let Versions::Current(state) = nonce_state(&payer) else {
+            unreachable!();
+        };
+        assert_eq!(
+            DurableNonceTransactionBuilder::new(payer, nonce_address, &Versions::Legacy(state)),
+            Err(DurableNonceError::LegacyNonce)
+        );
+    }
+
+    #[test]
+    fn test_missing_authority_signature() {
+        let payer = Keypair::new();
+        let authority = Address::new_unique();
+        let nonce_address = Address::new_unique();
+        let nonce_state = nonce_state(&authority);
+        let builder =
+            DurableNonceTransactionBuilder::new(payer.pubkey(), nonce_address, &nonce_state)
+                .unwrap();
+        assert_eq!(
+            builder.build_legacy(&[&payer]),
+            Err(DurableNonceError::MissingNonceAuthoritySignature(authority))
+        );
+    }
+
+    #[test]
+    fn test_duplicate_advance_nonce() {
+        let payer = Keypair::new();
+        let nonce_address = Address::new_unique();
+        let nonce_state = nonce_state(&payer.pubkey());
+        let builder =
+            DurableNonceTransactionBuilder::new(payer.pubkey(), nonce_address, &nonce_state)
+                .unwrap()
+                .instruction(advance_nonce_account(&nonce_address, &payer.pubkey()));
+        assert_eq!(
+            builder.build_legacy(&[&payer]),
+            Err(DurableNonceError::DuplicateAdvanceNonce)
+        );
+    }
+
+    #[test]
+    fn test_verify_rejects_mismatched_nonce() {
+        let payer = Keypair::new();
+        let nonce_address = Address::new_unique();
+        let nonce_state = nonce_state(&payer.pubkey());
+        let transaction =
+            DurableNonceTransactionBuilder::new(payer.pubkey(), nonce_address, &nonce_state)
+                .unwrap()
+                .build_v1(TransactionConfig::default(), &[&payer])
+                .unwrap();
+
+        assert_eq!(
+            verify_durable_nonce_transaction(
+                &transaction,
+                &Address::new_unique(),
+                &nonce_account(&nonce_state),
+                &[]
+            ),
+            Err(DurableNonceError::MissingAdvanceNonce)
+        );
+        assert_eq!(
+            verify_durable_nonce_transaction(
+                &transaction,
+                &nonce_address,
+                &nonce_account(&self::nonce_state(&payer.pubkey())),
+                &[]
+            ),
+            Err(DurableNonceError::InvalidDurableNonce)
+        );
+        let mut not_system_owned = nonce_account(&nonce_state);
+        not_system_owned.set_owner(Address::new_unique());
+        assert_eq!(
+            verify_durable_nonce_transaction(&transaction, &nonce_address, &not_system_owned, &[]),
+            Err(DurableNonceError::InvalidDurableNonce)
+        );
+        let other_authority = Address::new_unique();
+        let Versions::Current(mut state) = nonce_state.clone() else {
+            unreachable!();
+        };
+        if let State::Initialized(data) = state.as_mut() {
+            data.authority = other_authority;
+        }
+        assert_eq!(
+            verify_durable_nonce_transaction(
+                &transaction,
+                &nonce_address,
+                &nonce_account(&Versions::Current(state)),
+                &[]
+            ),
+            Err(DurableNonceError::MissingNonceAuthoritySignature(
+                other_authority
+            ))
+        );
+    }
+}
@@ -2,6 +2,8 @@
 
 #[cfg(feature = "frozen-abi")]
 use solana_frozen_abi_macro::{frozen_abi, AbiExample, StableAbi};
+#[cfg(feature = "async")]
+use solana_signer::async_signer::AsyncSigners;
 use {
     crate::Transaction,
     alloc::vec::Vec,
@@ -14,6 +16,7 @@ use {
 #[cfg(feature = "wincode")]
 use {
     alloc::string::ToString,
+    solana_address::Address,
     solana_signer::{signers::Signers, SignerError},
 };
 #[cfg(feature = "wincode")]
@@ -35,6 +38,10 @@ use {
     solana_short_vec as short_vec,
 };
 
+#[cfg(feature = "nonce")]
+pub mod durable_nonce;
+#[cfg(feature = "verify")]
+pub mod partially_signed;
 pub mod sanitized;
 
 /// Type that serializes to the string "legacy"
@@ -195,12 +202,37 @@ impl VersionedTransaction {
         message: VersionedMessage,
         keypairs: &T,
     ) -> Result<Self, SignerError> {
+        let signature_indexes = Self::signature_indexes(&message, &keypairs.try_pubkeys()?)?;
+        let unordered_signatures = keypairs.try_sign_message(&message.serialize())?;
+        Self::from_unordered_signatures(message, signature_indexes, unordered_signatures)
+    }
+
+    /// Signs a versioned message with asynchronous signers and if successful,
+    /// returns a signed transaction.
+    ///
+    /// The signatures are requested concurrently.
+    #[cfg(feature = "async")]
+    pub async fn try_new_async<T: AsyncSigners + ?Sized>(
+        message: VersionedMessage,
+        signers: &T,
+    ) -> Result<Self, SignerError> {
+        let signature_indexes = Self::signature_indexes(&message, &signers.try_pubkeys().await?)?;
+        let unordered_signatures = signers.try_sign_message(&message.serialize()).await?;
+        Self::from_unordered_signatures(message, signature_indexes, unordered_signatures)
+    }
+
+    /// Returns the position in `signer_keys` of each signer required by the
+    /// message.
+    #[cfg(feature = "wincode")]
+    fn signature_indexes(
+        message: &VersionedMessage,
+        signer_keys: &[Address],
+    ) -> Result<Vec<usize>, SignerError> {
         let static_account_keys = message.static_account_keys();
         if static_account_keys.len() < message.header().num_required_signatures as usize {
             return Err(SignerError::InvalidInput("invalid message".to_string()));
         }
 
-        let signer_keys = keypairs.try_pubkeys()?;
         let expected_signer_keys =
             &static_account_keys[0..message.header().num_required_signatures as usize];
 
@@ -210,8 +242,7 @@ impl VersionedTransaction {
             Ordering::Equal => Ok(()),
         }?;
 
-        let message_data = message.serialize();
-        let signature_indexes: Vec<usize> = expected_signer_keys
+        expected_signer_keys
             .iter()
             .map(|signer_key| {
                 signer_keys
@@ -219,9 +250,15 @@ impl VersionedTransaction {
                     .position(|key| key == signer_key)
                     .ok_or(SignerError::KeypairPubkeyMismatch)
             })
-            .collect::<Result<_, SignerError>>()?;
+            .collect()
+    }
 
-        let unordered_signatures = keypairs.try_sign_message(&message_data)?;
+    #[cfg(feature = "wincode")]
+    fn from_unordered_signatures(
+        message: VersionedMessage,
+        signature_indexes: Vec<usize>,
+        unordered_signatures: Vec<Signature>,
+    ) -> Result<Self, SignerError> {
         let signatures: Vec<Signature> = signature_indexes
             .into_iter()
             .map(|index| {
@@ -467,6 +504,7 @@ mod tests {
     use {
         super::*,
         alloc::vec,
+        futures::executor::block_on,
         solana_address::{Address, ADDRESS_BYTES},
         solana_hash::Hash,
         solana_instruction::{AccountMeta, Instruction},
@@ -481,7 +519,10 @@ mod tests {
             Message as LegacyMessage, MessageHeader,
         },
         solana_pubkey::Pubkey,
-        solana_signer::Signer,
+        solana_signer::{
+            async_signer::{AsyncAdapter, AsyncSigner},
+            Signer,
+        },
         solana_system_interface::instruction as system_instruction,
         test_case::test_case,
     };
@@ -530,6 +571,41 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_try_new_async() {
+        let keypair0 = Keypair::new();
+        let keypair1 = Keypair::new();
+
+        let message = VersionedMessage::Legacy(LegacyMessage::new(
+            &[Instruction::new_with_bytes(
+                Pubkey::new_unique(),
+                &[],
+                vec![AccountMeta::new_readonly(keypair1.pubkey(), true)],
+            )],
+            Some(&keypair0.pubkey()),
+        ));
+
+        assert_eq!(
+            block_on(VersionedTransaction::try_new_async(
+                message.clone(),
+                &[AsyncAdapter(&keypair0)]
+            )),
+            Err(SignerError::NotEnoughSigners)
+        );
+
+        let signers: [&dyn AsyncSigner; 2] = [&AsyncAdapter(&keypair1), &AsyncAdapter(&keypair0)];
+        let tx = block_on(VersionedTransaction::try_new_async(
+            message.clone(),
+            &signers,
+        ))
+        .unwrap();
+        assert_eq!(tx.verify_with_results(), vec![true; 2]);
+        assert_eq!(
+            Ok(tx),
+            VersionedTransaction::try_new(message, &[&keypair0, &keypair1])
+        );
+    }
+
     fn nonced_transfer_tx() -> (Pubkey, Pubkey, VersionedTransaction) {
         let from_keypair = Keypair::new();
         let from_pubkey = from_keypair.pubkey();
@@ -0,0 +1,536 @@
+//! A portable container for partially signed transactions.
+//!
+//! A [`PartiallySignedTransaction`] wraps a [`VersionedTransaction`] whose
+//! signatures may not all be present yet. It can be serialized and passed
+//! between machines, each adding the signatures of the keys it holds, and the
+//! copies can be merged back together. Every signature is verified against the
+//! message bytes before it is accepted.
+//!
+//! The serialized format is the four magic bytes `PSTX`, a format version
+//! byte, and the wire format of the transaction, with missing signatures set
+//! to [`Signature::default()`].
+
+use {
+    super::VersionedTransaction,
+    crate::{SignerError, Signers},
+    alloc::{string::ToString, vec::Vec},
+    solana_address::Address,
+    solana_message::VersionedMessage,
+    solana_signature::Signature,
+};
+
+/// Magic bytes at the start of a serialized [`PartiallySignedTransaction`].
+pub const PARTIALLY_SIGNED_TRANSACTION_MAGIC: [u8; 4] = *b"PSTX";
+
+/// Current version of the serialized [`PartiallySignedTransaction`] format.
+pub const PARTIALLY_SIGNED_TRANSACTION_VERSION: u8 = 1;
+
+/// Errors produced by [`PartiallySignedTransaction`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PartiallySignedTransactionError {
+    /// The serialized bytes do not start with the expected magic bytes.
+    InvalidFormat,
+    /// The serialized format version is not supported.
+    UnsupportedVersion(u8),
+    /// The transaction could not be encoded or decoded, or has an invalid
+    /// number of signatures.
+    InvalidTransaction,
+    /// A signature does not verify against the message and its signer.
+    InvalidSignature(Address),
+    /// The address is not a required signer of the message.
+    UnknownSigner(Address),
+    /// The transactions being merged have different messages.
+    MessageMismatch,
+    /// The transaction is missing the signatures of these signers.
+    MissingSignatures(Vec<Address>),
+}
+
+impl core::fmt::Display for PartiallySignedTransactionError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidFormat => write!(f, "invalid partially signed transaction format"),
+            Self::UnsupportedVersion(version) => {
+                write!(
+                    f,
+                    "unsupported partially signed transaction version {version}"
+                )
+            }
+            Self::InvalidTransaction => write!(f, "invalid transaction"),
+            Self::InvalidSignature(signer) => write!(f, "invalid signature for signer {signer}"),
+            Self::UnknownSigner(signer) => write!(f, "{signer} is not a required signer"),
+            Self::MessageMismatch => write!(f, "transaction messages do not match"),
+            Self::MissingSignatures(signers) => {
+                write!(f, "missing signatures for {} signers", signers.len())
+            }
+        }
+    }
+}
+
+impl core::error::Error for PartiallySignedTransactionError {}
+
+/// A transaction that may be missing some of its required signatures.
+///
+/// Signatures that are present are always valid for the message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartiallySignedTransaction {
+    transaction: VersionedTransaction,
+}
+
+impl PartiallySignedTransaction {
+    /// Creates an unsigned transaction for a message.
+    pub fn new(message: VersionedMessage) -> Result<Self, PartiallySignedTransactionError> {
+        let num_required_signatures = usize::from(message.header().num_required_signatures);
+        Self::try_from(VersionedTransaction {
+            signatures: alloc::vec![Signature::default(); num_required_signatures],
+            message,
+        })
+    }
+
+    /// Returns the wrapped transaction.
+    pub fn transaction(&self) -> &VersionedTransaction {
+        &self.transaction
+    }
+
+    /// Returns the transaction message.
+    pub fn message(&self) -> &VersionedMessage {
+        &self.transaction.message
+    }
+
+    /// Returns the signers that are required by the message, in signature
+    /// order.
+    pub fn required_signers(&self) -> &[Address] {
+        let num_required_signatures = self.transaction.signatures.len();
+        &self.transaction.message.static_account_keys()[..num_required_signatures]
+    }
+
+    /// Returns the required signers whose signatures are still missing.
+    pub fn missing_signers(&self) -> Vec<Address> {
+        self.required_signers()
+            .iter()
+            .zip(&self.transaction.signatures)
+            .filter(|(_, signature)| **signature == Signature::default())
+            .map(|(signer, _)| *signer)
+            .collect()
+    }
+
+    /// Returns true if all required signatures are present.
+    pub fn is_fully_signed(&self) -> bool {
+        !self
+            .transaction
+            .signatures
+            .iter()
+            .any(|signature| *signature == Signature::default())
+    }
+
+    /// Signs the message with a subset of the required signers.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SignerError::KeypairPubkeyMismatch`] if any of the signers is
+    /// not a required signer of the message, [`SignerError::Custom`] with the
+    /// [`PartiallySignedTransactionError::InvalidSignature`] message if a
+    /// signer returns a signature that does not verify, or any error returned
+    /// by the signers. No signature is added if any of them fails.
+    pub fn try_sign<T: Signers + ?Sized>(&mut self, signers: &T) -> Result<(), SignerError> {
+        let positions = signers
+            .try_pubkeys()?
+            .iter()
+            .map(|pubkey| {
+                self.required_signers()
+                    .iter()
+                    .position(|signer| signer == pubkey)
+                    .ok_or(SignerError::KeypairPubkeyMismatch)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let message_bytes = self.transaction.message.serialize();
+        let signatures = signers.try_sign_message(&message_bytes)?;
+        // Remote or faulty signers can return signatures that do not match,
+        // so check them like any other signature before they are added.
+        for (position, signature) in positions.iter().zip(&signatures) {
+            let signer = &self.required_signers()[*position];
+            if !signature.verify(signer.as_ref(), &message_bytes) {
+                return Err(SignerError::Custom(
+                    PartiallySignedTransactionError::InvalidSignature(*signer).to_string(),
+                ));
+            }
+        }
+        for (position, signature) in positions.into_iter().zip(signatures) {
+            self.transaction.signatures[position] = signature;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the signature of a required signer, e.g. one produced on another
+    /// machine, after verifying it against the message.
+    pub fn add_signature(
+        &mut self,
+        signer: &Address,
+        signature: Signature,
+    ) -> Result<(), PartiallySignedTransactionError> {
+        let position = self
+            .required_signers()
+            .iter()
+            .position(|required_signer| required_signer == signer)
+            .ok_or(PartiallySignedTransactionError::UnknownSigner(*signer))?;
+
+        if !signature.verify(signer.as_ref(), &self.transaction.message.serialize()) {
+            return Err(PartiallySignedTransactionError::InvalidSignature(*signer));
+        }
+        self.transaction.signatures[position] = signature;
+
+        Ok(())
+    }
+
+    /// Merges the signatures of another copy of the same transaction.
+    ///
+    /// Signatures missing from `self` are taken from `other`. Nothing is
+    /// merged if the messages differ or any of the signatures of `other` is
+    /// invalid.
+    pub fn merge(&mut self, other: &Self) -> Result<(), PartiallySignedTransactionError> {
+        let message_bytes = self.transaction.message.serialize();
+        if message_bytes != other.transaction.message.serialize() {
+            return Err(PartiallySignedTransactionError::MessageMismatch);
+        }
+        verify_signatures(&other.transaction, &message_bytes)?;
+
+        for (signature, other_signature) in self
+            .transaction
+            .signatures
+            .iter_mut()
+            .zip(&other.transaction.signatures)
+        {
+            if *signature == Signature::default() {
+                *signature = *other_signature;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the transaction if it is fully signed.
+    pub fn into_transaction(self) -> Result<VersionedTransaction, PartiallySignedTransactionError> {
+        if self.is_fully_signed() {
+            Ok(self.transaction)
+        } else {
+            Err(PartiallySignedTransactionError::MissingSignatures(
+                self.missing_signers(),
+            ))
+        }
+    }
+
+    /// Serializes the partially signed transaction.
+    pub fn serialize(&self) -> Result<Vec<u8>, PartiallySignedTransactionError> {
+        let mut bytes = Vec::from(PARTIALLY_SIGNED_TRANSACTION_MAGIC);
+        bytes.push(PARTIALLY_SIGNED_TRANSACTION_VERSION);
+        wincode::serialize_into(&mut bytes, &self.transaction)
+            .map_err(|_| PartiallySignedTransactionError::InvalidTransaction)?;
+        Ok(bytes)
+    }
+
+    /// Deserializes a partially signed transaction, verifying the signatures
+    /// that are present.
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, PartiallySignedTransactionError> {
+        let bytes = bytes
+            .strip_prefix(&PARTIALLY_SIGNED_TRANSACTION_MAGIC)
+            .ok_or(PartiallySignedTransactionError::InvalidFormat)?;
+        let (version, bytes) = bytes
+            .split_first()
+            .ok_or(PartiallySignedTransactionError::InvalidFormat)?;
+        if *version != PARTIALLY_SIGNED_TRANSACTION_VERSION {
+            return Err(PartiallySignedTransactionError::UnsupportedVersion(
+                *version,
+            ));
+        }
+
+        let transaction: VersionedTransaction = wincode::deserialize(bytes)
+            .map_err(|_| PartiallySignedTransactionError::InvalidTransaction)?;
+        Self::try_from(transaction)
+    }
+}
+
+impl TryFrom<VersionedTransaction> for PartiallySignedTransaction {
+    type Error = PartiallySignedTransactionError;
+
+    /// Wraps a transaction, verifying the signatures that are present.
+    fn try_from(transaction: VersionedTransaction) -> Result<Self, Self::Error> {
+        transaction
+            .sanitize()
+            .map_err(|_| PartiallySignedTransactionError::InvalidTransaction)?;
+        verify_signatures(&transaction, &transaction.message.serialize())?;
+
+        Ok(Self { transaction })
+    }
+}
+
+/// Verifies the signatures of a transaction that are present.
+fn verify_signatures(
+    transaction: &VersionedTransaction,
+    message_bytes: &[u8],
+) -> Result<(), PartiallySignedTransactionError> {
+    transaction
+        .signatures
+        .iter()
+        .zip(transaction.message.static_account_keys())
+        .filter(|(signature, _)| **signature != Signature::default())
+        .try_for_each(|(signature, signer)| {
+            if signature.verify(signer.as_ref(), message_bytes) {
+                Ok(())
+            } else {
+                Err(PartiallySignedTransactionError::InvalidSignature(*signer))
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        alloc::vec,
+        solana_hash::Hash,
+        solana_instruction::{AccountMeta, Instruction},
+        solana_keypair::Keypair,
+        solana_message::{v0, Message},
+        solana_signer::Signer,
+    };
+
+    fn message(signers: &[&Keypair]) -> VersionedMessage {
+        let instruction = Instruction::new_with_bytes(
+            Address::new_unique(),
+            &[1, 2, 3],
+            signers
+                .iter()
+                .map(|signer| AccountMeta::new(signer.pubkey(), true))
+                .collect(),
+        );
+        VersionedMessage::V0(
+            v0::Message::try_compile(
+                &signers[0].pubkey(),
+                &[instruction],
+                &[],
+                Hash::new_unique(),
+            )
+            .unwrap(),
+        )
+    }
+
+    fn sorted(mut addresses: Vec<Address>) -> Vec<Address> {
+        addresses.sort();
+        addresses
+    }
+
+    #[test]
+    fn test_offline_signing_workflow() {
+        let (payer, alice, bob) = (Keypair::new(), Keypair::new(), Keypair::new());
+        let unsigned = PartiallySignedTransaction::new(message(&[&payer, &alice, &bob])).unwrap();
+        assert_eq!(unsigned.missing_signers(), unsigned.required_signers());
+        assert_eq!(
+            sorted(unsigned.missing_signers()),
+            sorted(vec![payer.pubkey(), alice.pubkey(), bob.pubkey()])
+        );
+        let serialized = unsigned.serialize().unwrap();
+
+        // each air-gapped machine signs its own copy
+        let sign_copy = |signer: &Keypair| {
+            let mut copy = PartiallySignedTransaction::deserialize(&serialized).unwrap();
+            copy.try_sign(&[signer]).unwrap();
+            copy.serialize().unwrap()
+        };
+        let payer_copy = sign_copy(&payer);
+        let alice_copy = sign_copy(&alice);
+        let bob_copy = sign_copy(&bob);
+
+        let mut transaction = PartiallySignedTransaction::deserialize(&payer_copy).unwrap();
+        assert_eq!(
+            sorted(transaction.missing_signers()),
+            sorted(vec![alice.pubkey(), bob.pubkey()])
+        );
+
+        transaction
+            .merge(&PartiallySignedTransaction::deserialize(&alice_copy).unwrap())
+            .unwrap();
+        assert_eq!(transaction.missing_signers(), vec![bob.pubkey()]);
+        assert_eq!(
+            transaction.clone().into_transaction(),
+            Err(PartiallySignedTransactionError::MissingSignatures(vec![
+                bob.pubkey()
+            ]))
+        );
+
+        transaction
+            .merge(&PartiallySignedTransaction::deserialize(&bob_copy).unwrap())
+            .unwrap();
+        assert!(transaction.is_fully_signed());
+
+        let transaction = transaction.into_transaction().unwrap();
+        assert!(transaction.verify_with_results().into_iter().all(|ok| ok));
+        assert_eq!(
+            transaction,
+            VersionedTransaction::try_new(unsigned.message().clone(), &[&payer, &alice, &bob])
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_merge_rejects_different_messages() {
+        let payer = Keypair::new();
+        let mut transaction = PartiallySignedTransaction::new(message(&[&payer])).unwrap();
+        let mut other = PartiallySignedTransaction::new(message(&[&payer])).unwrap();
+        other.try_sign(&[&payer]).unwrap();
+
+        assert_eq!(
+            transaction.merge(&other),
+            Err(PartiallySignedTransactionError::MessageMismatch)
+        );
+        assert_eq!(transaction.missing_signers(), vec![payer.pubkey()]);
+    }
+
+    #[test]
+    fn test_merge_rejects_invalid_signatures() {
+        let (payer, alice) = (Keypair::new(), Keypair::new());
+        let message = message(&[&payer, &alice]);
+        let mut transaction = PartiallySignedTransaction::new(message.clone()).unwrap();
+
+        // a copy with a valid signature for the payer and a forged one for alice
+        let mut other = PartiallySignedTransaction::new(message.clone()).unwrap();
+        other.try_sign(&[&payer]).unwrap();
+        let alice_position = other
+            .required_signers()
+            .iter()
+            .position(|signer| *signer == alice.pubkey())
+            .unwrap();
+        other.transaction.signatures[alice_position] = alice.sign_message(b"something else");
+
+        assert_eq!(
+            transaction.merge(&other),
+            Err(PartiallySignedTransactionError::InvalidSignature(
+                alice.pubkey()
+            ))
+        );
+        // nothing was merged
+        assert_eq!(
+            sorted(transaction.missing_signers()),
+            sorted(vec![payer.pubkey(), alice.pubkey()])
+        );
+
+        // forged signatures are also rejected when deserializing
+        let mut bytes = Vec::from(PARTIALLY_SIGNED_TRANSACTION_MAGIC);
+        bytes.push(PARTIALLY_SIGNED_TRANSACTION_VERSION);
+        bytes.extend(wincode::serialize(&other.transaction).unwrap());
+        assert_eq!(
+            PartiallySignedTransaction::deserialize(&bytes),
+            Err(PartiallySignedTransactionError::InvalidSignature(
+                alice.pubkey()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_add_signature() {
+        let (payer, alice) = (Keypair::new(), Keypair::new());
+        let mut transaction = PartiallySignedTransaction::new(message(&[&payer, &alice])).unwrap();
+        let message_bytes = transaction.message().serialize();
+
+        assert_eq!(
+            transaction.add_signature(&payer.pubkey(), alice.sign_message(&message_bytes)),
+            Err(PartiallySignedTransactionError::InvalidSignature(
+                payer.pubkey()
+            ))
+        );
+        let unknown = Keypair::new();
+        assert_eq!(
+            transaction.add_signature(&unknown.pubkey(), unknown.sign_message(&message_bytes)),
+            Err(PartiallySignedTransactionError::UnknownSigner(
+                unknown.pubkey()
+            ))
+        );
+
+        transaction
+            .add_signature(&alice.pubkey(), alice.sign_message(&message_bytes))
+            .unwrap();
+        assert_eq!(transaction.missing_signers(), vec![payer.pubkey()]);
+    }
+
+    #[test]
+    fn test_try_sign_unknown_signer() {
+        let payer = Keypair::new();
+        let mut transaction = PartiallySignedTransaction::new(message(&[&payer])).unwrap();
+
+        assert_eq!(
+            transaction.try_sign(&[&Keypair::new()]),
+            Err(SignerError::KeypairPubkeyMismatch)
+        );
+    }
+
+    #[test]
+    fn test_try_sign_rejects_invalid_signatures() {
+        // a signer that returns a signature for another message
+        struct FaultySigner(Keypair);
+
+        impl Signer for FaultySigner {
+            fn try_pubkey(&self) -> Result<Address, SignerError> {
+                Ok(self.0.pubkey())
+            }
+
+            fn try_sign_message(&self, _message: &[u8]) -> Result<Signature, SignerError> {
+                Ok(self.0.sign_message(b"something else"))
+            }
+
+            fn is_interactive(&self) -> bool {
+                false
+            }
+        }
+
+        let (payer, alice) = (Keypair::new(), Keypair::new());
+        let mut transaction = PartiallySignedTransaction::new(message(&[&payer, &alice])).unwrap();
+        let faulty = FaultySigner(alice.insecure_clone());
+        assert_eq!(
+            transaction.try_sign(&[&payer as &dyn Signer, &faulty]),
+            Err(SignerError::Custom(
+                PartiallySignedTransactionError::InvalidSignature(alice.pubkey()).to_string()
+            ))
+        );
+        // nothing was signed
+        assert_eq!(
+            sorted(transaction.missing_signers()),
+            sorted(vec![payer.pubkey(), alice.pubkey()])
+        );
+    }
+
+    #[test]
+    fn test_deserialize_errors() {
+        let payer = Keypair::new();
+        let transaction = PartiallySignedTransaction::new(VersionedMessage::Legacy(Message::new(
+            &[],
+            Some(&payer.pubkey()),
+        )))
+        .unwrap();
+        let bytes = transaction.serialize().unwrap();
+
+        assert_eq!(
+            PartiallySignedTransaction::deserialize(&bytes),
+            Ok(transaction)
+        );
+        assert_eq!(
+            PartiallySignedTransaction::deserialize(&bytes[1..]),
+            Err(PartiallySignedTransactionError::InvalidFormat)
+        );
+        assert_eq!(
+            PartiallySignedTransaction::deserialize(&bytes[..4]),
+            Err(PartiallySignedTransactionError::InvalidFormat)
+        );
+
+        let mut unsupported = bytes.clone();
+        unsupported[4] = 2;
+        assert_eq!(
+            PartiallySignedTransaction::deserialize(&unsupported),
+            Err(PartiallySignedTransactionError::UnsupportedVersion(2))
+        );
+
+        assert_eq!(
+            PartiallySignedTransaction::deserialize(&bytes[..bytes.len() - 1]),
+            Err(PartiallySignedTransactionError::InvalidTransaction)
+        );
+    }
+}
@@ -0,0 +1,145 @@
+//! Derive macro for `solana_zero_copy::ZeroCopy`.
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+extern crate proc_macro;
+
+use {
+    proc_macro::TokenStream,
+    proc_macro2::TokenStream as TokenStream2,
+    quote::quote,
+    syn::{
+        parenthesized, parse_macro_input, token::Paren, Data, DeriveInput, Error, Expr, Fields,
+        Result,
+    },
+};
+
+/// Derives `solana_zero_copy::ZeroCopy` for a struct.
+///
+/// The struct must be `#[repr(C)]` or `#[repr(transparent)]`, have no generic
+/// parameters, and only contain fields that implement `ZeroCopy`. The derive
+/// asserts at compile time that the struct has an alignment of `1` and no
+/// padding.
+///
+/// An optional discriminator, checked by the generated loaders, precedes the
+/// struct in account data:
+///
+/// ```ignore
+/// #[derive(ZeroCopy)]
+/// #[zero_copy(discriminator = [1])]
+/// #[repr(C)]
+/// pub struct Counter {
+///     pub authority: Address,
+///     pub count: U64,
+/// }
+/// ```
+#[proc_macro_derive(ZeroCopy, attributes(zero_copy))]
+pub fn derive_zero_copy(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream2> {
+    let name = &input.ident;
+
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "ZeroCopy can only be derived for structs",
+        ));
+    };
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "ZeroCopy cannot be derived for generic structs",
+        ));
+    }
+    check_repr(&input)?;
+    let discriminator = parse_discriminator(&input)?;
+
+    let field_types: Vec<_> = match &data.fields {
+        Fields::Named(fields) => fields.named.iter().map(|field| &field.ty).collect(),
+        Fields::Unnamed(fields) => fields.unnamed.iter().map(|field| &field.ty).collect(),
+        Fields::Unit => Vec::new(),
+    };
+    let align_message = format!("`{name}` must have an alignment of 1");
+    let padding_message = format!("`{name}` must not have padding");
+    let discriminator = discriminator.map(|discriminator| {
+        quote! {
+            const DISCRIMINATOR: &'static [u8] = &#discriminator;
+        }
+    });
+
+    Ok(quote! {
+        const _: () = {
+            const fn assert_zero_copy<T: ::solana_zero_copy::ZeroCopy>() {}
+            #( assert_zero_copy::<#field_types>(); )*
+
+            assert!(::core::mem::align_of::<#name>() == 1, #align_message);
+            assert!(
+                ::core::mem::size_of::<#name>()
+                    == 0 #( + ::core::mem::size_of::<#field_types>() )*,
+                #padding_message
+            );
+        };
+
+        unsafe impl ::solana_zero_copy::ZeroCopy for #name {
+            #discriminator
+        }
+    })
+}
+
+/// Checks that the struct has a stable layout.
+fn check_repr(input: &DeriveInput) -> Result<()> {
+    let mut stable = false;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("repr"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("C") || meta.path.is_ident("transparent") {
+                stable = true;
+            } else if meta.input.peek(Paren) {
+                // Skip arguments such as `align(N)`, which the alignment
+                // assertion rejects.
+                let content;
+                parenthesized!(content in meta.input);
+                content.parse::<TokenStream2>()?;
+            }
+            Ok(())
+        })?;
+    }
+    if stable {
+        Ok(())
+    } else {
+        Err(Error::new_spanned(
+            &input.ident,
+            "ZeroCopy requires `#[repr(C)]` or `#[repr(transparent)]`",
+        ))
+    }
+}
+
+/// Parses `#[zero_copy(discriminator = ...)]`.
+fn parse_discriminator(input: &DeriveInput) -> Result<Option<Expr>> {
+    let mut discriminator = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("zero_copy"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("discriminator") {
+                if discriminator.is_some() {
+                    return Err(meta.error("duplicate discriminator"));
+                }
+                discriminator = Some(meta.value()?.parse::<Expr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported zero_copy attribute"))
+            }
+        })?;
+    }
+    Ok(discriminator)
+}
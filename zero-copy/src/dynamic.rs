@@ -0,0 +1,627 @@
+//! Length-prefixed views for dynamic-length account data.
+//!
+//! [`List`] and [`Str`] read a length prefix followed by that many items or
+//! UTF-8 bytes, and [`ListMut`] and [`StrMut`] modify them in place, growing
+//! into the rest of the byte slice they were created over. With a `U32`
+//! prefix, the default, the encoding matches Borsh's `Vec<T>` and `String`.
+//!
+//! [`HeaderList`] and [`HeaderListMut`] cover the common layout of a fixed
+//! [`ZeroCopy`] header followed by a list. When the list needs more room than
+//! the account data has, [`HeaderListMut::reserve`] resizes the account.
+
+use {
+    crate::{
+        layout::{ZeroCopy, ZeroCopyError},
+        unaligned::{U16, U32, U64},
+    },
+    core::{
+        marker::PhantomData,
+        mem::size_of,
+        ops::{Deref, DerefMut},
+        slice::{from_raw_parts, from_raw_parts_mut},
+        str::{from_utf8, from_utf8_unchecked, from_utf8_unchecked_mut},
+    },
+};
+#[cfg(feature = "account-view")]
+use {solana_account_view::AccountView, solana_program_error::ProgramError};
+
+/// A length prefix of a dynamic-length field.
+pub trait Length: ZeroCopy + Copy {
+    /// Returns the length as a `usize`, or `None` if it does not fit.
+    fn to_usize(self) -> Option<usize>;
+
+    /// Returns `len` as a length prefix, or `None` if it does not fit.
+    fn from_usize(len: usize) -> Option<Self>;
+}
+
+impl Length for u8 {
+    fn to_usize(self) -> Option<usize> {
+        Some(usize::from(self))
+    }
+
+    fn from_usize(len: usize) -> Option<Self> {
+        u8::try_from(len).ok()
+    }
+}
+
+macro_rules! impl_length {
+    ( $($type:ty => $int:ty),+ $(,)? ) => {
+        $(
+            impl Length for $type {
+                fn to_usize(self) -> Option<usize> {
+                    usize::try_from(<$int>::from(self)).ok()
+                }
+
+                fn from_usize(len: usize) -> Option<Self> {
+                    <$int>::try_from(len).ok().map(Self::from)
+                }
+            }
+        )+
+    };
+}
+
+impl_length!(U16 => u16, U32 => u32, U64 => u64);
+
+/// Returns the number of bytes of a list of `len` items, including the
+/// length prefix.
+#[inline(always)]
+fn list_len<T, L>(len: usize) -> Option<usize> {
+    len.checked_mul(size_of::<T>())?.checked_add(size_of::<L>())
+}
+
+/// Returns the number of items that fit in `bytes` after the length prefix.
+#[inline(always)]
+fn list_capacity<T, L>(bytes: usize) -> usize {
+    bytes
+        .saturating_sub(size_of::<L>())
+        .checked_div(size_of::<T>())
+        .unwrap_or(usize::MAX)
+}
+
+/// Reads the length prefix of `bytes` and checks that the items fit.
+#[inline(always)]
+fn read_len<T, L: Length>(bytes: &[u8]) -> Result<usize, ZeroCopyError> {
+    let len = L::from_bytes(bytes)?
+        .to_usize()
+        .ok_or(ZeroCopyError::InvalidLength)?;
+    match list_len::<T, L>(len) {
+        Some(byte_len) if byte_len <= bytes.len() => Ok(len),
+        _ => Err(ZeroCopyError::InvalidLength),
+    }
+}
+
+/// A length-prefixed list of items.
+pub struct List<'a, T, L = U32> {
+    items: &'a [T],
+    length: PhantomData<L>,
+}
+
+impl<'a, T: ZeroCopy, L: Length> List<'a, T, L> {
+    /// Reads a list from the start of `bytes`, returning it with the bytes
+    /// that follow it.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<(Self, &'a [u8]), ZeroCopyError> {
+        let len = read_len::<T, L>(bytes)?;
+        let (list, rest) = bytes.split_at(list_len::<T, L>(len).unwrap_or_default());
+        // SAFETY: `list` holds the length prefix followed by `len` items, and
+        // `T` has an alignment of `1` and is valid for any bit pattern.
+        let items = unsafe { from_raw_parts(list.as_ptr().add(size_of::<L>()) as *const T, len) };
+        Ok((
+            Self {
+                items,
+                length: PhantomData,
+            },
+            rest,
+        ))
+    }
+
+    /// Returns the number of bytes of the list, including the length prefix.
+    pub fn byte_len(&self) -> usize {
+        list_len::<T, L>(self.items.len()).unwrap_or_default()
+    }
+
+    /// Returns the items of the list.
+    pub fn as_slice(&self) -> &'a [T] {
+        self.items
+    }
+}
+
+impl<T, L> Deref for List<'_, T, L> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        self.items
+    }
+}
+
+/// A length-prefixed list of items that can grow in place.
+///
+/// The list can hold as many items as fit in the byte slice it was created
+/// over.
+pub struct ListMut<'a, T, L = U32> {
+    bytes: &'a mut [u8],
+    item: PhantomData<T>,
+    length: PhantomData<L>,
+}
+
+impl<'a, T: ZeroCopy, L: Length> ListMut<'a, T, L> {
+    /// Creates a view over a list at the start of `bytes`, which can grow into
+    /// the rest of `bytes`.
+    pub fn from_bytes_mut(bytes: &'a mut [u8]) -> Result<Self, ZeroCopyError> {
+        read_len::<T, L>(bytes)?;
+        Ok(Self {
+            bytes,
+            item: PhantomData,
+            length: PhantomData,
+        })
+    }
+
+    /// Writes an empty list at the start of `bytes` and creates a view over
+    /// it.
+    pub fn init_bytes(bytes: &'a mut [u8]) -> Result<Self, ZeroCopyError> {
+        let len = L::from_usize(0).ok_or(ZeroCopyError::InvalidLength)?;
+        *L::from_bytes_mut(bytes)? = len;
+        Self::from_bytes_mut(bytes)
+    }
+
+    /// Returns the number of bytes needed for a list of `len` items,
+    /// including the length prefix.
+    pub fn required_len(len: usize) -> Option<usize> {
+        list_len::<T, L>(len)
+    }
+
+    /// Returns the number of items in the list.
+    pub fn len(&self) -> usize {
+        // The length was validated when the view was created, and only the
+        // view changes it.
+        L::from_bytes(self.bytes)
+            .ok()
+            .and_then(|len| len.to_usize())
+            .unwrap_or_default()
+    }
+
+    /// Returns `true` if the list has no items.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of items the list can hold.
+    pub fn capacity(&self) -> usize {
+        list_capacity::<T, L>(self.bytes.len())
+    }
+
+    /// Returns the items of the list.
+    pub fn as_slice(&self) -> &[T] {
+        // SAFETY: The list holds `len` items after the length prefix.
+        unsafe {
+            from_raw_parts(
+                self.bytes.as_ptr().add(size_of::<L>()) as *const T,
+                self.len(),
+            )
+        }
+    }
+
+    /// Returns the items of the list mutably.
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        let len = self.len();
+        // SAFETY: The list holds `len` items after the length prefix.
+        unsafe { from_raw_parts_mut(self.items_ptr(), len) }
+    }
+
+    /// Appends an item, failing if the list is full.
+    pub fn push(&mut self, item: T) -> Result<(), ZeroCopyError> {
+        let len = self.len();
+        self.set_len(len.saturating_add(1))?;
+        // SAFETY: The list now holds `len + 1` items.
+        unsafe { self.items_ptr().add(len).write(item) };
+        Ok(())
+    }
+
+    /// Appends a copy of each item, failing without changes if they do not
+    /// fit.
+    pub fn extend_from_slice(&mut self, items: &[T]) -> Result<(), ZeroCopyError>
+    where
+        T: Copy,
+    {
+        let len = self.len();
+        self.set_len(len.saturating_add(items.len()))?;
+        self.as_mut_slice()[len..].copy_from_slice(items);
+        Ok(())
+    }
+
+    /// Removes the last item and returns it, or `None` if the list is empty.
+    pub fn pop(&mut self) -> Option<T>
+    where
+        T: Copy,
+    {
+        let item = *self.as_slice().last()?;
+        self.truncate(self.len().saturating_sub(1));
+        Some(item)
+    }
+
+    /// Shortens the list to `len` items, keeping the first ones.
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len() {
+            // A shorter length always fits.
+            let _ = self.set_len(len);
+        }
+    }
+
+    /// Removes all items.
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    fn set_len(&mut self, len: usize) -> Result<(), ZeroCopyError> {
+        if len > self.capacity() {
+            return Err(ZeroCopyError::CapacityExceeded);
+        }
+        let len = L::from_usize(len).ok_or(ZeroCopyError::CapacityExceeded)?;
+        *L::from_bytes_mut(self.bytes)? = len;
+        Ok(())
+    }
+
+    fn items_ptr(&mut self) -> *mut T {
+        // SAFETY: The length prefix fits in `bytes`.
+        unsafe { self.bytes.as_mut_ptr().add(size_of::<L>()) as *mut T }
+    }
+}
+
+impl<T: ZeroCopy, L: Length> Deref for ListMut<'_, T, L> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+
+impl<T: ZeroCopy, L: Length> DerefMut for ListMut<'_, T, L> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.as_mut_slice()
+    }
+}
+
+/// A length-prefixed UTF-8 string.
+pub struct Str<'a, L = U32> {
+    value: &'a str,
+    length: PhantomData<L>,
+}
+
+impl<'a, L: Length> Str<'a, L> {
+    /// Reads a string from the start of `bytes`, returning it with the bytes
+    /// that follow it.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<(Self, &'a [u8]), ZeroCopyError> {
+        let (list, rest) = List::<u8, L>::from_bytes(bytes)?;
+        let value = from_utf8(list.as_slice()).map_err(|_| ZeroCopyError::InvalidUtf8)?;
+        Ok((
+            Self {
+                value,
+                length: PhantomData,
+            },
+            rest,
+        ))
+    }
+
+    /// Returns the number of bytes of the string, including the length
+    /// prefix.
+    pub fn byte_len(&self) -> usize {
+        list_len::<u8, L>(self.value.len()).unwrap_or_default()
+    }
+
+    /// Returns the string.
+    pub fn as_str(&self) -> &'a str {
+        self.value
+    }
+}
+
+impl<L> Deref for Str<'_, L> {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+/// A length-prefixed UTF-8 string that can be changed in place.
+///
+/// The string can hold as many bytes as fit in the byte slice it was created
+/// over.
+pub struct StrMut<'a, L = U32> {
+    bytes: ListMut<'a, u8, L>,
+}
+
+impl<'a, L: Length> StrMut<'a, L> {
+    /// Creates a view over a string at the start of `bytes`, which can grow
+    /// into the rest of `bytes`.
+    pub fn from_bytes_mut(bytes: &'a mut [u8]) -> Result<Self, ZeroCopyError> {
+        let bytes = ListMut::from_bytes_mut(bytes)?;
+        from_utf8(bytes.as_slice()).map_err(|_| ZeroCopyError::InvalidUtf8)?;
+        Ok(Self { bytes })
+    }
+
+    /// Writes an empty string at the start of `bytes` and creates a view over
+    /// it.
+    pub fn init_bytes(bytes: &'a mut [u8]) -> Result<Self, ZeroCopyError> {
+        Ok(Self {
+            bytes: ListMut::init_bytes(bytes)?,
+        })
+    }
+
+    /// Returns the number of bytes the string can hold.
+    pub fn capacity(&self) -> usize {
+        self.bytes.capacity()
+    }
+
+    /// Returns the string.
+    pub fn as_str(&self) -> &str {
+        // SAFETY: The bytes were checked to be UTF-8, and are only changed
+        // by whole strings.
+        unsafe { from_utf8_unchecked(self.bytes.as_slice()) }
+    }
+
+    /// Returns the string mutably.
+    pub fn as_mut_str(&mut self) -> &mut str {
+        // SAFETY: The bytes were checked to be UTF-8, and are only changed
+        // by whole strings.
+        unsafe { from_utf8_unchecked_mut(self.bytes.as_mut_slice()) }
+    }
+
+    /// Appends `value`, failing without changes if it does not fit.
+    pub fn push_str(&mut self, value: &str) -> Result<(), ZeroCopyError> {
+        self.bytes.extend_from_slice(value.as_bytes())
+    }
+
+    /// Replaces the string with `value`, failing without changes if it does
+    /// not fit.
+    pub fn set(&mut self, value: &str) -> Result<(), ZeroCopyError> {
+        if value.len() > self.capacity() {
+            return Err(ZeroCopyError::CapacityExceeded);
+        }
+        self.bytes.clear();
+        self.push_str(value)
+    }
+}
+
+impl<L: Length> Deref for StrMut<'_, L> {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+/// A fixed header followed by a length-prefixed list.
+pub struct HeaderList<'a, H, T, L = U32> {
+    /// The header, after its discriminator.
+    pub header: &'a H,
+    /// The list that follows the header.
+    pub list: List<'a, T, L>,
+}
+
+impl<'a, H: ZeroCopy, T: ZeroCopy, L: Length> HeaderList<'a, H, T, L> {
+    /// Reads the header, checking its discriminator, and the list after it.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, ZeroCopyError> {
+        let header = H::from_bytes(bytes)?;
+        let (list, _) = List::from_bytes(&bytes[H::LEN..])?;
+        Ok(Self { header, list })
+    }
+}
+
+/// A fixed header followed by a length-prefixed list that can grow in place.
+pub struct HeaderListMut<'a, H, T, L = U32> {
+    /// The header, after its discriminator.
+    pub header: &'a mut H,
+    /// The list that follows the header, which can grow to the end of the
+    /// data.
+    pub list: ListMut<'a, T, L>,
+}
+
+impl<'a, H: ZeroCopy, T: ZeroCopy, L: Length> HeaderListMut<'a, H, T, L> {
+    /// Reads the header, checking its discriminator, and the list after it.
+    pub fn from_bytes_mut(bytes: &'a mut [u8]) -> Result<Self, ZeroCopyError> {
+        H::from_bytes_mut(bytes)?;
+        let (header, list) = bytes.split_at_mut(H::LEN);
+        Ok(Self {
+            header: H::from_bytes_mut(header)?,
+            list: ListMut::from_bytes_mut(list)?,
+        })
+    }
+
+    /// Writes the discriminator of the header and an empty list, leaving the
+    /// bytes of the header unchanged.
+    pub fn init_bytes(bytes: &'a mut [u8]) -> Result<Self, ZeroCopyError> {
+        H::init_bytes(bytes)?;
+        let (header, list) = bytes.split_at_mut(H::LEN);
+        Ok(Self {
+            header: H::from_bytes_mut(header)?,
+            list: ListMut::init_bytes(list)?,
+        })
+    }
+
+    /// Returns the number of bytes needed for the header and a list of `len`
+    /// items.
+    pub fn required_len(len: usize) -> Option<usize> {
+        ListMut::<T, L>::required_len(len)?.checked_add(H::LEN)
+    }
+
+    /// Resizes the data of `account` to fit `additional` more items in the
+    /// list, if it does not already.
+    ///
+    /// The account data can grow by at most
+    /// [`MAX_PERMITTED_DATA_INCREASE`](solana_account_view::MAX_PERMITTED_DATA_INCREASE)
+    /// bytes during an instruction, and must hold enough lamports to stay rent
+    /// exempt at its new size.
+    #[cfg(feature = "account-view")]
+    pub fn reserve(account: &mut AccountView, additional: usize) -> Result<(), ProgramError> {
+        let required_len = {
+            let data = account.try_borrow()?;
+            let list = HeaderList::<H, T, L>::from_bytes(&data)?.list;
+            list.len()
+                .checked_add(additional)
+                .and_then(Self::required_len)
+                .ok_or(ProgramError::InvalidRealloc)?
+        };
+        if required_len > account.data_len() {
+            account.resize(required_len)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod tests {
+    use {
+        super::*,
+        crate::{unaligned::Bool, ZeroCopy},
+    };
+
+    #[derive(ZeroCopy)]
+    #[zero_copy(discriminator = [9])]
+    #[repr(C)]
+    struct Registry {
+        open: Bool,
+    }
+
+    #[test]
+    fn test_list() {
+        let mut data = [0u8; 16];
+        let mut list = ListMut::<U16, u8>::init_bytes(&mut data).unwrap();
+        assert!(list.is_empty());
+        assert_eq!(list.capacity(), 7);
+
+        list.push(U16::from(1)).unwrap();
+        list.extend_from_slice(&[U16::from(2), U16::from(3)])
+            .unwrap();
+        list[0] = U16::from(4);
+        assert_eq!(
+            list.extend_from_slice(&[U16::from(0); 5]),
+            Err(ZeroCopyError::CapacityExceeded)
+        );
+        assert_eq!(list.pop(), Some(U16::from(3)));
+        assert_eq!(list.len(), 2);
+        assert_eq!(data[..5], [2, 4, 0, 2, 0]);
+
+        let (list, rest) = List::<U16, u8>::from_bytes(&data).unwrap();
+        assert_eq!(*list, [U16::from(4), U16::from(2)]);
+        assert_eq!(list.byte_len(), 5);
+        assert_eq!(rest.len(), 11);
+
+        // The length must fit in the data.
+        data[0] = 8;
+        assert_eq!(
+            List::<U16, u8>::from_bytes(&data).map(|_| ()),
+            Err(ZeroCopyError::InvalidLength)
+        );
+        assert_eq!(
+            ListMut::<U16, u8>::from_bytes_mut(&mut data).map(|_| ()),
+            Err(ZeroCopyError::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn test_str() {
+        let mut data = [0u8; 12];
+        let mut value = StrMut::<U32>::init_bytes(&mut data).unwrap();
+        value.set("solana").unwrap();
+        value.push_str("!").unwrap();
+        assert_eq!(
+            value.push_str("toolong"),
+            Err(ZeroCopyError::CapacityExceeded)
+        );
+        assert_eq!(
+            value.set("much too long"),
+            Err(ZeroCopyError::CapacityExceeded)
+        );
+        value.as_mut_str().make_ascii_uppercase();
+        assert_eq!(&*value, "SOLANA!");
+
+        // Borsh encodes strings with the same `u32` length prefix.
+        assert_eq!(data[..11], *b"\x07\0\0\0SOLANA!");
+        let (value, _) = Str::<U32>::from_bytes(&data).unwrap();
+        assert_eq!(value.as_str(), "SOLANA!");
+        assert_eq!(value.byte_len(), 11);
+
+        data[4] = 0xff;
+        assert_eq!(
+            Str::<U32>::from_bytes(&data).map(|_| ()),
+            Err(ZeroCopyError::InvalidUtf8)
+        );
+        assert_eq!(
+            StrMut::<U32>::from_bytes_mut(&mut data).map(|_| ()),
+            Err(ZeroCopyError::InvalidUtf8)
+        );
+    }
+
+    #[test]
+    fn test_header_list() {
+        let mut data = [0u8; 15];
+        let registry = HeaderListMut::<Registry, U32, u8>::init_bytes(&mut data).unwrap();
+        registry.header.open = Bool::from(true);
+        let mut list = registry.list;
+        list.push(U32::from(7)).unwrap();
+        assert_eq!(list.capacity(), 3);
+        assert_eq!(
+            HeaderListMut::<Registry, U32, u8>::required_len(3),
+            Some(15)
+        );
+
+        let registry = HeaderList::<Registry, U32, u8>::from_bytes(&data).unwrap();
+        assert!(bool::from(registry.header.open));
+        assert_eq!(*registry.list, [U32::from(7)]);
+
+        data[0] = 0;
+        assert_eq!(
+            HeaderList::<Registry, U32, u8>::from_bytes(&data).map(|_| ()),
+            Err(ZeroCopyError::InvalidDiscriminator)
+        );
+    }
+
+    #[cfg(feature = "account-view")]
+    #[test]
+    fn test_reserve() {
+        use solana_account_view::{RuntimeAccount, MAX_PERMITTED_DATA_INCREASE, NOT_BORROWED};
+
+        // 8-byte aligned account data followed by the space reserved for
+        // resizing.
+        let mut data = [0u64;
+            size_of::<RuntimeAccount>() / size_of::<u64>()
+                + 1
+                + MAX_PERMITTED_DATA_INCREASE / size_of::<u64>()];
+        let account = data.as_mut_ptr() as *mut RuntimeAccount;
+        unsafe {
+            (*account).borrow_state = NOT_BORROWED;
+            (*account).data_len = 6;
+        }
+        let mut account = unsafe { AccountView::new_unchecked(account) };
+        HeaderListMut::<Registry, U64>::init_bytes(&mut account.try_borrow_mut().unwrap()).unwrap();
+
+        HeaderListMut::<Registry, U64>::reserve(&mut account, 2).unwrap();
+        assert_eq!(account.data_len(), 22);
+        {
+            let mut data = account.try_borrow_mut().unwrap();
+            let mut registry = HeaderListMut::<Registry, U64>::from_bytes_mut(&mut data).unwrap();
+            registry.list.push(U64::from(1)).unwrap();
+            registry.list.push(U64::from(2)).unwrap();
+            assert_eq!(
+                registry.list.push(U64::from(3)),
+                Err(ZeroCopyError::CapacityExceeded)
+            );
+        }
+
+        // Reserving room that already exists does not resize.
+        HeaderListMut::<Registry, U64>::reserve(&mut account, 0).unwrap();
+        assert_eq!(account.data_len(), 22);
+
+        // Growth is limited by the account resize limit.
+        assert_eq!(
+            HeaderListMut::<Registry, U64>::reserve(
+                &mut account,
+                MAX_PERMITTED_DATA_INCREASE / size_of::<U64>()
+            ),
+            Err(ProgramError::InvalidRealloc)
+        );
+        assert_eq!(
+            HeaderListMut::<Registry, U64>::reserve(&mut account, usize::MAX),
+            Err(ProgramError::InvalidRealloc)
+        );
+    }
+}
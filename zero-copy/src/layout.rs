@@ -0,0 +1,259 @@
+//! Typed views over zero-copy account data.
+//!
+//! A type implementing [`ZeroCopy`] can be read from and written to a byte
+//! slice in place, without copying or aligning it. Account layouts usually
+//! implement it with the `ZeroCopy` derive macro, which checks the layout at
+//! compile time, and can prefix the data with a discriminator that the
+//! loaders verify.
+
+use {
+    crate::unaligned::{Bool, I16, I64, U128, U16, U32, U64},
+    core::{fmt, mem::size_of},
+};
+#[cfg(feature = "account-view")]
+use {
+    solana_account_view::{AccountView, Ref, RefMut},
+    solana_program_error::ProgramError,
+};
+
+/// Errors that can occur when loading or modifying zero-copy data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ZeroCopyError {
+    /// The data is shorter than the discriminator and the value.
+    InvalidLength,
+    /// The data does not start with the expected discriminator.
+    InvalidDiscriminator,
+    /// A string is not valid UTF-8.
+    InvalidUtf8,
+    /// A dynamic-length field does not fit in the available data.
+    CapacityExceeded,
+}
+
+impl core::error::Error for ZeroCopyError {}
+
+impl fmt::Display for ZeroCopyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ZeroCopyError::InvalidLength => f.write_str("data is too short for the layout"),
+            ZeroCopyError::InvalidDiscriminator => f.write_str("discriminator does not match"),
+            ZeroCopyError::InvalidUtf8 => f.write_str("string is not valid UTF-8"),
+            ZeroCopyError::CapacityExceeded => f.write_str("data is too short for the new length"),
+        }
+    }
+}
+
+#[cfg(feature = "account-view")]
+impl From<ZeroCopyError> for ProgramError {
+    fn from(_: ZeroCopyError) -> Self {
+        ProgramError::InvalidAccountData
+    }
+}
+
+/// A type that can be viewed in place over a byte slice.
+///
+/// Data is laid out as [`DISCRIMINATOR`](Self::DISCRIMINATOR) followed by
+/// the value. Any bytes after the value are ignored by the loaders.
+///
+/// # Safety
+///
+/// Implementors must have an alignment of `1`, no padding, no interior
+/// mutability, and every bit pattern must be a valid value. Prefer the
+/// `ZeroCopy` derive macro, which checks these at compile time.
+pub unsafe trait ZeroCopy: Sized {
+    /// Bytes that precede the value.
+    const DISCRIMINATOR: &'static [u8] = &[];
+
+    /// Length of the discriminator and the value.
+    #[allow(clippy::arithmetic_side_effects)]
+    const LEN: usize = Self::DISCRIMINATOR.len() + size_of::<Self>();
+
+    /// Returns a reference to the value after checking the discriminator.
+    #[inline]
+    fn from_bytes(bytes: &[u8]) -> Result<&Self, ZeroCopyError> {
+        let data = check_bytes::<Self>(bytes)?;
+        // SAFETY: `data` holds `size_of::<Self>()` bytes, and `Self` has an
+        // alignment of `1` and is valid for any bit pattern.
+        Ok(unsafe { &*(data.as_ptr() as *const Self) })
+    }
+
+    /// Returns a mutable reference to the value after checking the
+    /// discriminator.
+    #[inline]
+    fn from_bytes_mut(bytes: &mut [u8]) -> Result<&mut Self, ZeroCopyError> {
+        check_bytes::<Self>(bytes)?;
+        // SAFETY: `bytes` holds the discriminator followed by
+        // `size_of::<Self>()` bytes, and `Self` has an alignment of `1` and is
+        // valid for any bit pattern.
+        Ok(unsafe { &mut *(bytes.as_mut_ptr().add(Self::DISCRIMINATOR.len()) as *mut Self) })
+    }
+
+    /// Writes the discriminator and returns a mutable reference to the
+    /// value, leaving its bytes unchanged.
+    #[inline]
+    fn init_bytes(bytes: &mut [u8]) -> Result<&mut Self, ZeroCopyError> {
+        if bytes.len() < Self::LEN {
+            return Err(ZeroCopyError::InvalidLength);
+        }
+        bytes[..Self::DISCRIMINATOR.len()].copy_from_slice(Self::DISCRIMINATOR);
+        Self::from_bytes_mut(bytes)
+    }
+
+    /// Borrows the data of `account` as the value.
+    #[cfg(feature = "account-view")]
+    #[inline]
+    fn from_account(account: &AccountView) -> Result<Ref<'_, Self>, ProgramError> {
+        Ref::try_map(account.try_borrow()?, Self::from_bytes).map_err(|(_, error)| error.into())
+    }
+
+    /// Mutably borrows the data of `account` as the value.
+    #[cfg(feature = "account-view")]
+    #[inline]
+    fn from_account_mut(account: &mut AccountView) -> Result<RefMut<'_, Self>, ProgramError> {
+        RefMut::try_map(account.try_borrow_mut()?, Self::from_bytes_mut)
+            .map_err(|(_, error)| error.into())
+    }
+}
+
+/// Checks the length and discriminator of `bytes`, returning the bytes of
+/// the value.
+#[inline(always)]
+fn check_bytes<T: ZeroCopy>(bytes: &[u8]) -> Result<&[u8], ZeroCopyError> {
+    if bytes.len() < T::LEN {
+        return Err(ZeroCopyError::InvalidLength);
+    }
+    let (discriminator, data) = bytes.split_at(T::DISCRIMINATOR.len());
+    if discriminator != T::DISCRIMINATOR {
+        return Err(ZeroCopyError::InvalidDiscriminator);
+    }
+    Ok(&data[..size_of::<T>()])
+}
+
+macro_rules! impl_zero_copy {
+    ( $($type:ty),+ $(,)? ) => {
+        $(
+            const _: () = assert!(core::mem::align_of::<$type>() == 1);
+
+            unsafe impl ZeroCopy for $type {}
+        )+
+    };
+}
+
+impl_zero_copy!(u8, i8, Bool, U16, I16, U32, U64, I64, U128);
+
+unsafe impl<T: ZeroCopy, const N: usize> ZeroCopy for [T; N] {}
+
+#[cfg(feature = "address")]
+impl_zero_copy!(solana_address::Address);
+
+/// `MaybeNull<T>` is a transparent wrapper, so it is zero-copy when `T` is.
+#[cfg(feature = "nullable")]
+unsafe impl<T: ZeroCopy + solana_nullable::Nullable> ZeroCopy for solana_nullable::MaybeNull<T> {}
+
+#[cfg(all(test, feature = "derive"))]
+mod tests {
+    use {super::*, crate::ZeroCopy};
+
+    #[derive(ZeroCopy, Debug, PartialEq)]
+    #[zero_copy(discriminator = [7, 8])]
+    #[repr(C)]
+    struct Counter {
+        flag: Bool,
+        count: U64,
+        history: [U16; 2],
+    }
+
+    #[derive(ZeroCopy)]
+    #[repr(C)]
+    struct Nested(Counter, u8);
+
+    #[test]
+    fn test_from_bytes() {
+        assert_eq!(Counter::LEN, 15);
+        assert!(Nested::DISCRIMINATOR.is_empty());
+        assert_eq!(Nested::LEN, 14);
+
+        let mut data = [7, 8, 1, 42, 0, 0, 0, 0, 0, 0, 0, 3, 0, 4, 0, 99];
+        let counter = Counter::from_bytes(&data).unwrap();
+        assert!(bool::from(counter.flag));
+        assert_eq!(u64::from(counter.count), 42);
+        assert_eq!(u16::from(counter.history[1]), 4);
+        assert_eq!(
+            Counter::from_bytes(&data[..14]),
+            Err(ZeroCopyError::InvalidLength)
+        );
+
+        let counter = Counter::from_bytes_mut(&mut data).unwrap();
+        counter.count = counter.count.saturating_add(1u64);
+        assert_eq!(data[3], 43);
+
+        data[0] = 0;
+        assert_eq!(
+            Counter::from_bytes(&data),
+            Err(ZeroCopyError::InvalidDiscriminator)
+        );
+        let counter = Counter::init_bytes(&mut data).unwrap();
+        assert_eq!(u64::from(counter.count), 43);
+        assert_eq!(data[..2], [7, 8]);
+
+        // The layout starts right after the discriminator, unaligned.
+        let nested = Nested::from_bytes(&data[2..]).unwrap();
+        assert_eq!(u64::from(nested.0.count), 43);
+        assert_eq!(nested.1, 99);
+    }
+
+    #[cfg(feature = "account-view")]
+    #[test]
+    fn test_from_account() {
+        use solana_account_view::{RuntimeAccount, NOT_BORROWED};
+
+        // 8-byte aligned account data followed by the counter.
+        let mut data = [0u64; size_of::<RuntimeAccount>() / size_of::<u64>() + 2];
+        let account = data.as_mut_ptr() as *mut RuntimeAccount;
+        unsafe {
+            (*account).borrow_state = NOT_BORROWED;
+            (*account).data_len = Counter::LEN as u64;
+        }
+        let mut account = unsafe { AccountView::new_unchecked(account) };
+        assert_eq!(
+            Counter::from_account(&account).map(|_| ()),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        let mut counter = RefMut::map(account.try_borrow_mut().unwrap(), |data| {
+            Counter::init_bytes(data).unwrap()
+        });
+        counter.count = U64::from(5);
+        drop(counter);
+
+        {
+            let counter = Counter::from_account(&account).unwrap();
+            assert_eq!(u64::from(counter.count), 5);
+            assert!(account.check_borrow_mut().is_err());
+        }
+        let mut counter = Counter::from_account_mut(&mut account).unwrap();
+        counter.flag = Bool::from(true);
+        drop(counter);
+        assert!(bool::from(Counter::from_account(&account).unwrap().flag));
+    }
+
+    #[cfg(all(feature = "address", feature = "nullable"))]
+    #[test]
+    fn test_address_fields() {
+        use {solana_address::Address, solana_nullable::MaybeNull};
+
+        #[derive(ZeroCopy)]
+        #[repr(C)]
+        struct Mint {
+            authority: MaybeNull<Address>,
+            supply: U64,
+        }
+
+        let mut data = [0; 40];
+        assert_eq!(Mint::from_bytes(&data).unwrap().authority.as_ref(), None);
+        data[..32].copy_from_slice(&[1; 32]);
+        assert_eq!(
+            Mint::from_bytes(&data).unwrap().authority.as_ref(),
+            Some(&Address::new_from_array([1; 32]))
+        );
+    }
+}
@@ -1,12 +1,22 @@
 //! Solana zero-copy types.
 //!
 //! This crate provides unaligned primitive wrappers for use in Solana
-//! zero-copy data structures.
+//! zero-copy data structures, the [`ZeroCopy`] trait to view such
+//! structures in place over account data, and length-prefixed views for
+//! the dynamic-length lists and strings that follow them.
 
 #![no_std]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
 #[cfg(feature = "borsh")]
 extern crate alloc;
+// Lets the derive macro refer to this crate from within it.
+extern crate self as solana_zero_copy;
 
+pub mod dynamic;
+pub mod layout;
 pub mod unaligned;
+
+pub use layout::{ZeroCopy, ZeroCopyError};
+#[cfg(feature = "derive")]
+pub use solana_zero_copy_macro::ZeroCopy;